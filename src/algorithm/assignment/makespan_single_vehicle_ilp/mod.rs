@@ -1,251 +1,241 @@
-use itertools::Itertools;
-
 use algorithm::assignment::greedy_makespan::GreedyMakespan;
 use algorithm::assignment::AssignmentAlgorithm;
-use algorithm::assignment::LPIOError;
-use algorithm::DAT_FILE_NAME;
-use algorithm::RUN_FILE_NAME;
+use algorithm::solver::Comparison;
+use algorithm::solver::Model;
+use algorithm::solver::Solution;
+use algorithm::solver::Solver;
+use algorithm::solver::Variable;
 use fnv::FnvHashMap;
 use simulation::demand::Request;
 use simulation::plan::Plan;
 use simulation::plan::Vertex;
 use simulation::settings::Settings;
-use std::convert::AsRef;
-use std::env::temp_dir;
-use std::fs::create_dir;
-use std::fs::remove_dir_all;
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
-use std::path::PathBuf;
-use std::process::Command;
+use std::f64;
+use std::sync::Arc;
 use std::thread::spawn;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
-const MOD_FILE_PATH: &str = "/home/bram/git/disjoint-path-routing/src/algorithm/assignment/makespan_single_vehicle_ilp/makespan_single_vehicle_ilp.mod";
-const WORKING_DIRECTORY: &str = "makespan_single_vehicle_ilp";
+const TIME_LIMIT: Duration = Duration::from_secs(30);
 
+/// Assigns requests with `GreedyMakespan` and then orders the requests of each robot such that
+/// the distance it travels without a parcel is minimal.
+///
+/// The orders are calculated in parallel, one thread per robot. When the solver doesn't find an
+/// order, for example because a robot has too many requests, the robot serves the nearest
+/// request next.
 pub struct MakespanSingleVehicleILP<'p, 's> {
     plan: &'p (dyn Plan + 'p),
     settings: &'s Settings,
+    solver: Arc<Solver>,
+}
+
+/// The variables of the formulation, indexed by the position of a request in the assigned
+/// requests.
+struct Variables {
+    first: Vec<Variable>,
+    /// `None` for a transition from a request to itself
+    transition: Vec<Vec<Option<Variable>>>,
 }
 
 impl<'p, 's> MakespanSingleVehicleILP<'p, 's> {
-    pub fn new(plan: &'p impl Plan, settings: &'s Settings) -> MakespanSingleVehicleILP<'p, 's> {
-        MakespanSingleVehicleILP { plan, settings }
+    pub fn new(plan: &'p Plan, settings: &'s Settings) -> MakespanSingleVehicleILP<'p, 's> {
+        MakespanSingleVehicleILP {
+            plan,
+            settings,
+            solver: settings.solver.create(TIME_LIMIT),
+        }
     }
+    /// Solves the models with another solver than the one in the settings.
+    pub fn with_solver(mut self, solver: Arc<Solver>) -> MakespanSingleVehicleILP<'p, 's> {
+        self.solver = solver;
+        self
+    }
+    /// Calculates the order in a separate thread, which returns the order and the distance
+    /// travelled between the requests.
     fn calculate_optimal_request_order(
         &self,
-        robot: usize,
         start_vertex: Vertex,
         assigned_requests: Vec<usize>,
         requests: &FnvHashMap<usize, Request>,
-    ) -> JoinHandle<Vec<usize>> {
-        let (model_path, working_directory, data_path, run_path) =
-            MakespanSingleVehicleILP::get_paths(robot);
-        MakespanSingleVehicleILP::create_working_directory(working_directory);
-        let (first_distances, transition_distances, last_distances) =
+    ) -> JoinHandle<(Vec<usize>, u64)> {
+        let (first_distances, transition_distances, _) =
             self.calculate_distances(start_vertex, &assigned_requests, requests);
-        MakespanSingleVehicleILP::write_data_file(
-            data_path.as_path(),
-            &assigned_requests,
-            first_distances,
-            transition_distances,
-            last_distances,
-        );
-        MakespanSingleVehicleILP::write_run_file(
-            run_path.as_path(),
-            model_path.as_path(),
-            data_path.as_path(),
-        );
-        spawn(|| {
-            let output = Command::new("/home/bram/Downloads/amplide.linux64/ampl")
-                .arg(run_path)
-                .output()
-                .unwrap();
-            let (first, transitions, last, _) =
-                MakespanSingleVehicleILP::parse_ampl_output(output.stdout);
-            MakespanSingleVehicleILP::reconstruct_request_order(first, transitions, last)
+        let nearest_order =
+            MakespanSingleVehicleILP::nearest_order(&first_distances, &transition_distances);
+        let (mut model, variables) =
+            MakespanSingleVehicleILP::create_model(&transition_distances);
+        MakespanSingleVehicleILP::set_start(&mut model, &variables, &nearest_order);
+
+        let solver = Arc::clone(&self.solver);
+        spawn(move || {
+            let order = match solver.solve(&model) {
+                Ok(solution) => {
+                    MakespanSingleVehicleILP::reconstruct_request_order(&solution, &variables)
+                }
+                Err(_) => nearest_order,
+            };
+            let distance = order
+                .windows(2)
+                .map(|pair| transition_distances[pair[0]][pair[1]])
+                .sum();
+
+            (
+                order.into_iter().map(|i| assigned_requests[i]).collect(),
+                distance,
+            )
         })
     }
-    fn create_working_directory(directory: PathBuf) {
-        if directory.exists() {
-            remove_dir_all(directory.as_path());
-        }
-        create_dir(directory).unwrap();
-    }
-    fn reconstruct_request_order(
-        first: usize,
-        ones: FnvHashMap<usize, usize>,
-        last: usize,
-    ) -> Vec<usize> {
-        let mut requests = Vec::new();
-        requests.push(first);
-
-        if ones.contains_key(&first) {
-            let mut current = first;
-
-            while current != last {
-                if let Some(request) = ones.get(&current) {
-                    requests.push(*request);
-                    current = *request;
-                    if *request == last {
-                        break;
-                    }
-                } else {
-                    panic!()
-                }
+    /// An open path through the requests that doesn't visit any request twice, of which only the
+    /// transitions between requests are a cost.
+    fn create_model(transition_distances: &Vec<Vec<u64>>) -> (Model, Variables) {
+        let nr_requests = transition_distances.len();
+        let mut model = Model::new();
+
+        let first = (0..nr_requests)
+            .map(|_| model.add_binary(0_f64))
+            .collect::<Vec<_>>();
+        let transition = (0..nr_requests)
+            .map(|i| {
+                (0..nr_requests)
+                    .map(|j| {
+                        if i == j {
+                            None
+                        } else {
+                            Some(model.add_binary(transition_distances[i][j] as f64))
+                        }
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+        let last = (0..nr_requests)
+            .map(|_| model.add_binary(0_f64))
+            .collect::<Vec<_>>();
+        let number = (0..nr_requests)
+            .map(|_| model.add_continuous(0_f64, f64::INFINITY, 0_f64))
+            .collect::<Vec<_>>();
+
+        for i in 0..nr_requests {
+            let mut incoming = vec![(first[i], 1_f64)];
+            incoming.extend(
+                (0..nr_requests)
+                    .filter_map(|j| transition[j][i])
+                    .map(|t| (t, 1_f64)),
+            );
+            let mut outgoing = vec![(last[i], 1_f64)];
+            outgoing.extend(
+                (0..nr_requests)
+                    .filter_map(|j| transition[i][j])
+                    .map(|t| (t, 1_f64)),
+            );
+
+            model.add_constraint(incoming, Comparison::Equal, 1_f64);
+            model.add_constraint(outgoing, Comparison::Equal, 1_f64);
+
+            for j in (0..nr_requests).filter(|&j| j != i) {
+                // Following a transition increases the number by at least one
+                model.add_constraint(
+                    vec![
+                        (number[j], 1_f64),
+                        (number[i], -1_f64),
+                        (transition[i][j].unwrap(), -(nr_requests as f64 + 1_f64)),
+                    ],
+                    Comparison::GreaterEqual,
+                    -(nr_requests as f64),
+                );
             }
         }
+        model.add_constraint(
+            first.iter().map(|&variable| (variable, 1_f64)).collect(),
+            Comparison::Equal,
+            1_f64,
+        );
 
-        requests
-    }
-    fn parse_ampl_output(output: Vec<u8>) -> (usize, FnvHashMap<usize, usize>, usize, u64) {
-        let output = String::from_utf8(output).unwrap();
-        let segments = output.lines().collect::<Vec<_>>();
-
-        let objective = segments
-            .iter()
-            .skip_while(|line| !line.contains("objective"))
-            .next()
-            .unwrap()
-            .split_whitespace()
-            .last()
-            .unwrap()
-            .parse::<u64>()
-            .unwrap();
-
-        let mut segments = segments.split(|&line| line == ";");
-        let mut get_lines = |pattern: &str| {
-            segments
-                .next()
-                .unwrap()
-                .into_iter()
-                .skip_while(|&&line| !line.starts_with(pattern))
-                .skip(1)
-                .map(|line| *line)
-                .collect::<Vec<_>>()
-        };
-        let first = get_lines("First_Request");
-        let transitions = get_lines("Transition");
-        let last = get_lines("Last_Request");
-
-        let first = first[0]
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-        let transitions = transitions
-            .into_iter()
-            .map(
-                |line| match line.split_whitespace().collect_vec().as_slice() {
-                    [i, j, "1"] => {
-                        let from = i.parse::<usize>().unwrap();
-                        let to = j.parse::<usize>().unwrap();
-                        (from, to)
-                    }
-                    _ => panic!(),
-                },
-            )
-            .collect();
-        let last = last[0]
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .parse::<usize>()
-            .unwrap();
-
-        (first, transitions, last, objective)
+        (model, Variables { first, transition })
     }
-    fn write_data_file(
-        path: impl AsRef<Path>,
-        assigned_requests: &Vec<usize>,
-        first_distances: Vec<(usize, u64)>,
-        transition_distances: Vec<(usize, usize, u64)>,
-        last_distances: Vec<(usize, u64)>,
-    ) -> Result<(), LPIOError> {
-        let mut file = File::create(path).unwrap();
-
-        file.write("set REQUESTS :=\n".as_ref());
-        for request in assigned_requests {
-            file.write(format!("  {},\n", request).as_ref());
+    fn set_start(model: &mut Model, variables: &Variables, order: &Vec<usize>) {
+        for &variable in variables.first.iter() {
+            model.set_start(variable, 0_f64);
         }
-        file.write(";\n".as_ref());
-
-        file.write("param start_cost default 0 :=\n".as_ref());
-        for (first, distance) in first_distances {
-            if distance != 0 {
-                file.write(format!("{}\t{}\n", first, distance).as_ref());
-            }
+        for &variable in variables.transition.iter().flatten().flatten() {
+            model.set_start(variable, 0_f64);
         }
-        file.write(";\n".as_ref());
 
-        file.write("param transition_cost default 0 :=\n".as_ref());
-        for (from, to, distance) in transition_distances {
-            if distance != 0 {
-                file.write(format!("{}\t{}\t{}\n", from, to, distance).as_ref());
-            }
+        if let Some(&first) = order.first() {
+            model.set_start(variables.first[first], 1_f64);
+        }
+        for pair in order.windows(2) {
+            model.set_start(variables.transition[pair[0]][pair[1]].unwrap(), 1_f64);
         }
-        file.write(";\n".as_ref());
+    }
+    fn reconstruct_request_order(solution: &Solution, variables: &Variables) -> Vec<usize> {
+        let mut order = Vec::new();
 
-        file.write("param end_cost default 0 :=\n".as_ref());
-        for (last, distance) in last_distances {
-            if distance != 0 {
-                file.write(format!("{}\t{}\n", last, distance).as_ref());
-            }
+        let mut current = variables
+            .first
+            .iter()
+            .position(|&variable| solution.is_set(variable));
+        while let Some(i) = current {
+            order.push(i);
+            current = variables.transition[i]
+                .iter()
+                .position(|&variable| variable.map_or(false, |variable| solution.is_set(variable)));
         }
-        file.write(";\n".as_ref());
 
-        Ok(())
+        order
     }
-    fn write_run_file(
-        path: impl AsRef<Path>,
-        model_path: impl AsRef<Path>,
-        data_path: impl AsRef<Path>,
-    ) -> Result<(), LPIOError> {
-        let mut file = File::create(path).unwrap();
-
-        writeln!(file, "model '{}';", model_path.as_ref().to_str().unwrap());
-        writeln!(file, "data '{}';", data_path.as_ref().to_str().unwrap());
-        file.write("option solver '/home/bram/Downloads/amplide.linux64/gurobi';\n".as_ref());
-        writeln!(file, "option gurobi_options 'timelim {} bestbound 1';", 30);
-        file.write("solve;\n".as_ref());
-        file.write("option omit_zero_rows 1;\n".as_ref());
-        file.write("option display_1col 1000000;\n".as_ref());
-        file.write("display First_Request;\n".as_ref());
-        file.write("display Transition;\n".as_ref());
-        file.write("display Last_Request;\n".as_ref());
-
-        Ok(())
+    /// Starting with the request closest to the start vertex, always serves the closest request
+    /// next.
+    fn nearest_order(
+        first_distances: &Vec<u64>,
+        transition_distances: &Vec<Vec<u64>>,
+    ) -> Vec<usize> {
+        let nr_requests = first_distances.len();
+        let mut order = Vec::with_capacity(nr_requests);
+        let mut served = vec![false; nr_requests];
+
+        let mut distances = first_distances;
+        while order.len() < nr_requests {
+            let next = (0..nr_requests)
+                .filter(|&i| !served[i])
+                .min_by_key(|&i| (distances[i], i))
+                .unwrap();
+            order.push(next);
+            served[next] = true;
+            distances = &transition_distances[next];
+        }
+
+        order
     }
+    /// Distances from the start vertex to each request, between two requests and from the last
+    /// request to the end, indexed by the position of the request in the assigned requests.
     fn calculate_distances(
         &self,
         start_vertex: Vertex,
         assigned_requests: &Vec<usize>,
         requests: &FnvHashMap<usize, Request>,
-    ) -> (
-        Vec<(usize, u64)>,
-        Vec<(usize, usize, u64)>,
-        Vec<(usize, u64)>,
-    ) {
+    ) -> (Vec<u64>, Vec<Vec<u64>>, Vec<u64>) {
         let requests = assigned_requests
-            .into_iter()
-            .map(|request_id| (*request_id, requests.get(&request_id).unwrap()))
+            .iter()
+            .map(|request_id| requests.get(&request_id).unwrap())
             .collect::<Vec<_>>();
 
         let first_distances = requests
             .iter()
-            .map(|&(id, request)| (id as usize, start_vertex.distance(request.from)))
+            .map(|request| self.plan.path_length(start_vertex, request.from))
             .collect();
         let transition_distances = requests
             .iter()
-            .cartesian_product(requests.iter())
-            .map(|(&(i, request_i), &(j, request_j))| (i, j, request_i.to.distance(request_j.from)))
+            .map(|request_i| {
+                requests
+                    .iter()
+                    .map(|request_j| self.plan.path_length(request_i.to, request_j.from))
+                    .collect()
+            })
             .collect();
         let last_distances = requests
             .iter()
-            .map(|&(id, request)| (id, self.calculate_last_distance(start_vertex, request.to)))
+            .map(|request| self.calculate_last_distance(start_vertex, request.to))
             .collect();
 
         (first_distances, transition_distances, last_distances)
@@ -253,23 +243,12 @@ impl<'p, 's> MakespanSingleVehicleILP<'p, 's> {
     fn calculate_last_distance(&self, _start_vertex: Vertex, _last_vertex: Vertex) -> u64 {
         0
     }
-    fn get_paths(robot: usize) -> (PathBuf, PathBuf, PathBuf, PathBuf) {
-        let model_path = <&str as AsRef<Path>>::as_ref(&MOD_FILE_PATH).to_path_buf();
-
-        let mut working_directory = String::from(WORKING_DIRECTORY);
-        working_directory.push('_');
-        working_directory.push_str(&robot.to_string());
-        let working_directory = temp_dir().join(working_directory);
-        let dat_path = working_directory.join(DAT_FILE_NAME);
-        let run_path = working_directory.join(RUN_FILE_NAME);
-
-        (model_path, working_directory, dat_path, run_path)
-    }
-    pub fn calculate_assignment_quality(
-        &mut self,
+    /// Orders the requests that `GreedyMakespan` assigns to each robot.
+    fn calculate_orders(
+        &self,
         requests: &FnvHashMap<usize, Request>,
         availability: &Vec<(usize, Vertex)>,
-    ) -> u64 {
+    ) -> Vec<(Vec<usize>, u64)> {
         let mut makespan_assignment = GreedyMakespan::new(self.plan, self.settings);
         let assignment = makespan_assignment.calculate_assignment(requests, availability);
 
@@ -277,44 +256,34 @@ impl<'p, 's> MakespanSingleVehicleILP<'p, 's> {
             .into_iter()
             .enumerate()
             .map(|(index, assigned)| {
-                let (robot, start_vertex) = availability[index];
-                let (model_path, working_directory, data_path, run_path) =
-                    MakespanSingleVehicleILP::get_paths(robot);
-                MakespanSingleVehicleILP::create_working_directory(working_directory);
-                let (first_distances, transition_distances, last_distances) =
-                    self.calculate_distances(start_vertex, &assigned, requests);
-                MakespanSingleVehicleILP::write_data_file(
-                    data_path.as_path(),
-                    &assigned,
-                    first_distances,
-                    transition_distances,
-                    last_distances,
-                );
-                MakespanSingleVehicleILP::write_run_file(
-                    run_path.as_path(),
-                    model_path.as_path(),
-                    data_path.as_path(),
-                );
-                (
-                    spawn(move || {
-                        let output = Command::new("/home/bram/Downloads/amplide.linux64/ampl")
-                            .arg(run_path)
-                            .output()
-                            .unwrap();
-                        let (_, _, _, obj) =
-                            MakespanSingleVehicleILP::parse_ampl_output(output.stdout);
-                        obj
-                    }),
-                    assigned
-                        .iter()
-                        .map(|r| requests.get(r).unwrap().distance())
-                        .sum::<u64>(),
-                )
+                let (_, start_vertex) = availability[index];
+                self.calculate_optimal_request_order(start_vertex, assigned, requests)
             })
             .collect::<Vec<_>>();
         handles
             .into_iter()
-            .map(|(handle, sum)| handle.join().ok().unwrap() + sum)
+            .map(JoinHandle::join)
+            .map(Result::ok)
+            .map(Option::unwrap)
+            .collect()
+    }
+    pub fn calculate_assignment_quality(
+        &mut self,
+        requests: &FnvHashMap<usize, Request>,
+        availability: &Vec<(usize, Vertex)>,
+    ) -> u64 {
+        self.calculate_orders(requests, availability)
+            .into_iter()
+            .map(|(order, distance)| {
+                distance
+                    + order
+                        .iter()
+                        .map(|r| {
+                            let request = requests.get(r).unwrap();
+                            self.plan.path_length(request.from, request.to)
+                        })
+                        .sum::<u64>()
+            })
             .max()
             .unwrap()
     }
@@ -326,22 +295,9 @@ impl<'p, 's> AssignmentAlgorithm<'p, 's> for MakespanSingleVehicleILP<'p, 's> {
         requests: &FnvHashMap<usize, Request>,
         availability: &Vec<(usize, Vertex)>,
     ) -> Vec<Vec<usize>> {
-        let mut makespan_assignment = GreedyMakespan::new(self.plan, self.settings);
-        let assignment = makespan_assignment.calculate_assignment(requests, availability);
-
-        let handles = assignment
-            .into_iter()
-            .enumerate()
-            .map(|(index, assigned)| {
-                let (robot, start_vertex) = availability[index];
-                self.calculate_optimal_request_order(robot, start_vertex, assigned, requests)
-            })
-            .collect::<Vec<_>>();
-        handles
+        self.calculate_orders(requests, availability)
             .into_iter()
-            .map(JoinHandle::join)
-            .map(Result::ok)
-            .map(Option::unwrap)
+            .map(|(order, _)| order)
             .collect()
     }
 }
@@ -352,9 +308,11 @@ mod test {
     use algorithm::assignment::makespan_single_vehicle_ilp::MakespanSingleVehicleILP;
     use algorithm::assignment::test::get_test_variables;
     use algorithm::assignment::AssignmentAlgorithm;
+    use algorithm::solver::ampl::mock::MockAmpl;
     use fnv::FnvHashMap;
     use simulation::demand::Request;
     use simulation::plan::Vertex;
+    use std::sync::Arc;
 
     #[test]
     fn assignment_one() {
@@ -396,6 +354,34 @@ mod test {
         assert_eq!(result, vec![vec![0, 1]]);
     }
 
+    #[test]
+    fn assignment_ampl() {
+        let (plan, settings) = get_test_variables(1, 3);
+        let mut algorithm =
+            MakespanSingleVehicleILP::new(&plan, &settings).with_solver(Arc::new(MockAmpl::new()));
+
+        // Nearest first would serve request 0 before request 2
+        let requests = map!
+        [
+            0 => Request {
+                from: Vertex { x: 0, y: 1, },
+                to: Vertex { x: 0, y: 10, },
+            },
+            1 => Request {
+                from: Vertex { x: 0, y: 10, },
+                to: Vertex { x: 0, y: 0, },
+            },
+            2 => Request {
+                from: Vertex { x: 0, y: 2, },
+                to: Vertex { x: 0, y: 1, },
+            },
+        ];
+        let availability = vec![(0, Vertex { x: 0, y: 0 })];
+
+        let result = algorithm.calculate_assignment(&requests, &availability);
+        assert_eq!(result, vec![vec![2, 0, 1]]);
+    }
+
     #[test]
     fn assignment_many() {
         let nr_requests = 200;
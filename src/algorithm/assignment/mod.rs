@@ -14,11 +14,11 @@ pub trait AssignmentAlgorithm<'p, 's> {
     ) -> Vec<Vec<usize>>;
 }
 
-struct LPIOError {}
-
 #[cfg(test)]
 pub mod test {
 
+    use algorithm::solver::SolverSettings;
+    use simulation::placement::Placement;
     use simulation::plan::one_three_rectangle::OneThreeRectangle;
     use simulation::plan::Plan;
     use simulation::settings::Settings;
@@ -30,6 +30,9 @@ pub mod test {
             nr_robots: robots,
             nr_requests: requests,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         (plan, settings)
     }
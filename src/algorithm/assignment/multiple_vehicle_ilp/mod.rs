@@ -1,385 +1,283 @@
 use fnv::FnvHashMap;
-use itertools::Itertools;
 
 use algorithm::assignment::greedy_makespan::GreedyMakespan;
 use algorithm::assignment::AssignmentAlgorithm;
-use algorithm::assignment::LPIOError;
-use algorithm::DAT_FILE_NAME;
-use algorithm::RUN_FILE_NAME;
+use algorithm::solver::Comparison;
+use algorithm::solver::Model;
+use algorithm::solver::Solution;
+use algorithm::solver::Solver;
+use algorithm::solver::Variable;
 use simulation::demand::Request;
 use simulation::plan::Plan;
 use simulation::plan::Vertex;
 use simulation::settings::Settings;
-use std::env::temp_dir;
-use std::fs::create_dir;
-use std::fs::remove_dir_all;
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
-use std::path::PathBuf;
-use std::process::Command;
-
-const MOD_FILE_PATH: &str = "/home/bram/git/disjoint-path-routing/src/algorithm/assignment/multiple_vehicle_ilp/multiple_vehicle_ilp.mod";
-const WORKING_DIRECTORY: &str = "multiple_vehicle_ilp";
+use std::f64;
+use std::sync::Arc;
+use std::time::Duration;
 
+const TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// Assigns requests to robots such that the time until the last request is delivered is
+/// minimal, with a vehicle routing formulation.
+///
+/// Every robot has a path through the requests it serves, starting at its current location.
+/// Subtours are prevented by numbering the requests along each path.
 pub struct MultiVehicleIlpFormulation<'p, 's> {
     plan: &'p (Plan + 'p),
     settings: &'s Settings,
+    solver: Arc<Solver>,
+}
+
+/// The variables of the formulation, indexed by the position of a robot in the availability and
+/// the position of a request in the sorted request ids.
+struct Variables {
+    first: Vec<Vec<Variable>>,
+    /// `None` for a transition from a request to itself
+    transition: Vec<Vec<Vec<Option<Variable>>>>,
 }
 
 impl<'p, 's> MultiVehicleIlpFormulation<'p, 's> {
-    pub fn new(plan: &'p impl Plan, settings: &'s Settings) -> MultiVehicleIlpFormulation<'p, 's> {
-        MultiVehicleIlpFormulation { plan, settings }
+    pub fn new(plan: &'p Plan, settings: &'s Settings) -> MultiVehicleIlpFormulation<'p, 's> {
+        MultiVehicleIlpFormulation {
+            plan,
+            settings,
+            solver: settings.solver.create(TIME_LIMIT),
+        }
     }
-    fn get_paths() -> (PathBuf, PathBuf, PathBuf, PathBuf) {
-        let model_path = <&str as AsRef<Path>>::as_ref(&MOD_FILE_PATH).to_path_buf();
-
-        let working_directory = temp_dir().join(WORKING_DIRECTORY);
-        let dat_path = working_directory.join(DAT_FILE_NAME);
-        let run_path = working_directory.join(RUN_FILE_NAME);
-
-        (model_path, working_directory, dat_path, run_path)
+    /// Solves the models with another solver than the one in the settings.
+    pub fn with_solver(mut self, solver: Arc<Solver>) -> MultiVehicleIlpFormulation<'p, 's> {
+        self.solver = solver;
+        self
+    }
+    /// Cost of serving a request as the first one: moving to it and delivering it.
+    fn start_cost(&self, location: Vertex, request: &Request) -> f64 {
+        (self.plan.path_length(location, request.from)
+            + self.plan.path_length(request.from, request.to)) as f64
     }
-    fn calculate_start_costs(
+    /// Cost of serving request `second` directly after request `first`.
+    fn transition_cost(&self, first: &Request, second: &Request) -> f64 {
+        (self.plan.path_length(first.to, second.from)
+            + self.plan.path_length(second.from, second.to)) as f64
+    }
+    fn create_model(
+        &self,
+        requests: &[(usize, Request)],
         availability: &Vec<(usize, Vertex)>,
-        requests: &FnvHashMap<usize, Request>,
-    ) -> Vec<(usize, usize, u64)> {
-        let mut costs = Vec::with_capacity(availability.len() * requests.len());
+    ) -> (Model, Variables) {
+        let nr_requests = requests.len();
+        let mut model = Model::new();
 
-        for (robot, &(time, current_location)) in availability.iter().enumerate() {
-            for (&request_id, request) in requests.iter() {
-                let start_distance = current_location.distance(request.from);
-                costs.push((robot, request_id, start_distance + request.distance()));
+        let maximum = model.add_integer(0_f64, f64::INFINITY, 1_f64);
+        let first = availability
+            .iter()
+            .map(|_| requests.iter().map(|_| model.add_binary(0_f64)).collect())
+            .collect::<Vec<Vec<_>>>();
+        let transition = availability
+            .iter()
+            .map(|_| {
+                (0..nr_requests)
+                    .map(|i| {
+                        (0..nr_requests)
+                            .map(|j| {
+                                if i == j {
+                                    None
+                                } else {
+                                    Some(model.add_binary(0_f64))
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect::<Vec<Vec<Vec<_>>>>();
+        let last = availability
+            .iter()
+            .map(|_| requests.iter().map(|_| model.add_binary(0_f64)).collect())
+            .collect::<Vec<Vec<_>>>();
+        // Position of each request on the path of a robot, zero if the robot doesn't serve it
+        let number = availability
+            .iter()
+            .map(|_| {
+                requests
+                    .iter()
+                    .map(|_| model.add_continuous(0_f64, f64::INFINITY, 0_f64))
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+
+        for (r, &(_, location)) in availability.iter().enumerate() {
+            let incoming = |j: usize| {
+                let mut terms = vec![(first[r][j], 1_f64)];
+                terms.extend(
+                    (0..nr_requests)
+                        .filter_map(|i| transition[r][i][j])
+                        .map(|t| (t, 1_f64)),
+                );
+                terms
+            };
+
+            for i in 0..nr_requests {
+                // A path that arrives at a request either continues or ends there
+                let mut terms = incoming(i);
+                terms.extend(
+                    (0..nr_requests)
+                        .filter_map(|j| transition[r][i][j])
+                        .map(|t| (t, -1_f64)),
+                );
+                terms.push((last[r][i], -1_f64));
+                model.add_constraint(terms, Comparison::Equal, 0_f64);
+
+                let mut terms = incoming(i)
+                    .into_iter()
+                    .map(|(variable, _)| (variable, -(nr_requests as f64 - 1_f64)))
+                    .collect::<Vec<_>>();
+                terms.push((number[r][i], 1_f64));
+                model.add_constraint(terms, Comparison::LessEqual, 0_f64);
+
+                for j in (0..nr_requests).filter(|&j| j != i) {
+                    // Following a transition increases the number by at least one
+                    model.add_constraint(
+                        vec![
+                            (number[r][j], 1_f64),
+                            (number[r][i], -1_f64),
+                            (transition[r][i][j].unwrap(), -(nr_requests as f64 + 1_f64)),
+                        ],
+                        Comparison::GreaterEqual,
+                        -(nr_requests as f64),
+                    );
+                }
             }
-        }
 
-        costs
-    }
-    fn calculate_transition_costs(
-        requests: &FnvHashMap<usize, Request>,
-    ) -> Vec<(usize, usize, u64)> {
-        let mut costs = Vec::with_capacity(requests.len() * requests.len());
+            model.add_constraint(
+                first[r].iter().map(|&variable| (variable, 1_f64)).collect(),
+                Comparison::LessEqual,
+                1_f64,
+            );
 
-        for (&id1, request1) in requests.iter() {
-            for (&id2, request2) in requests.iter() {
-                let in_between_distance = request1.to.distance(request2.from);
-                costs.push((id1, id2, in_between_distance + request2.distance()));
+            // The maximum is at least the total duration of each robot
+            let mut terms = vec![(maximum, 1_f64)];
+            for (i, (_, request)) in requests.iter().enumerate() {
+                terms.push((first[r][i], -self.start_cost(location, request)));
+                for (j, (_, other)) in requests.iter().enumerate() {
+                    if let Some(variable) = transition[r][i][j] {
+                        terms.push((variable, -self.transition_cost(request, other)));
+                    }
+                }
             }
+            model.add_constraint(terms, Comparison::GreaterEqual, 0_f64);
         }
 
-        costs
-    }
-    fn calculate_end_costs(
-        availability: &Vec<(usize, Vertex)>,
-        requests: &FnvHashMap<usize, Request>,
-    ) -> Vec<(usize, usize, u64)> {
-        let mut costs = Vec::with_capacity(availability.len() * requests.len());
-
-        for &(robot, _) in availability.iter() {
-            for (&request_id, _) in requests.iter() {
-                costs.push((robot, request_id, 0));
+        for j in 0..nr_requests {
+            let mut terms = Vec::new();
+            for r in 0..availability.len() {
+                terms.push((first[r][j], 1_f64));
+                terms.extend(
+                    (0..nr_requests)
+                        .filter_map(|i| transition[r][i][j])
+                        .map(|t| (t, 1_f64)),
+                );
             }
+            model.add_constraint(terms, Comparison::Equal, 1_f64);
         }
 
-        costs
+        (model, Variables { first, transition })
     }
-    fn write_data_file(
-        path: impl AsRef<Path>,
-        availability: &Vec<(usize, Vertex)>,
-        requests: &FnvHashMap<usize, Request>,
-        start_costs: Vec<(usize, usize, u64)>,
-        transition_costs: Vec<(usize, usize, u64)>,
-        end_costs: Vec<(usize, usize, u64)>,
-        initial_solution: Vec<Vec<usize>>,
-    ) -> Result<(), LPIOError> {
-        let mut file = File::create(path).unwrap();
-
-        file.write("set ROBOTS :=\n".as_ref());
-        for (robot, (_, _)) in availability.iter().enumerate() {
-            write!(file, "  {},\n", robot);
-        }
-        file.write(";\n".as_ref());
-
-        file.write("set REQUESTS :=\n".as_ref());
-        for &request in requests.keys() {
-            write!(file, "  {},\n", request);
-        }
-        file.write(";\n".as_ref());
+    /// Starts the model from an assignment.
+    fn set_start(
+        model: &mut Model,
+        variables: &Variables,
+        requests: &[(usize, Request)],
+        assignment: &Vec<Vec<usize>>,
+    ) {
+        let positions = requests
+            .iter()
+            .enumerate()
+            .map(|(position, &(id, _))| (id, position))
+            .collect::<FnvHashMap<_, _>>();
 
-        file.write("param start_cost default 0 :=\n".as_ref());
-        for (robot, request, cost) in start_costs {
-            if cost > 0 {
-                write!(file, "{} {} {}\n", robot, request, cost);
+        for (r, assigned) in assignment.iter().enumerate() {
+            for &variable in variables.first[r].iter() {
+                model.set_start(variable, 0_f64);
             }
-        }
-        file.write(";\n".as_ref());
-
-        file.write("param transition_cost default 0 :=\n".as_ref());
-        for (request_i, request_j, cost) in transition_costs {
-            if cost > 0 {
-                write!(file, "{} {} {}\n", request_i, request_j, cost);
+            for &variable in variables.transition[r].iter().flatten().flatten() {
+                model.set_start(variable, 0_f64);
             }
-        }
-        file.write(";\n".as_ref());
 
-        file.write("param end_cost default 0 :=\n".as_ref());
-        for (request, end_location, cost) in end_costs {
-            if cost > 0 {
-                write!(file, "{} {} {}\n", request, end_location, cost);
+            let assigned = assigned.iter().map(|id| positions[id]).collect::<Vec<_>>();
+            if let Some(&first) = assigned.first() {
+                model.set_start(variables.first[r][first], 1_f64);
             }
-        }
-        file.write(";\n".as_ref());
-
-        writeln!(file, "var First_Request :=");
-        for (robot, requests) in initial_solution.iter().enumerate() {
-            writeln!(file, "{} {} {}", robot, requests[0], 1);
-        }
-        writeln!(file, ";");
-
-        writeln!(file, "var Transition :=");
-        for (robot, requests) in initial_solution.iter().enumerate() {
-            for i in 0..(requests.len() - 1) {
-                writeln!(file, "{} {} {} {}", robot, requests[i], requests[i + 1], 1);
+            for pair in assigned.windows(2) {
+                model.set_start(variables.transition[r][pair[0]][pair[1]].unwrap(), 1_f64);
             }
         }
-        writeln!(file, ";");
-
-        writeln!(file, "var Last_Request :=");
-        for (robot, requests) in initial_solution.iter().enumerate() {
-            writeln!(
-                file,
-                "{} {} {} {}",
-                robot,
-                robot,
-                requests.last().unwrap(),
-                1
-            );
-        }
-        writeln!(file, ";");
-
-        Ok(())
     }
-    fn write_run_file<T>(path: T, model_path: T, data_path: T, time_limit: f64)
-    where
-        T: AsRef<Path>,
-    {
-        let mut file = File::create(path).unwrap();
-
-        writeln!(file, "model '{}';", model_path.as_ref().to_str().unwrap());
-        writeln!(file, "data '{}';", data_path.as_ref().to_str().unwrap());
-        file.write("option solver '/home/bram/Downloads/amplide.linux64/gurobi';\n".as_ref());
-        file.write("option show_stats 0;\n".as_ref());
-        writeln!(file, "option gurobi_options 'timelim {} bestbound 1';", 30);
-        file.write("solve;\n".as_ref());
-        file.write("option omit_zero_rows 1;\n".as_ref());
-        file.write("option display_1col 1000000;\n".as_ref());
-        file.write("display First_Request;\n".as_ref());
-        file.write("display Transition;\n".as_ref());
-        file.write("display Last_Request;\n".as_ref());
-        file.write("option omit_zero_rows 0;\n".as_ref());
-        file.write("display Robot_Number;\n".as_ref());
+    fn reconstruct_assignment(
+        solution: &Solution,
+        variables: &Variables,
+        requests: &[(usize, Request)],
+    ) -> Vec<Vec<usize>> {
+        variables
+            .first
+            .iter()
+            .zip(variables.transition.iter())
+            .map(|(first, transition)| {
+                let mut assigned = Vec::new();
+                let mut current = first.iter().position(|&variable| solution.is_set(variable));
+                while let Some(i) = current {
+                    assigned.push(requests[i].0);
+                    current = transition[i].iter().position(|&variable| {
+                        variable.map_or(false, |variable| solution.is_set(variable))
+                    });
+                }
+
+                assigned
+            })
+            .collect()
     }
-    fn calculate_time_limit(
-        availability: &Vec<(usize, Vertex)>,
+    /// Solves the formulation, starting from the assignment of `GreedyMakespan`.
+    fn solve(
+        &self,
         requests: &FnvHashMap<usize, Request>,
-    ) -> f64 {
-        requests.iter().map(|(_, r)| r.distance()).sum::<u64>() as f64 / availability.len() as f64
-    }
-    fn parse_ampl_output(
-        output: Vec<u8>,
-    ) -> (
-        FnvHashMap<usize, usize>,
-        FnvHashMap<(usize, usize), usize>,
-        FnvHashMap<(usize, usize), usize>,
-        u64,
-        f64,
-    ) {
-        let output = String::from_utf8(output).unwrap();
-        let lines = output.lines().collect::<Vec<_>>();
+        availability: &Vec<(usize, Vertex)>,
+    ) -> (Vec<Vec<usize>>, Option<Solution>) {
+        let mut pre_algorithm = GreedyMakespan::new(self.plan, self.settings);
+        let initial_assignment = pre_algorithm.calculate_assignment(requests, availability);
 
-        let objective = lines
-            .iter()
-            .skip_while(|line| !line.contains("objective"))
-            .next()
-            .unwrap()
-            .split_whitespace()
-            .last()
-            .unwrap()
-            .parse::<u64>()
-            .unwrap();
-        let relmipgap = if let Some(line) = lines
+        let mut requests = requests
             .iter()
-            .skip_while(|line| !line.contains("relmipgap"))
-            .next()
-        {
-            line.split_whitespace()
-                .last()
-                .unwrap()
-                .parse::<f64>()
-                .unwrap()
-        } else {
-            0 as f64
-        };
-
-        let mut segments = lines.split(|&line| line == ";");
-        let mut get_lines = |pattern: &str| {
-            segments
-                .next()
-                .unwrap()
-                .into_iter()
-                .skip_while(|&&line| !line.starts_with(pattern))
-                .skip(1)
-                .map(|line| *line)
-                .collect::<Vec<_>>()
-        };
-        let firsts = get_lines("First_Request");
-        let transitions = get_lines("Transition");
-        let lasts = get_lines("Last_Request");
-
-        let first = MultiVehicleIlpFormulation::parse_first_requests(firsts);
-        let transitions = MultiVehicleIlpFormulation::parse_transitions(transitions);
-        let lasts = MultiVehicleIlpFormulation::parse_last_locations(lasts);
-
-        (first, transitions, lasts, objective, relmipgap)
-    }
-    fn parse_first_requests(lines: Vec<&str>) -> FnvHashMap<usize, usize> {
-        MultiVehicleIlpFormulation::parse_lines_2d(lines)
-    }
-    fn parse_lines_2d(lines: Vec<&str>) -> FnvHashMap<usize, usize> {
-        lines
-            .into_iter()
-            .map(
-                |line| match line.split_whitespace().collect_vec().as_slice() {
-                    [first, second, "1"] => {
-                        if let (Ok(first), Ok(second)) =
-                            (first.parse::<usize>(), second.parse::<usize>())
-                        {
-                            (first, second)
-                        } else {
-                            panic!()
-                        }
-                    }
-                    _ => panic!(),
-                },
-            )
-            .collect()
-    }
-    fn parse_transitions(lines: Vec<&str>) -> FnvHashMap<(usize, usize), usize> {
-        MultiVehicleIlpFormulation::parse_lines_3d(lines)
-    }
-    fn parse_last_locations(lines: Vec<&str>) -> FnvHashMap<(usize, usize), usize> {
-        MultiVehicleIlpFormulation::parse_lines_3d(lines)
-    }
-    fn parse_lines_3d(lines: Vec<&str>) -> FnvHashMap<(usize, usize), usize> {
-        lines
-            .into_iter()
-            .map(
-                |line| match line.split_whitespace().collect_vec().as_slice() {
-                    [first, second, third, "1"] => {
-                        if let (Ok(first), Ok(second), Ok(third)) = (
-                            first.parse::<usize>(),
-                            second.parse::<usize>(),
-                            third.parse::<usize>(),
-                        ) {
-                            (first, second, third)
-                        } else {
-                            panic!()
-                        }
-                    }
-                    _ => panic!(),
-                },
-            )
-            .map(|(first, second, third)| ((first, second), third))
-            .collect()
-    }
-    fn reconstruct_assignment(
-        firsts: FnvHashMap<usize, usize>,
-        transitions: FnvHashMap<(usize, usize), usize>,
-        lasts: FnvHashMap<(usize, usize), usize>,
-    ) -> Vec<Vec<usize>> {
-        let missing = (0..firsts.len())
-            .filter(|i| !firsts.contains_key(i))
+            .map(|(&id, &request)| (id, request))
             .collect::<Vec<_>>();
-        let mut assignments = FnvHashMap::default();
-
-        for (robot, first_request) in firsts {
-            assignments.insert(
-                robot,
-                MultiVehicleIlpFormulation::reconstruct_transitions(
-                    robot,
-                    first_request,
-                    &transitions,
-                ),
-            );
-        }
-        for robot in missing {
-            assignments.insert(robot, Vec::with_capacity(0));
-        }
+        requests.sort_by_key(|&(id, _)| id);
+        let (mut model, variables) = self.create_model(&requests, availability);
+        MultiVehicleIlpFormulation::set_start(
+            &mut model,
+            &variables,
+            &requests,
+            &initial_assignment,
+        );
 
-        let mut assignments = assignments.into_iter().collect::<Vec<_>>();
-        assignments.sort_by_key(|&(robot, _)| robot);
-        assignments
-            .into_iter()
-            .map(|(_, assigned)| assigned)
-            .collect()
-    }
-    fn reconstruct_transitions(
-        robot: usize,
-        first_request: usize,
-        transitions: &FnvHashMap<(usize, usize), usize>,
-    ) -> Vec<usize> {
-        let mut assigned = Vec::new();
-        assigned.push(first_request);
-
-        let mut current = first_request;
-        while let Some(next) = transitions.get(&(robot, current)) {
-            current = *next;
-            assigned.push(current);
+        match self.solver.solve(&model) {
+            Ok(solution) => (
+                MultiVehicleIlpFormulation::reconstruct_assignment(
+                    &solution, &variables, &requests,
+                ),
+                Some(solution),
+            ),
+            // The greedy assignment is better than none
+            Err(_) => (initial_assignment, None),
         }
-
-        assigned
     }
+    /// The makespan of the assignment and the relative gap to the best bound on it, if the
+    /// solver found a solution.
     pub fn calculate_assignment_quality(
         &mut self,
         requests: &FnvHashMap<usize, Request>,
         availability: &Vec<(usize, Vertex)>,
-    ) -> (u64, f64) {
-        let (model_path, working_directory, data_path, run_path) =
-            MultiVehicleIlpFormulation::get_paths();
-        if working_directory.exists() {
-            remove_dir_all(working_directory.as_path());
-        }
-        create_dir(working_directory).unwrap();
-
-        let start_costs = MultiVehicleIlpFormulation::calculate_start_costs(availability, requests);
-        let transition_costs = MultiVehicleIlpFormulation::calculate_transition_costs(requests);
-        let end_costs = MultiVehicleIlpFormulation::calculate_end_costs(availability, requests);
-        let mut pre_algorithm = GreedyMakespan::new(self.plan, self.settings);
-        let initial_assignment = pre_algorithm.calculate_assignment(requests, availability);
-        MultiVehicleIlpFormulation::write_data_file(
-            data_path.as_path(),
-            availability,
-            requests,
-            start_costs,
-            transition_costs,
-            end_costs,
-            initial_assignment,
-        );
-        MultiVehicleIlpFormulation::write_run_file(
-            run_path.as_path(),
-            model_path.as_path(),
-            data_path.as_path(),
-            MultiVehicleIlpFormulation::calculate_time_limit(availability, requests),
-        );
-
-        let output = Command::new("/home/bram/Downloads/amplide.linux64/ampl")
-            .arg(run_path)
-            .output()
-            .unwrap();
-
-        let (_, _, _, objective, relmipgap) =
-            MultiVehicleIlpFormulation::parse_ampl_output(output.stdout);
-        (objective, relmipgap)
+    ) -> Option<(u64, f64)> {
+        self.solve(requests, availability)
+            .1
+            .map(|solution| (solution.objective().round() as u64, solution.gap()))
     }
 }
 
@@ -389,45 +287,7 @@ impl<'p, 's> AssignmentAlgorithm<'p, 's> for MultiVehicleIlpFormulation<'p, 's>
         requests: &FnvHashMap<usize, Request>,
         availability: &Vec<(usize, Vertex)>,
     ) -> Vec<Vec<usize>> {
-        let (model_path, working_directory, data_path, run_path) =
-            MultiVehicleIlpFormulation::get_paths();
-        if working_directory.exists() {
-            remove_dir_all(working_directory.as_path());
-        }
-        create_dir(working_directory).unwrap();
-
-        let start_costs = MultiVehicleIlpFormulation::calculate_start_costs(availability, requests);
-        let transition_costs = MultiVehicleIlpFormulation::calculate_transition_costs(requests);
-        let end_costs = MultiVehicleIlpFormulation::calculate_end_costs(availability, requests);
-        let mut pre_algorithm = GreedyMakespan::new(self.plan, self.settings);
-        let initial_assignment = pre_algorithm.calculate_assignment(requests, availability);
-        if transition_costs.len() * availability.len() > 10_000_000 {
-            panic!()
-        }
-        MultiVehicleIlpFormulation::write_data_file(
-            data_path.as_path(),
-            availability,
-            requests,
-            start_costs,
-            transition_costs,
-            end_costs,
-            initial_assignment,
-        );
-        MultiVehicleIlpFormulation::write_run_file(
-            run_path.as_path(),
-            model_path.as_path(),
-            data_path.as_path(),
-            MultiVehicleIlpFormulation::calculate_time_limit(availability, requests),
-        );
-
-        let output = Command::new("/home/bram/Downloads/amplide.linux64/ampl")
-            .arg(run_path)
-            .output()
-            .unwrap();
-
-        let (firsts, transitions, lasts, objective, relmipgap) =
-            MultiVehicleIlpFormulation::parse_ampl_output(output.stdout);
-        MultiVehicleIlpFormulation::reconstruct_assignment(firsts, transitions, lasts)
+        self.solve(requests, availability).0
     }
 }
 
@@ -436,6 +296,7 @@ mod test {
 
     use super::*;
     use algorithm::assignment::test::get_test_variables;
+    use algorithm::solver::ampl::mock::MockAmpl;
 
     #[test]
     fn assignment_one() {
@@ -499,6 +360,37 @@ mod test {
         assert_eq!(result, vec![vec![0], vec![1]]);
     }
 
+    #[test]
+    fn assignment_ampl() {
+        let (plan, settings) = get_test_variables(2, 3);
+        let mut algorithm = MultiVehicleIlpFormulation::new(&plan, &settings)
+            .with_solver(Arc::new(MockAmpl::new()));
+
+        let requests = map!
+        [
+            0 => Request {
+                from: Vertex { x: 0, y: 0, },
+                to: Vertex { x: 0, y: 1, },
+            },
+            1 => Request {
+                from: Vertex { x: 10, y: 10, },
+                to: Vertex { x: 0, y: 2, },
+            },
+            2 => Request {
+                from: Vertex { x: 0, y: 2, },
+                to: Vertex { x: 0, y: 0, },
+            },
+        ];
+        let availability = vec![(0, Vertex { x: 0, y: 0 }), (1, Vertex { x: 10, y: 10 })];
+
+        let result = algorithm.calculate_assignment(&requests, &availability);
+        assert_eq!(result, vec![vec![0, 2], vec![1]]);
+        assert_eq!(
+            algorithm.calculate_assignment_quality(&requests, &availability),
+            Some((18, 0_f64))
+        );
+    }
+
     #[test]
     fn assignment_many() {
         let (nr_robots, nr_requests) = (3, 11);
@@ -1,9 +1,57 @@
+use algorithm::solver::SolveError;
+use std::env::temp_dir;
+use std::fmt;
+use std::fs::create_dir;
+use std::fs::remove_dir_all;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
 pub mod assignment;
 pub mod path;
+pub mod solver;
 
 const RUN_FILE_NAME: &str = "run.run";
 const DAT_FILE_NAME: &str = "data.dat";
 
+static NEXT_THREAD_NUMBER: AtomicUsize = AtomicUsize::new(0);
+thread_local!(static THREAD_NUMBER: usize = NEXT_THREAD_NUMBER.fetch_add(1, Ordering::SeqCst));
+
+/// Temporary directory for the AMPL files of an algorithm, which is removed again when dropped.
+///
+/// The directory is unique for each process and thread, such that simulations can run in parallel.
+struct WorkingDirectory {
+    path: PathBuf,
+}
+
+impl WorkingDirectory {
+    /// Creates the directory, empty, removing what an earlier run may have left behind.
+    fn create(name: &str) -> io::Result<WorkingDirectory> {
+        let thread_number = THREAD_NUMBER.with(|number| *number);
+        let path = temp_dir().join(format!("{}_{}_{}", name, process::id(), thread_number));
+        if path.exists() {
+            remove_dir_all(&path)?;
+        }
+        create_dir(&path)?;
+
+        Ok(WorkingDirectory { path })
+    }
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for WorkingDirectory {
+    fn drop(&mut self) {
+        // Nothing can be done about a failure here, the directory is only a temporary one
+        let _ = remove_dir_all(&self.path);
+    }
+}
+
+#[derive(Debug)]
 pub struct NoSolutionError {
     message: String,
 }
@@ -12,4 +60,19 @@ impl NoSolutionError {
     fn new(message: String) -> NoSolutionError {
         NoSolutionError { message }
     }
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+}
+
+impl From<SolveError> for NoSolutionError {
+    fn from(error: SolveError) -> Self {
+        NoSolutionError::new(error.to_string())
+    }
+}
+
+impl fmt::Display for NoSolutionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
 }
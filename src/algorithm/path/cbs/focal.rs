@@ -0,0 +1,154 @@
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use priority_queue::PriorityQueue;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
+/// Open items of a focal search, which are visited in order of their priority as long as their
+/// cost is within the suboptimality factor of the lowest lower bound of all open items.
+///
+/// The lowest lower bound may only increase as items are visited, as it does in A* with a
+/// consistent heuristic. With a suboptimality factor of one, the items of the lowest cost are
+/// visited first.
+pub struct FocalQueue<K: Hash + Eq + Copy, P: Ord> {
+    suboptimality: f64,
+    bound: u64,
+
+    /// Lower bound and cost of each open item
+    values: FnvHashMap<K, (u64, u64)>,
+    lower_bounds: BTreeMap<u64, FnvHashSet<K>>,
+    /// Items within the bound
+    focal: PriorityQueue<K, P>,
+    /// Items of which the cost is above the bound, by their cost
+    waiting: BTreeMap<u64, FnvHashMap<K, P>>,
+}
+
+impl<K: Hash + Eq + Copy, P: Ord> FocalQueue<K, P> {
+    pub fn new(suboptimality: f64) -> FocalQueue<K, P> {
+        debug_assert!(suboptimality >= 1_f64);
+
+        FocalQueue {
+            suboptimality,
+            bound: 0,
+
+            values: FnvHashMap::default(),
+            lower_bounds: BTreeMap::new(),
+            focal: PriorityQueue::new(),
+            waiting: BTreeMap::new(),
+        }
+    }
+    /// Adds an item, or changes the priority of an item that is already open.
+    pub fn push(&mut self, item: K, lower_bound: u64, cost: u64, priority: P) {
+        debug_assert!(lower_bound <= cost);
+
+        if let Some(&values) = self.values.get(&item) {
+            debug_assert_eq!(values, (lower_bound, cost));
+        } else {
+            self.values.insert(item, (lower_bound, cost));
+            self.lower_bounds
+                .entry(lower_bound)
+                .or_insert_with(FnvHashSet::default)
+                .insert(item);
+        }
+
+        if cost <= self.bound {
+            self.focal.push(item, priority);
+        } else {
+            self.waiting
+                .entry(cost)
+                .or_insert_with(FnvHashMap::default)
+                .insert(item, priority);
+        }
+    }
+    /// Removes the item of the highest priority within the bound.
+    ///
+    /// The bound is only raised here, after all successors of the previous item were added, such
+    /// that it doesn't jump past their lower bounds.
+    pub fn pop(&mut self) -> Option<K> {
+        self.update_bound();
+        let (item, _) = self.focal.pop()?;
+
+        let (lower_bound, _) = self.values.remove(&item).unwrap();
+        let remove_bucket = {
+            let items = self.lower_bounds.get_mut(&lower_bound).unwrap();
+            items.remove(&item);
+            items.is_empty()
+        };
+        if remove_bucket {
+            self.lower_bounds.remove(&lower_bound);
+        }
+
+        Some(item)
+    }
+    /// The lowest lower bound of all open items.
+    pub fn lower_bound(&self) -> Option<u64> {
+        self.lower_bounds.keys().next().cloned()
+    }
+    fn update_bound(&mut self) {
+        let bound = match self.lower_bound() {
+            // Guard against rounding down products that are integral
+            Some(lower_bound) => (lower_bound as f64 * self.suboptimality + 1e-9).floor() as u64,
+            None => return,
+        };
+        if bound <= self.bound && !self.focal.is_empty() {
+            return;
+        }
+
+        self.bound = self.bound.max(bound);
+        let costs = self
+            .waiting
+            .range(..(self.bound + 1))
+            .map(|(&cost, _)| cost)
+            .collect::<Vec<_>>();
+        for cost in costs {
+            for (item, priority) in self.waiting.remove(&cost).unwrap() {
+                self.focal.push(item, priority);
+            }
+        }
+        // Only when the costs are not within the suboptimality factor of the lower bounds
+        if self.focal.is_empty() {
+            let cost = *self.waiting.keys().next().unwrap();
+            for (item, priority) in self.waiting.remove(&cost).unwrap() {
+                self.focal.push(item, priority);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::path::cbs::focal::FocalQueue;
+    use std::cmp::Reverse;
+
+    #[test]
+    fn test_optimal() {
+        let mut queue = FocalQueue::new(1_f64);
+        queue.push(0, 2, 2, Reverse(5));
+        queue.push(1, 2, 2, Reverse(1));
+        queue.push(2, 3, 3, Reverse(0));
+
+        assert_eq!(queue.lower_bound(), Some(2));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(0));
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn test_suboptimal() {
+        let mut queue = FocalQueue::new(1.5_f64);
+        queue.push(0, 2, 2, Reverse(3));
+        queue.push(1, 3, 3, Reverse(1));
+        queue.push(2, 2, 3, Reverse(0));
+        queue.push(3, 4, 5, Reverse(0));
+
+        // Costs up to three are within the bound
+        assert_eq!(queue.pop(), Some(2));
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop(), Some(0));
+        assert_eq!(queue.lower_bound(), Some(4));
+        assert_eq!(queue.pop(), Some(3));
+        assert_eq!(queue.pop(), None);
+    }
+}
@@ -0,0 +1,277 @@
+use algorithm::path::cbs::focal::FocalQueue;
+use algorithm::path::goals::Goal;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use std::cmp::Reverse;
+
+/// Positions and moves that a single robot is not allowed to take, in time steps since the
+/// paths were planned.
+#[derive(Debug, Default)]
+pub struct Constraints {
+    vertices: FnvHashSet<(Vertex, usize)>,
+    /// Moves from the first to the second vertex, arriving at the time
+    edges: FnvHashSet<(Vertex, Vertex, usize)>,
+}
+
+impl Constraints {
+    pub fn forbid_vertex(&mut self, vertex: Vertex, time: usize) {
+        self.vertices.insert((vertex, time));
+    }
+    pub fn forbid_edge(&mut self, from: Vertex, to: Vertex, time: usize) {
+        self.edges.insert((from, to, time));
+    }
+    /// Whether the robot can move from a vertex to another (or the same) vertex, arriving at the
+    /// time.
+    fn allows(&self, from: Vertex, to: Vertex, time: usize) -> bool {
+        !self.vertices.contains(&(to, time)) && !self.edges.contains(&(from, to, time))
+    }
+    /// The last time at which the robot is not allowed to be at the vertex.
+    fn last_time_at(&self, vertex: Vertex) -> Option<usize> {
+        self.vertices
+            .iter()
+            .filter(|&&(constrained, _)| constrained == vertex)
+            .map(|&(_, time)| time)
+            .max()
+    }
+    fn last_time(&self) -> usize {
+        self.vertices
+            .iter()
+            .map(|&(_, time)| time)
+            .chain(self.edges.iter().map(|&(_, _, time)| time))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A path of a single robot through space and time that satisfies the constraints, found with a
+/// focal search, and a lower bound on the length of the shortest such path.
+///
+/// The path contains the position of the robot at each time step, starting at the `start`. When
+/// the robot serves its goal, the path ends with the step in which it does so. The `distances`
+/// are the shortest path lengths to the goal, which are used as the heuristic.
+///
+/// Of the paths that are at most `suboptimality` times as long as the shortest path, the search
+/// prefers those with the fewest `conflicts` with the paths of other robots, as counted for each
+/// move from a vertex to another (or the same) vertex, arriving at a time. With a suboptimality
+/// factor of one, the search is A* in which conflicts break ties.
+pub fn find_path(
+    plan: &Plan,
+    start: Vertex,
+    goal: Goal,
+    distances: &FnvHashMap<Vertex, u64>,
+    constraints: &Constraints,
+    suboptimality: f64,
+    conflicts: &Fn(Vertex, Vertex, usize) -> usize,
+) -> Option<(Vec<Vertex>, u64)> {
+    if !constraints.allows(start, start, 0) {
+        return None;
+    }
+
+    // After the last constraint, any shortest path to the goal can be taken
+    let horizon = constraints.last_time() + plan.vertices().len() + 1;
+    let last_time_at_goal = constraints.last_time_at(goal.vertex());
+    // Serving the goal takes a step
+    let extra_steps = match goal {
+        Goal::Serve(_) => 1,
+        Goal::Park(_) => 0,
+    };
+    let estimate = |time: usize, vertex: Vertex| {
+        distances
+            .get(&vertex)
+            .map(|distance| time as u64 + distance + extra_steps)
+    };
+
+    let mut came_from = FnvHashMap::default();
+    let mut nr_conflicts = FnvHashMap::default();
+    let mut visited = FnvHashSet::default();
+    let mut to_visit = FocalQueue::new(suboptimality);
+    let start_estimate = estimate(0, start)?;
+    nr_conflicts.insert((0, start), 0);
+    to_visit.push(
+        (0, start),
+        start_estimate,
+        start_estimate,
+        (Reverse(0), Reverse(start_estimate), 0),
+    );
+
+    while let Some(lower_bound) = to_visit.lower_bound() {
+        let current = to_visit.pop().unwrap();
+        visited.insert(current);
+
+        let (time, vertex) = current;
+        if vertex == goal.vertex() {
+            match goal {
+                Goal::Serve(_) if constraints.allows(vertex, vertex, time + 1) => {
+                    let mut path = reconstruct_path(&came_from, current);
+                    path.push(vertex);
+                    return Some((path, lower_bound));
+                }
+                Goal::Park(_) if last_time_at_goal.map_or(true, |last| last < time) => {
+                    return Some((reconstruct_path(&came_from, current), lower_bound));
+                }
+                _ => (),
+            }
+        }
+        if time == horizon {
+            continue;
+        }
+
+        let next_time = time + 1;
+        for neighbor in plan.neighbors(&vertex).into_iter().chain(Some(vertex)) {
+            let next = (next_time, neighbor);
+            if visited.contains(&next) || !constraints.allows(vertex, neighbor, next_time) {
+                continue;
+            }
+            if let Some(estimate) = estimate(next_time, neighbor) {
+                let next_conflicts =
+                    nr_conflicts[&current] + conflicts(vertex, neighbor, next_time);
+                if nr_conflicts
+                    .get(&next)
+                    .map_or(true, |&known| next_conflicts < known)
+                {
+                    came_from.insert(next, current);
+                    nr_conflicts.insert(next, next_conflicts);
+                    // Prefer the longest paths on ties, as they are closer to the goal
+                    let priority = (Reverse(next_conflicts), Reverse(estimate), next_time);
+                    to_visit.push(next, estimate, estimate, priority);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn reconstruct_path(
+    came_from: &FnvHashMap<(usize, Vertex), (usize, Vertex)>,
+    end: (usize, Vertex),
+) -> Vec<Vertex> {
+    let mut path = vec![end.1];
+    let mut current = end;
+    while let Some(&previous) = came_from.get(&current) {
+        path.push(previous.1);
+        current = previous;
+    }
+    path.reverse();
+
+    path
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::path::cbs::low_level::find_path;
+    use algorithm::path::cbs::low_level::Constraints;
+    use algorithm::path::goals::Goal;
+    use simulation::plan::breadth_first_distances;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use simulation::plan::Vertex;
+
+    #[test]
+    fn test_find_path() {
+        let plan = OneThreeRectangle::new(3, 3);
+        let start = Vertex { x: 0, y: 0 };
+        let goal = Vertex { x: 2, y: 0 };
+        let distances = breadth_first_distances(&plan, goal);
+        let mut constraints = Constraints::default();
+        let no_conflicts = |_, _, _| 0;
+
+        assert_eq!(
+            find_path(
+                &plan,
+                start,
+                Goal::Serve(goal),
+                &distances,
+                &constraints,
+                1_f64,
+                &no_conflicts
+            ),
+            Some((vec![start, Vertex { x: 1, y: 0 }, goal, goal], 3))
+        );
+
+        // Waiting is shorter than going around
+        constraints.forbid_vertex(Vertex { x: 1, y: 0 }, 1);
+        assert_eq!(
+            find_path(
+                &plan,
+                start,
+                Goal::Park(goal),
+                &distances,
+                &constraints,
+                1_f64,
+                &no_conflicts
+            ),
+            Some((vec![start, start, Vertex { x: 1, y: 0 }, goal], 3))
+        );
+
+        // The robot can only stay at its goal after it is last needed elsewhere
+        constraints.forbid_vertex(goal, 5);
+        let (path, _) = find_path(
+            &plan,
+            start,
+            Goal::Park(goal),
+            &distances,
+            &constraints,
+            1_f64,
+            &no_conflicts,
+        )
+        .unwrap();
+        assert_eq!(path.len(), 7);
+        assert_eq!(path.last(), Some(&goal));
+
+        constraints.forbid_vertex(start, 0);
+        assert_eq!(
+            find_path(
+                &plan,
+                start,
+                Goal::Park(goal),
+                &distances,
+                &constraints,
+                1_f64,
+                &no_conflicts
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_suboptimal() {
+        let plan = OneThreeRectangle::new(3, 3);
+        let start = Vertex { x: 0, y: 0 };
+        let goal = Vertex { x: 2, y: 0 };
+        let distances = breadth_first_distances(&plan, goal);
+        let constraints = Constraints::default();
+        // Another robot stays at the vertex between the start and the goal
+        let blocked = Vertex { x: 1, y: 0 };
+        let conflicts = |_, to, _| if to == blocked { 1 } else { 0 };
+
+        // Going around is not much longer than the shortest path
+        let (path, lower_bound) = find_path(
+            &plan,
+            start,
+            Goal::Park(goal),
+            &distances,
+            &constraints,
+            2_f64,
+            &conflicts,
+        )
+        .unwrap();
+        assert_eq!(path.len() - 1, 4);
+        assert!(!path.contains(&Vertex { x: 1, y: 0 }));
+        assert_eq!(lower_bound, 2);
+
+        let (path, _) = find_path(
+            &plan,
+            start,
+            Goal::Park(goal),
+            &distances,
+            &constraints,
+            1.5_f64,
+            &conflicts,
+        )
+        .unwrap();
+        assert_eq!(path, vec![start, Vertex { x: 1, y: 0 }, goal]);
+    }
+}
@@ -0,0 +1,627 @@
+use algorithm::assignment::AssignmentAlgorithm;
+use algorithm::path::cbs::focal::FocalQueue;
+use algorithm::path::cbs::low_level::find_path;
+use algorithm::path::cbs::low_level::Constraints;
+use algorithm::path::goals::assign_requests;
+use algorithm::path::goals::goals;
+use algorithm::path::goals::instructions;
+use algorithm::path::goals::position;
+use algorithm::path::goals::Goal;
+use algorithm::path::PathAlgorithm;
+use algorithm::NoSolutionError;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use simulation::conflict::find_conflicts;
+use simulation::conflict::Conflict;
+use simulation::conflict::ConflictKind;
+use simulation::plan::breadth_first_distances;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::settings::Settings;
+use simulation::state::History;
+use simulation::state::RobotState;
+use simulation::state::State;
+use simulation::Instructions;
+use std::cmp::Reverse;
+use std::time::Duration;
+use std::time::Instant;
+
+pub mod focal;
+pub mod low_level;
+
+const TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// Plans paths for all robots towards the vertex they need to go to next with Conflict-Based
+/// Search, such that the sum of the lengths of the paths is minimal.
+///
+/// The low level search finds a shortest path for a single robot through space and time. The
+/// high level search resolves the conflicts between these paths, by constraining either of the two
+/// robots involved and planning its path again. Paths are planned again each time a robot gets a
+/// new goal.
+///
+/// With a suboptimality factor above one, this is Enhanced Conflict-Based Search: both searches
+/// are focal searches, which prefer paths with few conflicts over short paths, as long as the sum
+/// of the lengths stays within the factor of the minimum.
+pub struct ConflictBasedSearch<'p, 's, 'a> {
+    settings: &'s Settings,
+    plan: &'p Plan,
+    assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
+
+    time_limit: Duration,
+    suboptimality: f64,
+    /// Shortest path lengths towards each goal, used by the low level search
+    distances: FnvHashMap<Vertex, FnvHashMap<Vertex, u64>>,
+
+    pub assignment: Vec<Vec<usize>>,
+    /// Goals towards which the current paths were planned
+    goals: Vec<Goal>,
+    /// Position of each robot in each step, since the time the paths were planned at
+    paths: Vec<Vec<Vertex>>,
+    planned_at: usize,
+    /// Times at which no paths were found in time, such that the robots moved greedily
+    pub failures: Vec<(usize, NoSolutionError)>,
+}
+
+/// A robot is not allowed to be at a vertex, or to move over an edge, at a time.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Constraint {
+    Vertex {
+        robot: usize,
+        vertex: Vertex,
+        time: usize,
+    },
+    Edge {
+        robot: usize,
+        from: Vertex,
+        to: Vertex,
+        time: usize,
+    },
+}
+
+/// A node of the constraint tree of the high level search.
+struct Node {
+    constraints: Vec<Constraint>,
+    paths: Vec<Vec<Vertex>>,
+    /// Lower bound on the length of the shortest path of each robot
+    lower_bounds: Vec<u64>,
+    cost: u64,
+    /// The earliest conflict between the paths, and the time at which it happens
+    conflict: Option<(usize, Conflict)>,
+    nr_conflicts: usize,
+}
+
+impl Node {
+    fn lower_bound(&self) -> u64 {
+        self.lower_bounds.iter().sum()
+    }
+    /// Nodes with fewer conflicts are visited first, they likely need fewer constraints.
+    fn priority(&self) -> (Reverse<usize>, Reverse<u64>) {
+        (Reverse(self.nr_conflicts), Reverse(self.cost))
+    }
+}
+
+impl<'p, 's, 'a> ConflictBasedSearch<'p, 's, 'a> {
+    pub fn new(
+        plan: &'p Plan,
+        settings: &'s Settings,
+        assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
+    ) -> ConflictBasedSearch<'p, 's, 'a> {
+        ConflictBasedSearch {
+            settings,
+            plan,
+            assignment_algorithm,
+
+            time_limit: TIME_LIMIT,
+            suboptimality: 1_f64,
+            distances: FnvHashMap::default(),
+
+            assignment: Vec::new(),
+            goals: Vec::new(),
+            paths: Vec::new(),
+            planned_at: 0,
+            failures: Vec::new(),
+        }
+    }
+    /// Gives up on finding paths after another time than the default of one second, after which
+    /// the robots move greedily.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> ConflictBasedSearch<'p, 's, 'a> {
+        self.time_limit = time_limit;
+        self
+    }
+    /// Allows the sum of the lengths of the paths to be up to a factor of at least one longer than
+    /// the minimum, in exchange for fewer conflicts to resolve.
+    pub fn with_suboptimality(mut self, suboptimality: f64) -> ConflictBasedSearch<'p, 's, 'a> {
+        debug_assert!(suboptimality >= 1_f64);
+
+        self.suboptimality = suboptimality;
+        self
+    }
+    /// Whether the robots are where the current paths say, and the paths tell where to go next.
+    fn follows_paths(&self, state: &State, step: usize) -> bool {
+        self.paths.len() == state.robot_states.len()
+            && state.robot_states.iter().all(|robot| {
+                let path = &self.paths[robot.robot_id];
+                let continues = match self.goals[robot.robot_id] {
+                    Goal::Serve(_) => step + 1 < path.len(),
+                    Goal::Park(_) => true,
+                };
+                continues && robot.vertex == Some(path[step.min(path.len() - 1)])
+            })
+    }
+    /// Conflict free paths from the starts to the goals, of minimal total length up to the
+    /// suboptimality factor.
+    fn find_paths(
+        &mut self,
+        starts: &Vec<Vertex>,
+        goals: &Vec<Goal>,
+    ) -> Result<Vec<Vec<Vertex>>, NoSolutionError> {
+        let started = Instant::now();
+        for goal in goals.iter() {
+            let plan = self.plan;
+            self.distances
+                .entry(goal.vertex())
+                .or_insert_with(|| breadth_first_distances(plan, goal.vertex()));
+        }
+
+        let (mut paths, mut lower_bounds) = (Vec::new(), Vec::new());
+        for robot in 0..starts.len() {
+            match self.find_path(robot, starts, goals, &Vec::new(), &paths) {
+                Some((path, lower_bound)) => {
+                    paths.push(path);
+                    lower_bounds.push(lower_bound);
+                }
+                None => {
+                    return Err(NoSolutionError::new(format!(
+                        "Robot {} can't reach its goal",
+                        robot
+                    )))
+                }
+            }
+        }
+
+        let mut nodes = FnvHashMap::default();
+        let mut to_visit = FocalQueue::new(self.suboptimality);
+        let root = self.create_node(Vec::new(), paths, lower_bounds, goals);
+        to_visit.push(0, root.lower_bound(), root.cost, root.priority());
+        nodes.insert(0, root);
+        let mut nr_nodes = 1;
+
+        while let Some(index) = to_visit.pop() {
+            let node = nodes.remove(&index).unwrap();
+            let (time, conflict) = match node.conflict {
+                Some(conflict) => conflict,
+                None => return Ok(node.paths),
+            };
+            if started.elapsed() > self.time_limit {
+                return Err(NoSolutionError::new(format!(
+                    "No conflict free paths found within {:?}",
+                    self.time_limit
+                )));
+            }
+
+            for constraint in ConflictBasedSearch::resolve(&node.paths, goals, time, conflict) {
+                let robot = match constraint {
+                    Constraint::Vertex { robot, .. } | Constraint::Edge { robot, .. } => robot,
+                };
+                let mut constraints = node.constraints.clone();
+                constraints.push(constraint);
+                if let Some((path, lower_bound)) =
+                    self.find_path(robot, starts, goals, &constraints, &node.paths)
+                {
+                    let mut paths = node.paths.clone();
+                    paths[robot] = path;
+                    let mut lower_bounds = node.lower_bounds.clone();
+                    lower_bounds[robot] = lower_bound;
+                    let child = self.create_node(constraints, paths, lower_bounds, goals);
+                    to_visit.push(nr_nodes, child.lower_bound(), child.cost, child.priority());
+                    nodes.insert(nr_nodes, child);
+                    nr_nodes += 1;
+                }
+            }
+        }
+
+        Err(NoSolutionError::new(
+            "The goals can't be reached without conflicts".to_string(),
+        ))
+    }
+    /// A path for a single robot that satisfies those constraints that apply to it, with as few
+    /// conflicts with the paths of the other robots as the suboptimality factor allows.
+    fn find_path(
+        &self,
+        robot: usize,
+        starts: &Vec<Vertex>,
+        goals: &Vec<Goal>,
+        constraints: &Vec<Constraint>,
+        paths: &Vec<Vec<Vertex>>,
+    ) -> Option<(Vec<Vertex>, u64)> {
+        let mut robot_constraints = Constraints::default();
+        for &constraint in constraints.iter() {
+            match constraint {
+                Constraint::Vertex {
+                    robot: constrained,
+                    vertex,
+                    time,
+                } if constrained == robot => robot_constraints.forbid_vertex(vertex, time),
+                Constraint::Edge {
+                    robot: constrained,
+                    from,
+                    to,
+                    time,
+                } if constrained == robot => robot_constraints.forbid_edge(from, to, time),
+                _ => (),
+            }
+        }
+
+        let forbid_following = self.settings.forbid_following;
+        let conflicts = |from: Vertex, to: Vertex, time: usize| {
+            paths
+                .iter()
+                .enumerate()
+                .filter(|&(other, path)| {
+                    let before = position(path, goals[other], time - 1);
+                    let after = position(path, goals[other], time);
+                    other != robot
+                        && (after == Some(to)
+                            || from != to
+                                && before == Some(to)
+                                && (after == Some(from) || forbid_following))
+                })
+                .count()
+        };
+
+        find_path(
+            self.plan,
+            starts[robot],
+            goals[robot],
+            &self.distances[&goals[robot].vertex()],
+            &robot_constraints,
+            self.suboptimality,
+            &conflicts,
+        )
+    }
+    fn create_node(
+        &self,
+        constraints: Vec<Constraint>,
+        paths: Vec<Vec<Vertex>>,
+        lower_bounds: Vec<u64>,
+        goals: &Vec<Goal>,
+    ) -> Node {
+        let cost = paths.iter().map(|path| path.len() as u64 - 1).sum();
+        let (conflict, nr_conflicts) = self.find_conflicts(&paths, goals);
+
+        Node {
+            constraints,
+            paths,
+            lower_bounds,
+            cost,
+            conflict,
+            nr_conflicts,
+        }
+    }
+    /// The earliest conflict between the paths, and the number of conflicts.
+    ///
+    /// Robots that served their goal are no longer on the plan, as they get a new goal and their
+    /// paths are planned again by then.
+    fn find_conflicts(
+        &self,
+        paths: &Vec<Vec<Vertex>>,
+        goals: &Vec<Goal>,
+    ) -> (Option<(usize, Conflict)>, usize) {
+        let length = paths.iter().map(|path| path.len()).max().unwrap_or(0);
+        let robot_states = |time| {
+            paths
+                .iter()
+                .zip(goals.iter())
+                .enumerate()
+                .map(|(robot_id, (path, &goal))| RobotState {
+                    robot_id,
+                    parcel_id: None,
+                    vertex: position(path, goal, time),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut earliest = None;
+        let mut nr_conflicts = 0;
+        let mut previous = robot_states(0);
+        for time in 1..length {
+            let next = robot_states(time);
+            let conflicts = find_conflicts(&previous, &next, self.settings.forbid_following);
+            if earliest.is_none() {
+                earliest = conflicts.first().map(|&conflict| (time, conflict));
+            }
+            nr_conflicts += conflicts.len();
+            previous = next;
+        }
+
+        (earliest, nr_conflicts)
+    }
+    /// The two constraints of which one of the two robots needs to satisfy, to resolve the
+    /// conflict in the step towards the time.
+    fn resolve(
+        paths: &Vec<Vec<Vertex>>,
+        goals: &Vec<Goal>,
+        time: usize,
+        conflict: Conflict,
+    ) -> Vec<Constraint> {
+        let Conflict {
+            kind,
+            robot_id,
+            other_robot_id,
+            vertex,
+        } = conflict;
+        match kind {
+            ConflictKind::Vertex => vec![
+                Constraint::Vertex {
+                    robot: robot_id,
+                    vertex,
+                    time,
+                },
+                Constraint::Vertex {
+                    robot: other_robot_id,
+                    vertex,
+                    time,
+                },
+            ],
+            ConflictKind::Swap => {
+                let from = position(&paths[robot_id], goals[robot_id], time - 1).unwrap();
+                vec![
+                    Constraint::Edge {
+                        robot: robot_id,
+                        from,
+                        to: vertex,
+                        time,
+                    },
+                    Constraint::Edge {
+                        robot: other_robot_id,
+                        from: vertex,
+                        to: from,
+                        time,
+                    },
+                ]
+            }
+            // Either the robot arrives later, or the other robot leaves earlier
+            ConflictKind::Following => vec![
+                Constraint::Vertex {
+                    robot: robot_id,
+                    vertex,
+                    time,
+                },
+                Constraint::Vertex {
+                    robot: other_robot_id,
+                    vertex,
+                    time: time - 1,
+                },
+            ],
+        }
+    }
+    /// The location of each robot after a step in which robots move closer to their goal when
+    /// they can, for when no paths were found.
+    ///
+    /// Robots only move to vertices that no robot is at, such that they can't collide or follow
+    /// each other.
+    fn greedy_locations(&self, state: &State, goals: &Vec<Goal>) -> Vec<Vertex> {
+        let mut occupied = state
+            .robot_states
+            .iter()
+            .map(|robot| robot.vertex.unwrap())
+            .collect::<FnvHashSet<_>>();
+
+        state
+            .robot_states
+            .iter()
+            .map(|robot| {
+                let vertex = robot.vertex.unwrap();
+                let distances = &self.distances[&goals[robot.robot_id].vertex()];
+                let next = self
+                    .plan
+                    .neighbors(&vertex)
+                    .into_iter()
+                    .filter(|neighbor| !occupied.contains(neighbor))
+                    .find(|neighbor| distances.get(neighbor) < distances.get(&vertex));
+                match next {
+                    Some(next) => {
+                        occupied.insert(next);
+                        next
+                    }
+                    None => vertex,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<'p, 's, 'a> PathAlgorithm<'p, 's, 'a> for ConflictBasedSearch<'p, 's, 'a> {
+    fn initialize(&mut self) -> Result<(), NoSolutionError> {
+        Ok(())
+    }
+
+    fn next_step(&mut self, history: &History) -> Instructions {
+        let state = history.last_state();
+        if self.contains_new_requests(history) {
+            self.assignment = assign_requests(
+                self.plan,
+                self.settings,
+                &mut *self.assignment_algorithm,
+                state,
+            );
+        }
+
+        let goals = goals(&self.assignment, state, &self.goals);
+        let step = history.time() - self.planned_at;
+        if goals != self.goals || !self.follows_paths(state, step) {
+            let starts = state
+                .robot_states
+                .iter()
+                .map(|robot| robot.vertex.unwrap())
+                .collect();
+            match self.find_paths(&starts, &goals) {
+                Ok(paths) => {
+                    self.paths = paths;
+                    self.planned_at = history.time();
+                }
+                Err(error) => {
+                    self.failures.push((history.time(), error));
+                    self.paths.clear();
+                    let new_locations = self.greedy_locations(state, &goals);
+                    self.goals = goals;
+                    return instructions(&mut self.assignment, history, new_locations);
+                }
+            }
+            self.goals = goals;
+        }
+
+        let step = history.time() - self.planned_at;
+        let new_locations = self
+            .paths
+            .iter()
+            .map(|path| path[(step + 1).min(path.len() - 1)])
+            .collect();
+        instructions(&mut self.assignment, history, new_locations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use fnv::FnvHashMap;
+
+    use algorithm::assignment::greedy_makespan::GreedyMakespan;
+    use algorithm::path::cbs::ConflictBasedSearch;
+    use algorithm::path::goals::Goal;
+    use algorithm::path::PathAlgorithm;
+    use algorithm::solver::SolverSettings;
+    use simulation::conflict::find_conflicts;
+    use simulation::demand::Request;
+    use simulation::placement::Placement;
+    use simulation::plan::grid_map::GridMap;
+    use simulation::plan::Vertex;
+    use simulation::settings::Settings;
+    use simulation::state::History;
+    use simulation::state::RobotState;
+    use simulation::state::State;
+    use simulation::MoveInstruction;
+    use simulation::PlacementInstruction;
+
+    /// A corridor along the bottom, with two dead ends above it.
+    const CORRIDOR: &str = "type octile
+height 2
+width 4
+map
+@S@T
+....
+";
+
+    fn settings(nr_robots: usize, forbid_following: bool) -> Settings {
+        Settings {
+            total_time: 10,
+            nr_robots,
+            nr_requests: 2,
+            output_file: None,
+            forbid_following,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        }
+    }
+
+    fn robot_states(paths: &Vec<Vec<Vertex>>, time: usize) -> Vec<RobotState> {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(robot_id, path)| RobotState {
+                robot_id,
+                parcel_id: None,
+                vertex: path.get(time).cloned(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_paths() {
+        let plan = GridMap::from_map(CORRIDOR).unwrap();
+        let starts = vec![Vertex { x: 0, y: 0 }, Vertex { x: 3, y: 0 }];
+        let goals = vec![
+            Goal::Serve(Vertex { x: 3, y: 0 }),
+            Goal::Serve(Vertex { x: 0, y: 0 }),
+        ];
+
+        for &(forbid_following, suboptimality) in &[(false, 1_f64), (true, 1_f64), (true, 1.5_f64)]
+        {
+            let settings = settings(2, forbid_following);
+            let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+            let mut algorithm = ConflictBasedSearch::new(&plan, &settings, assignment_algorithm)
+                .with_suboptimality(suboptimality);
+
+            let paths = algorithm.find_paths(&starts, &goals).unwrap();
+            for time in 1..paths.iter().map(|path| path.len()).max().unwrap() {
+                assert_eq!(
+                    find_conflicts(
+                        &robot_states(&paths, time - 1),
+                        &robot_states(&paths, time),
+                        forbid_following,
+                    ),
+                    Vec::new()
+                );
+            }
+            // One robot makes way in the dead end next to its start, the other one waits for it
+            let cost = paths.iter().map(|path| path.len() - 1).sum::<usize>();
+            let minimum = if forbid_following { 13 } else { 10 };
+            assert!(minimum <= cost && cost as f64 <= suboptimality * minimum as f64);
+        }
+    }
+
+    #[test]
+    fn test_next_step() {
+        let plan = GridMap::from_map(CORRIDOR).unwrap();
+        let settings = settings(2, false);
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm = ConflictBasedSearch::new(&plan, &settings, assignment_algorithm);
+
+        let history = History {
+            states: vec![State {
+                robot_states: vec![
+                    RobotState {
+                        robot_id: 0,
+                        parcel_id: None,
+                        vertex: Some(Vertex { x: 1, y: 1 }),
+                    },
+                    RobotState {
+                        robot_id: 1,
+                        parcel_id: Some(1),
+                        vertex: Some(Vertex { x: 1, y: 0 }),
+                    },
+                ],
+                requests: map!
+                [
+                    0 => Request {
+                        from: Vertex { x: 1, y: 1 },
+                        to: Vertex { x: 3, y: 1 },
+                    },
+                    1 => Request {
+                        from: Vertex { x: 1, y: 1 },
+                        to: Vertex { x: 3, y: 1 },
+                    },
+                ],
+            }],
+            calculation_times: Vec::new(),
+        };
+
+        let instructions = algorithm.next_step(&history);
+        assert_eq!(
+            instructions.placements,
+            vec![PlacementInstruction {
+                robot_id: 0,
+                parcel: 0,
+                vertex: Vertex { x: 1, y: 1 },
+            }]
+        );
+        assert_eq!(
+            instructions.movements,
+            vec![MoveInstruction {
+                robot_id: 1,
+                vertex: Vertex { x: 2, y: 0 },
+            }]
+        );
+        assert!(algorithm.failures.is_empty());
+    }
+}
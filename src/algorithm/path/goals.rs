@@ -0,0 +1,168 @@
+use algorithm::assignment::AssignmentAlgorithm;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::settings::Settings;
+use simulation::state::History;
+use simulation::state::State;
+use simulation::Instructions;
+use simulation::MoveInstruction;
+use simulation::PlacementInstruction;
+use simulation::RemovalInstruction;
+use std::iter::repeat;
+
+/// The vertex that a robot is planned to go to.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Goal {
+    /// The robot picks up or delivers a parcel at the vertex, which takes a step, after which it
+    /// gets a new goal
+    Serve(Vertex),
+    /// The robot has no request to serve and stays at the vertex, unless it needs to make way
+    Park(Vertex),
+}
+
+impl Goal {
+    pub fn vertex(&self) -> Vertex {
+        match self {
+            Goal::Serve(vertex) | Goal::Park(vertex) => *vertex,
+        }
+    }
+}
+
+/// Assigns the requests that are not yet picked up, robots carrying a parcel first deliver it.
+pub fn assign_requests<'p, 's, 'a>(
+    plan: &Plan,
+    settings: &Settings,
+    assignment_algorithm: &mut (AssignmentAlgorithm<'p, 's> + 'a),
+    state: &State,
+) -> Vec<Vec<usize>> {
+    let carried = state
+        .robot_states
+        .iter()
+        .filter_map(|robot| robot.parcel_id)
+        .collect::<FnvHashSet<_>>();
+    let open_requests = state
+        .requests
+        .iter()
+        .filter(|&(id, _)| !carried.contains(id))
+        .map(|(&id, &request)| (id, request))
+        .collect::<FnvHashMap<_, _>>();
+    let availability = state
+        .robot_states
+        .iter()
+        .map(|robot| {
+            let vertex = robot.vertex.unwrap();
+            match robot.parcel_id {
+                Some(parcel) => {
+                    let to = state.requests.get(&parcel).unwrap().to;
+                    (plan.path_length(vertex, to) as usize + 1, to)
+                }
+                None => (0, vertex),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut assignment = if open_requests.len() > 0 {
+        assignment_algorithm.calculate_assignment(&open_requests, &availability)
+    } else {
+        repeat(Vec::with_capacity(0))
+            .take(settings.nr_robots)
+            .collect()
+    };
+    for robot in state.robot_states.iter() {
+        if let Some(parcel) = robot.parcel_id {
+            assignment[robot.robot_id].insert(0, parcel);
+        }
+    }
+
+    assignment
+}
+
+/// The goal of each robot: the vertex of the request it serves next, or the vertex it parked at
+/// according to the `previous` goals.
+pub fn goals(assignment: &Vec<Vec<usize>>, state: &State, previous: &Vec<Goal>) -> Vec<Goal> {
+    state
+        .robot_states
+        .iter()
+        .map(|robot| match assignment[robot.robot_id].first() {
+            Some(request_id) => {
+                let request = state.requests.get(request_id).unwrap();
+                if robot.parcel_id.is_some() {
+                    Goal::Serve(request.to)
+                } else {
+                    Goal::Serve(request.from)
+                }
+            }
+            None => match previous.get(robot.robot_id) {
+                // Robots that made way return to where they parked
+                Some(&Goal::Park(vertex)) => Goal::Park(vertex),
+                _ => Goal::Park(robot.vertex.unwrap()),
+            },
+        })
+        .collect()
+}
+
+/// Position of a robot at a time along its path towards the goal.
+///
+/// Robots that served their goal are no longer on the path, as they get a new goal and their
+/// paths are planned again by then.
+pub fn position(path: &Vec<Vertex>, goal: Goal, time: usize) -> Option<Vertex> {
+    match goal {
+        _ if time < path.len() => Some(path[time]),
+        Goal::Serve(_) => None,
+        Goal::Park(vertex) => Some(vertex),
+    }
+}
+
+/// Robots at their goal pick up or deliver their parcel, the others move to their new location.
+///
+/// Delivered requests are removed from the assignment.
+pub fn instructions(
+    assignment: &mut Vec<Vec<usize>>,
+    history: &History,
+    new_locations: Vec<Vertex>,
+) -> Instructions {
+    let mut instructions = Instructions {
+        movements: Vec::new(),
+        placements: Vec::new(),
+        removals: Vec::new(),
+        robot_removals: Vec::new(),
+    };
+
+    for (robot_id, new_location) in new_locations.into_iter().enumerate() {
+        let previous_state = history.last_robot_state(robot_id);
+        let previous_location = previous_state.vertex.unwrap();
+        if let Some(parcel) = previous_state.parcel_id {
+            let goal_location = history.last_state().requests.get(&parcel).unwrap().to;
+            if previous_location == goal_location {
+                instructions.removals.push(RemovalInstruction {
+                    parcel,
+                    robot_id,
+                    vertex: previous_location,
+                });
+                assignment[robot_id].remove(0);
+                continue;
+            }
+        } else if let Some(&request_id) = assignment[robot_id].first() {
+            let goal_location = history.last_state().requests.get(&request_id).unwrap().from;
+            if previous_location == goal_location {
+                instructions.placements.push(PlacementInstruction {
+                    robot_id,
+                    parcel: request_id,
+                    vertex: goal_location,
+                });
+                continue;
+            }
+        }
+
+        if new_location != previous_location {
+            instructions.movements.push(MoveInstruction {
+                robot_id,
+                vertex: new_location,
+            });
+        }
+    }
+
+    instructions
+}
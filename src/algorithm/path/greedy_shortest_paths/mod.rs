@@ -1,7 +1,9 @@
 use algorithm::assignment::AssignmentAlgorithm;
 use algorithm::path::greedy_shortest_paths::time_graph::TimeGraph;
+use algorithm::path::token_passing::parking;
 use algorithm::path::PathAlgorithm;
 use algorithm::NoSolutionError;
+use fnv::FnvHashMap;
 use fnv::FnvHashSet;
 use simulation::demand::Request;
 use simulation::plan::Plan;
@@ -14,38 +16,50 @@ use simulation::Instructions;
 use simulation::MoveInstruction;
 use simulation::PlacementInstruction;
 use simulation::RemovalInstruction;
-use simulation::RobotRemovalInstruction;
 use std::iter::repeat;
 
+pub mod safe_intervals;
 pub mod time_graph;
 
 pub struct GreedyShortestPaths<'p, 's, 'a> {
     time_graph: TimeGraph<'p>,
+    /// Vertices away from the pickups and deliveries, at which the itineraries end
+    parking: FnvHashSet<Vertex>,
     settings: &'s Settings,
     assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
 
     time: usize,
     assignment: Vec<Vec<usize>>,
-    // (robot), (parcel, path)
-    active_paths: Vec<Option<(usize, Option<PathType>)>>,
+    // (robot), (parcel, itinerary)
+    active_paths: Vec<Option<(usize, Option<Itinerary>)>>,
     // parcel
     active_requests: FnvHashSet<usize>,
 }
 
+/// The path of a robot along the source and the destination of a request, to a parking vertex.
+/// The robot stays at the source for a step to pick up the parcel at `pickup_time`, and at the
+/// destination to deliver it at `delivery_time`.
 #[derive(Clone, Debug, PartialEq, Eq)]
-enum PathType {
-    Pickup(Path),
-    Delivery(Path),
+struct Itinerary {
+    path: Path,
+    pickup_time: usize,
+    delivery_time: usize,
 }
 
 impl<'p, 's, 'a> GreedyShortestPaths<'p, 's, 'a> {
     pub fn new(
-        plan: &'p impl Plan,
+        plan: &'p Plan,
         settings: &'s Settings,
-        assignment_algorithm: Box<impl AssignmentAlgorithm<'p, 's> + 'a>,
+        assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
     ) -> GreedyShortestPaths<'p, 's, 'a> {
+        let time_graph = TimeGraph::from_plan(plan);
         GreedyShortestPaths {
-            time_graph: TimeGraph::from_plan(plan, settings.total_time),
+            time_graph: if settings.forbid_following {
+                time_graph.with_buffer()
+            } else {
+                time_graph
+            },
+            parking: parking(plan, settings.nr_robots).into_iter().collect(),
             settings,
             assignment_algorithm,
 
@@ -57,40 +71,67 @@ impl<'p, 's, 'a> GreedyShortestPaths<'p, 's, 'a> {
             active_requests: FnvHashSet::default(),
         }
     }
+    /// Keeps robots a step apart, even when the simulation allows them to follow each other.
+    pub fn with_buffer(mut self) -> Self {
+        self.time_graph = self.time_graph.with_buffer();
+        self
+    }
     /// Requires up-to-date assignments
+    ///
+    /// Robots without an itinerary are parked in the time graph, such that no path goes through
+    /// them. The itineraries end at a parking vertex, where the robot can stay until its next
+    /// itinerary without blocking any request.
     fn update_paths(&mut self, last_state: &State) {
-        for (robot, mut task) in self.active_paths.iter_mut().enumerate() {
-            if let (None, Some(parcel)) = (task.clone(), self.assignment[robot].pop()) {
-                *task = Some((parcel, None));
+        for (robot, task) in self.active_paths.iter_mut().enumerate() {
+            let delivered = match task {
+                Some((_, Some(itinerary))) => self.time > itinerary.path.end_time(),
+                _ => false,
+            };
+            if delivered {
+                if let Some((parcel, _)) = task.take() {
+                    self.active_requests.remove(&parcel);
+                }
+            }
+            if task.is_none() {
+                if let Some(parcel) = self.assignment[robot].pop() {
+                    self.active_requests.insert(parcel);
+                    *task = Some((parcel, None));
+                }
             }
 
+            let has_itinerary = match task {
+                Some((_, Some(_))) => true,
+                _ => false,
+            };
+            if let (false, Some(vertex)) = (has_itinerary, last_state.robot_states[robot].vertex) {
+                let park_time = self.time_graph.unpark(vertex).unwrap_or(self.time);
+                self.time_graph.park(vertex, park_time);
+            }
+        }
+
+        for (robot, task) in self.active_paths.iter_mut().enumerate() {
+            // Planning the pickup and delivery at once, such that no other robot takes the
+            // source while the parcel is picked up
             let placed = last_state.robot_states[robot].vertex;
-            if let (Some(current_vertex), Some((parcel, maybe_path))) = (placed, task) {
-                match maybe_path {
-                    None => {
-                        let Request { from, to } = last_state.requests.get(&parcel).unwrap();
-                        if current_vertex == *from {
-                            if let Some(path) = self.time_graph.find_path(self.time + 1, *from, *to)
-                            {
-                                self.time_graph.remove_path(&path);
-                                *maybe_path = Some(PathType::Delivery(path));
-                            }
-                        } else {
-                            if let Some(path) =
-                                self.time_graph.find_path(self.time, current_vertex, *from)
-                            {
-                                self.time_graph.remove_path(&path);
-                                *maybe_path = Some(PathType::Pickup(path));
-                            }
-                        }
-                    }
-                    Some(PathType::Pickup(path)) => if self.time > path.end_time() {
-                        println!("{:?}, {:?}", path, (path.end_time(), self.time));
-                        *maybe_path = None;
-                    },
-                    Some(PathType::Delivery(path)) => if self.time > path.end_time() {
-                        *maybe_path = None;
-                    },
+            if let (Some(current_vertex), Some((parcel, None))) = (placed, task.clone()) {
+                let Request { from, to } = last_state.requests[&parcel];
+                let park_time = self.time_graph.unpark(current_vertex);
+                let goals = [from, to];
+                if let Some((path, goal_times)) =
+                    self.time_graph
+                        .find_safe_tour(self.time, current_vertex, &goals, &self.parking)
+                {
+                    self.time_graph.remove_path(&path);
+                    self.time_graph
+                        .park(*path.nodes.last().unwrap(), path.end_time() + 1);
+                    let itinerary = Itinerary {
+                        path,
+                        pickup_time: goal_times[0],
+                        delivery_time: goal_times[1],
+                    };
+                    *task = Some((parcel, Some(itinerary)));
+                } else if let Some(park_time) = park_time {
+                    self.time_graph.park(current_vertex, park_time);
                 }
             }
         }
@@ -105,54 +146,36 @@ impl<'p, 's, 'a> GreedyShortestPaths<'p, 's, 'a> {
         debug_assert!(self.active_paths.len() > 0);
 
         let previous_vertex = previous_state.robot_states[robot_id].vertex.unwrap();
-        if let &Some((parcel, ref maybe_path)) = &self.active_paths[robot_id] {
-            match maybe_path {
-                Some(PathType::Pickup(path)) => {
-                    debug_assert!(self.time >= path.start_time);
-
-                    if self.time == path.end_time() {
-                        instructions.placements.push(PlacementInstruction {
-                            robot_id,
-                            parcel,
-                            vertex: previous_vertex,
-                        });
-                    } else {
-                        debug_assert!(self.time < path.end_time());
-
-                        let next_state_location = path.nodes[1 + self.time - path.start_time];
-                        if previous_vertex != next_state_location {
-                            debug_assert!(previous_vertex.distance(next_state_location) == 1);
-                            instructions.movements.push(MoveInstruction {
-                                robot_id,
-                                vertex: next_state_location,
-                            });
-                        }
-                    }
-                }
-                Some(PathType::Delivery(path)) => {
-                    if self.time == path.end_time() {
-                        instructions.removals.push(RemovalInstruction {
-                            robot_id,
-                            parcel,
-                            vertex: previous_vertex,
-                        });
-                    } else {
-                        debug_assert!(self.time < path.end_time());
-
-                        let next_state_location = path.nodes[1 + self.time - path.start_time];
-                        if previous_vertex != next_state_location {
-                            debug_assert!(previous_vertex.distance(next_state_location) == 1);
-                            instructions.movements.push(MoveInstruction {
-                                robot_id,
-                                vertex: next_state_location,
-                            });
-                        }
-                    }
-                }
-                None => instructions.robot_removals.push(RobotRemovalInstruction {
+        // Robots without an itinerary stay where they are
+        if let Some((parcel, Some(itinerary))) = &self.active_paths[robot_id] {
+            let Itinerary {
+                path,
+                pickup_time,
+                delivery_time,
+            } = itinerary;
+            debug_assert!(self.time >= path.start_time);
+
+            if self.time == *pickup_time {
+                instructions.placements.push(PlacementInstruction {
+                    robot_id,
+                    parcel: *parcel,
+                    vertex: previous_vertex,
+                });
+            } else if self.time == *delivery_time {
+                instructions.removals.push(RemovalInstruction {
                     robot_id,
+                    parcel: *parcel,
                     vertex: previous_vertex,
-                }),
+                });
+            } else if self.time < path.end_time() {
+                let next_state_location = path.nodes[1 + self.time - path.start_time];
+                if previous_vertex != next_state_location {
+                    debug_assert!(previous_vertex.distance(next_state_location) == 1);
+                    instructions.movements.push(MoveInstruction {
+                        robot_id,
+                        vertex: next_state_location,
+                    });
+                }
             }
         }
     }
@@ -161,32 +184,24 @@ impl<'p, 's, 'a> GreedyShortestPaths<'p, 's, 'a> {
             .requests
             .iter()
             .filter(|&(id, _)| !self.active_requests.contains(id))
-            .collect::<Vec<_>>();
+            .map(|(&id, &request)| (id, request))
+            .collect::<FnvHashMap<_, _>>();
 
         let availability = self.get_earliest_availability(state);
         self.assignment = self
             .assignment_algorithm
-            .calculate_assignment(&state.requests, &availability);
+            .calculate_assignment(&unassigned_requests, &availability);
     }
     fn get_earliest_availability(&self, state: &State) -> Vec<(usize, Vertex)> {
         self.active_paths
             .iter()
             .enumerate()
-            .map(|(robot, maybe_path)| match maybe_path {
-                None => (self.time, state.robot_states[robot].vertex.unwrap()),
-                Some((request_id, maybe_path)) => match maybe_path {
-                    Some(path) => {
-                        let path = match path {
-                            PathType::Pickup(path) => path,
-                            PathType::Delivery(path) => path,
-                        };
-                        (
-                            path.start_time + path.nodes.len() - 1 + 2,
-                            *path.nodes.last().unwrap(),
-                        )
-                    }
-                    None => (self.time, state.robot_states[robot].vertex.unwrap()),
-                },
+            .map(|(robot, task)| match task {
+                // The path reserves the end for a step after it
+                Some((_, Some(Itinerary { path, .. }))) => {
+                    (path.end_time() + 2, *path.nodes.last().unwrap())
+                }
+                _ => (self.time, state.robot_states[robot].vertex.unwrap()),
             })
             .collect::<Vec<_>>()
     }
@@ -236,7 +251,7 @@ impl Path {
     fn length(&self) -> usize {
         self.nodes.len()
     }
-    fn end_time(&self) -> usize {
+    pub fn end_time(&self) -> usize {
         self.start_time + self.nodes.len() - 1
     }
 }
@@ -246,16 +261,37 @@ mod test {
 
     use algorithm::assignment::greedy_makespan::GreedyMakespan;
     use algorithm::path::greedy_shortest_paths::GreedyShortestPaths;
+    use algorithm::path::greedy_shortest_paths::Itinerary;
     use algorithm::path::greedy_shortest_paths::Path;
-    use algorithm::path::greedy_shortest_paths::PathType;
+    use algorithm::solver::SolverSettings;
     use fnv::FnvHashMap;
     use simulation::demand::Request;
+    use simulation::placement::Placement;
     use simulation::plan::one_three_rectangle::OneThreeRectangle;
     use simulation::plan::Vertex;
     use simulation::settings::Settings;
     use simulation::state::RobotState;
     use simulation::state::State;
 
+    /// The parcel of a robot, its path up to the time, and its pickup and delivery times, after
+    /// checking that the path ends at a parking vertex.
+    fn planned(
+        algorithm: &GreedyShortestPaths,
+        robot: usize,
+        time: usize,
+    ) -> (usize, Path, usize, usize) {
+        let (parcel, itinerary) = algorithm.active_paths[robot].clone().unwrap();
+        let Itinerary {
+            mut path,
+            pickup_time,
+            delivery_time,
+        } = itinerary.unwrap();
+        assert!(algorithm.parking.contains(path.nodes.last().unwrap()));
+        path.nodes.truncate(time + 1 - path.start_time);
+
+        (parcel, path, pickup_time, delivery_time)
+    }
+
     #[test]
     fn test_calculate_paths_single_3_3() {
         let plan = OneThreeRectangle::new(3, 3);
@@ -264,6 +300,9 @@ mod test {
             nr_robots: 1,
             nr_requests: 1,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let source = Vertex { x: 0, y: 1 };
         let terminal = Vertex { x: 2, y: 1 };
@@ -285,16 +324,11 @@ mod test {
         let mut algorithm = GreedyShortestPaths::new(&plan, &settings, assignment_algorithm);
         algorithm.update_assignment(&state);
         algorithm.update_paths(&state);
-        assert_eq!(
-            algorithm.active_paths,
-            vec![Some((
-                0 as usize,
-                Some(PathType::Delivery(Path {
-                    start_time: 2,
-                    nodes: vec![source, Vertex { x: 1, y: 1 }, terminal],
-                })),
-            ))]
-        );
+        let path = Path {
+            start_time: 1,
+            nodes: vec![source, source, Vertex { x: 1, y: 1 }, terminal],
+        };
+        assert_eq!(planned(&algorithm, 0, 4), (0, path, 1, 4));
     }
 
     #[test]
@@ -305,6 +339,9 @@ mod test {
             nr_robots: 1,
             nr_requests: 1,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let mut assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
         let mut algorithm = GreedyShortestPaths::new(&plan, &settings, assignment_algorithm);
@@ -325,16 +362,19 @@ mod test {
 
         algorithm.update_assignment(&state);
         algorithm.update_paths(&state);
+        let delivery_time = 3 + 1 + from.distance(to) as usize;
+        let (parcel, path, pickup_time, planned_delivery_time) =
+            planned(&algorithm, 0, delivery_time);
+        assert_eq!(
+            (parcel, pickup_time, planned_delivery_time),
+            (0, 3, delivery_time)
+        );
         assert_eq!(
-            algorithm.active_paths,
-            vec![Some((
-                0 as usize,
-                Some(PathType::Pickup(Path {
-                    start_time: 1,
-                    nodes: vec![Vertex { x: 0, y: 1 }, Vertex { x: 0, y: 2 }, from],
-                })),
-            ))]
+            path.nodes[..4],
+            [Vertex { x: 0, y: 1 }, Vertex { x: 0, y: 2 }, from, from]
         );
+        // One of the shortest paths from the source on
+        assert_eq!(path.nodes.last(), Some(&to));
     }
 
     #[test]
@@ -357,6 +397,9 @@ mod test {
             nr_robots: 2,
             nr_requests: requests.len() as u64,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let state = State {
             robot_states: vec![
@@ -373,34 +416,49 @@ mod test {
             ],
             requests,
         };
-        let mut assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let first = (
+            1,
+            Path {
+                start_time: 1,
+                nodes: vec![source, source, Vertex { x: 1, y: 1 }, terminal],
+            },
+            1,
+            4,
+        );
+
+        // Following the other robot into the source as it leaves
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
         let mut algorithm = GreedyShortestPaths::new(&plan, &settings, assignment_algorithm);
+        algorithm.update_assignment(&state);
+        algorithm.update_paths(&state);
 
+        assert_eq!(planned(&algorithm, 0, 4), first.clone());
+        let (parcel, path, pickup_time, _) = planned(&algorithm, 1, 3);
+        assert_eq!((parcel, pickup_time), (0, 3));
+        assert_eq!(
+            path.nodes,
+            vec![Vertex { x: 0, y: 0 }, Vertex { x: 0, y: 0 }, source]
+        );
+
+        // Staying a step behind with the buffer
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm =
+            GreedyShortestPaths::new(&plan, &settings, assignment_algorithm).with_buffer();
         algorithm.update_assignment(&state);
         algorithm.update_paths(&state);
 
-        let expected = vec![
-            Some((
-                1,
-                Some(PathType::Delivery(Path {
-                    start_time: 2,
-                    nodes: vec![Vertex { x: 0, y: 1 }, Vertex { x: 1, y: 1 }, terminal],
-                })),
-            )),
-            Some((
-                0,
-                Some(PathType::Pickup(Path {
-                    start_time: 1,
-                    nodes: vec![
-                        Vertex { x: 0, y: 0 },
-                        Vertex { x: 0, y: 0 },
-                        Vertex { x: 0, y: 0 },
-                        source,
-                    ],
-                })),
-            )),
-        ];
-        assert_eq!(algorithm.active_paths, expected);
+        assert_eq!(planned(&algorithm, 0, 4), first);
+        let (parcel, path, pickup_time, _) = planned(&algorithm, 1, 4);
+        assert_eq!((parcel, pickup_time), (0, 4));
+        assert_eq!(
+            path.nodes,
+            vec![
+                Vertex { x: 0, y: 0 },
+                Vertex { x: 0, y: 0 },
+                Vertex { x: 0, y: 0 },
+                source,
+            ]
+        );
     }
 
     #[test]
@@ -423,6 +481,9 @@ mod test {
             nr_robots: 2,
             nr_requests: requests.len() as u64,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let state = State {
             robot_states: vec![
@@ -444,16 +505,12 @@ mod test {
 
         algorithm.update_assignment(&state);
         algorithm.update_paths(&state);
-        let expected = vec![
-            Some((
-                1,
-                Some(PathType::Delivery(Path {
-                    start_time: 2,
-                    nodes: vec![source, Vertex { x: 1, y: 1 }, terminal],
-                })),
-            )),
-            Some((0, None)),
-        ];
-        assert_eq!(algorithm.active_paths, expected);
+        let path = Path {
+            start_time: 1,
+            nodes: vec![source, source, Vertex { x: 1, y: 1 }, terminal],
+        };
+        assert_eq!(planned(&algorithm, 0, 4), (1, path, 1, 4));
+        // Starting where the other robot picks up its parcel
+        assert_eq!(algorithm.active_paths[1], Some((0, None)));
     }
 }
@@ -0,0 +1,193 @@
+use fnv::FnvHashMap;
+use simulation::plan::Vertex;
+use std::cmp::Ordering;
+
+/// The times at which each vertex is free, as sorted intervals that don't touch.
+///
+/// Only the vertices that are taken at some time are stored, the others are always free.
+#[derive(Default)]
+pub struct SafeIntervals {
+    intervals: FnvHashMap<Vertex, Vec<Interval>>,
+}
+
+const ALWAYS: &[Interval] = &[Interval {
+    start: 0,
+    end: usize::max_value(),
+}];
+
+/// The times from `start` up to, but not including, `end`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Interval {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SafeIntervals {
+    pub fn get(&self, vertex: Vertex) -> &[Interval] {
+        self.intervals
+            .get(&vertex)
+            .map_or(ALWAYS, |intervals| intervals.as_slice())
+    }
+    /// The index of the interval in which the vertex is free at the time, if it is.
+    pub fn index_of(&self, vertex: Vertex, time: usize) -> Option<usize> {
+        position(self.get(vertex), time).ok()
+    }
+    /// The index of the first interval that ends after the time.
+    pub fn first_from(&self, vertex: Vertex, time: usize) -> usize {
+        match position(self.get(vertex), time) {
+            Ok(index) | Err(index) => index,
+        }
+    }
+    /// The time from which the vertex stays free.
+    pub fn free_from(&self, vertex: Vertex) -> usize {
+        self.get(vertex).last().unwrap().start
+    }
+    /// The time from which all vertices stay free.
+    pub fn all_free_from(&self) -> usize {
+        self.intervals
+            .values()
+            .map(|intervals| intervals.last().unwrap().start)
+            .max()
+            .unwrap_or(0)
+    }
+    /// Takes the vertex at the time.
+    pub fn remove(&mut self, vertex: Vertex, time: usize) {
+        let intervals = self
+            .intervals
+            .entry(vertex)
+            .or_insert_with(|| ALWAYS.to_vec());
+        if let Ok(index) = position(intervals, time) {
+            let Interval { start, end } = intervals[index];
+            let mut remaining = Vec::with_capacity(2);
+            if start < time {
+                remaining.push(Interval { start, end: time });
+            }
+            if time + 1 < end {
+                remaining.push(Interval {
+                    start: time + 1,
+                    end,
+                });
+            }
+            intervals.splice(index..(index + 1), remaining);
+        }
+    }
+    /// Frees the vertex at the time.
+    pub fn insert(&mut self, vertex: Vertex, time: usize) {
+        let intervals = match self.intervals.get_mut(&vertex) {
+            Some(intervals) => intervals,
+            None => return,
+        };
+        if let Err(index) = position(intervals, time) {
+            let joins_previous = index > 0 && intervals[index - 1].end == time;
+            let joins_next = index < intervals.len() && intervals[index].start == time + 1;
+            match (joins_previous, joins_next) {
+                (true, true) => {
+                    intervals[index - 1].end = intervals[index].end;
+                    intervals.remove(index);
+                }
+                (true, false) => intervals[index - 1].end = time + 1,
+                (false, true) => intervals[index].start = time,
+                (false, false) => intervals.insert(
+                    index,
+                    Interval {
+                        start: time,
+                        end: time + 1,
+                    },
+                ),
+            }
+        }
+    }
+    /// Forgets the intervals that end before the time, and the vertices that are free from then on.
+    pub fn clean_front(&mut self, time: usize) {
+        for intervals in self.intervals.values_mut() {
+            let passed = intervals
+                .iter()
+                .take_while(|interval| interval.end <= time)
+                .count();
+            intervals.drain(..passed);
+        }
+        self.intervals
+            .retain(|_, intervals| intervals.len() > 1 || intervals[0].start > time);
+    }
+    /// Approximate number of bytes that the intervals take, for the time graph benchmarks.
+    #[cfg(test)]
+    pub fn memory(&self) -> usize {
+        use std::mem::size_of;
+
+        self.intervals.capacity() * (size_of::<(Vertex, Vec<Interval>)>() + 1)
+            + self
+                .intervals
+                .values()
+                .map(|intervals| intervals.capacity() * size_of::<Interval>())
+                .sum::<usize>()
+    }
+}
+
+/// The index of the interval that contains the time, or of the first one after it.
+fn position(intervals: &[Interval], time: usize) -> Result<usize, usize> {
+    intervals.binary_search_by(|interval| {
+        if interval.end <= time {
+            Ordering::Less
+        } else if interval.start > time {
+            Ordering::Greater
+        } else {
+            Ordering::Equal
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_remove_insert() {
+        let mut safe_intervals = SafeIntervals::default();
+        let vertex = Vertex { x: 1, y: 1 };
+        assert_eq!(safe_intervals.get(vertex), ALWAYS);
+        safe_intervals.insert(vertex, 3);
+        assert_eq!(safe_intervals.all_free_from(), 0);
+
+        safe_intervals.remove(vertex, 3);
+        safe_intervals.remove(vertex, 5);
+        safe_intervals.remove(vertex, 5);
+        safe_intervals.remove(vertex, 0);
+        assert_eq!(
+            safe_intervals.get(vertex),
+            &[
+                Interval { start: 1, end: 3 },
+                Interval { start: 4, end: 5 },
+                Interval {
+                    start: 6,
+                    end: usize::max_value(),
+                },
+            ]
+        );
+        assert_eq!(safe_intervals.index_of(vertex, 4), Some(1));
+        assert_eq!(safe_intervals.index_of(vertex, 5), None);
+        assert_eq!(safe_intervals.first_from(vertex, 5), 2);
+        assert_eq!(safe_intervals.free_from(vertex), 6);
+        assert_eq!(safe_intervals.all_free_from(), 6);
+
+        safe_intervals.insert(vertex, 3);
+        safe_intervals.insert(vertex, 0);
+        assert_eq!(
+            safe_intervals.get(vertex),
+            &[
+                Interval { start: 0, end: 5 },
+                Interval {
+                    start: 6,
+                    end: usize::max_value(),
+                },
+            ]
+        );
+
+        safe_intervals.clean_front(5);
+        assert_eq!(safe_intervals.get(vertex).len(), 1);
+        safe_intervals.insert(vertex, 5);
+        assert_eq!(safe_intervals.free_from(vertex), 5);
+        // Free from then on
+        safe_intervals.clean_front(6);
+        assert_eq!(safe_intervals.get(vertex), ALWAYS);
+    }
+}
@@ -1,3 +1,4 @@
+use algorithm::path::greedy_shortest_paths::safe_intervals::SafeIntervals;
 use algorithm::path::greedy_shortest_paths::Path;
 use fnv::FnvHashMap;
 use fnv::FnvHashSet;
@@ -5,40 +6,105 @@ use priority_queue::PriorityQueue;
 use simulation::plan::Plan;
 use simulation::plan::Vertex;
 use std::cmp::Reverse;
-use std::collections::VecDeque;
 use std::iter::repeat;
 
 pub struct TimeGraph<'a> {
     plan: &'a Plan,
-    vertices: VecDeque<FnvHashSet<Vertex>>,
+    /// The times at which the vertices are free, which only holds the vertices that paths use
+    safe_intervals: SafeIntervals,
+    /// Vertices that are taken from a time on, by robots that stay there
+    parked: FnvHashMap<Vertex, usize>,
+    /// The moves of paths, from a vertex at a time to a neighbor a step later
+    moves: FnvHashSet<(usize, Vertex, Vertex)>,
+    /// Whether paths take their vertices a step before and after they are there
+    buffer: bool,
 
     earliest_time: usize,
-    capacity: usize,
 }
 
 impl<'a> TimeGraph<'a> {
-    pub fn from_plan(plan: &'a impl Plan, initial_capacity: usize) -> TimeGraph<'a> {
+    pub fn from_plan(plan: &'a Plan) -> TimeGraph<'a> {
         debug_assert!(plan.sources().len() > 0);
         debug_assert!(plan.terminals().len() > 0);
 
-        let plan_vertices = plan.vertices().into_iter().collect::<FnvHashSet<_>>();
-        let vertices = repeat(plan_vertices).take(initial_capacity + 1).collect();
-
         TimeGraph {
             plan,
-            vertices,
+            safe_intervals: SafeIntervals::default(),
+            parked: FnvHashMap::default(),
+            moves: FnvHashSet::default(),
+            buffer: false,
 
             earliest_time: 0,
-            capacity: initial_capacity + 1,
         }
     }
-    pub fn find_path(&mut self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+    /// Keeps robots a step apart, so that they never follow each other.
+    ///
+    /// Without the buffer, paths take only the vertices at which they are and the edges over
+    /// which they move, such that robots can follow each other closely but not swap.
+    pub fn with_buffer(mut self) -> Self {
+        self.buffer = true;
+        self
+    }
+    /// A path after which the robot can stay at `to` for a step, to pick up or deliver a parcel.
+    pub fn find_path(&self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+        self.find_path_to_stay(start_time, from, to, 1)
+    }
+    /// A path after which the robot can stay at `to` for the given number of steps.
+    pub fn find_path_to_stay(
+        &self,
+        start_time: usize,
+        from: Vertex,
+        to: Vertex,
+        steps: usize,
+    ) -> Option<Path> {
+        self.search(start_time, from, to, 0, steps)
+    }
+    /// A path after which the robot can stay at `to` until it is parked elsewhere, as no other
+    /// path uses `to` after this one ends.
+    pub fn find_path_to_park(&self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+        if self.parked.contains_key(&to) {
+            return None;
+        }
+
+        let earliest_index = self
+            .safe_intervals
+            .free_from(to)
+            .saturating_sub(self.earliest_time);
+        self.search(start_time, from, to, earliest_index, 1)
+    }
+    /// A* through the free vertices, ending at `to` no earlier than `earliest_index` and such that
+    /// `to` is free for the `steps` after.
+    fn search(
+        &self,
+        start_time: usize,
+        from: Vertex,
+        to: Vertex,
+        earliest_index: usize,
+        steps: usize,
+    ) -> Option<Path> {
         debug_assert_ne!(from, to);
 
         let start_index = start_time - self.earliest_time;
-        if !&self.vertices[start_index].contains(&from) {
+        if !self.is_free(start_index, from) {
             return None;
         }
+        // After the last reservation, any shortest path to `to` can be taken
+        let nr_vertices = self.plan.vertices().len();
+        let last_reserved_index = self
+            .safe_intervals
+            .all_free_from()
+            .saturating_sub(self.earliest_time);
+        let last_parked_index = self
+            .parked
+            .values()
+            .map(|&time| time.saturating_sub(self.earliest_time))
+            .max()
+            .unwrap_or(0);
+        let horizon = last_reserved_index
+            .max(last_parked_index)
+            .max(earliest_index)
+            .max(start_index)
+            + nr_vertices;
 
         let mut came_from = FnvHashMap::default();
         let mut visited = FnvHashSet::default();
@@ -50,13 +116,17 @@ impl<'a> TimeGraph<'a> {
 
         while let Some((current, _)) = to_visit.pop() {
             let (index, vertex) = current;
-            if vertex == to {
-                if self.vertices[index + 1].contains(&to) {
-                    return Some(TimeGraph::reconstruct_path(came_from, current, start_time));
-                }
+            if vertex == to
+                && index >= earliest_index
+                && (1..(steps + 1)).all(|step| self.is_free(index + step, to))
+            {
+                return Some(TimeGraph::reconstruct_path(came_from, current, start_time));
             }
 
             visited.insert(current);
+            if index == horizon {
+                continue;
+            }
 
             for neighbor in self.neighbors(vertex, index) {
                 if visited.contains(&neighbor) {
@@ -77,6 +147,163 @@ impl<'a> TimeGraph<'a> {
 
         None
     }
+    /// A path after which the robot can stay at `to` for a step, like `find_path`, found with Safe
+    /// Interval Path Planning.
+    ///
+    /// The states are the intervals in which a vertex is free rather than each time, and a robot
+    /// waits only as long as it needs to reach a neighbor in one of its intervals. This keeps the
+    /// search small when paths are long or when the robot has to wait long.
+    pub fn find_safe_path(&self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+        debug_assert_ne!(from, to);
+
+        self.safe_search(start_time, from, &[to], None)
+            .map(|(path, _)| path)
+    }
+    /// A path that visits the goals in order and stays at each of them for a step, after which
+    /// the robot parks at one of the parking vertices. The times at which the robot is at the
+    /// goals, before it stays there, are returned with the path.
+    ///
+    /// The steps at the goals are part of the path, so that no other path takes the goals in
+    /// between. No other path uses the vertex at which the path ends, like with
+    /// `find_path_to_park`.
+    pub fn find_safe_tour(
+        &self,
+        start_time: usize,
+        from: Vertex,
+        goals: &[Vertex],
+        parking: &FnvHashSet<Vertex>,
+    ) -> Option<(Path, Vec<usize>)> {
+        self.safe_search(start_time, from, goals, Some(parking))
+    }
+    /// Safe Interval Path Planning along the goals, ending at the last one where the robot can
+    /// stay for a step, or at one of the parking vertices after it if these are given.
+    fn safe_search(
+        &self,
+        start_time: usize,
+        from: Vertex,
+        goals: &[Vertex],
+        parking: Option<&FnvHashSet<Vertex>>,
+    ) -> Option<(Path, Vec<usize>)> {
+        debug_assert!(goals.len() > 0);
+
+        // The length of the tour from each goal on, with the steps at the goals
+        let mut remaining = vec![0; goals.len() + 1];
+        if parking.is_some() {
+            remaining[goals.len() - 1] = 1;
+        }
+        for index in (0..(goals.len() - 1)).rev() {
+            remaining[index] = remaining[index + 1] + 1 + goals[index].distance(goals[index + 1]);
+        }
+        let heuristic = |vertex: Vertex, reached: usize| {
+            goals.get(reached).map_or(0, |&goal| vertex.distance(goal)) + remaining[reached]
+        };
+
+        let start_interval = self.safe_intervals.index_of(from, start_time)?;
+        if start_time >= self.safe_end(from, start_interval) {
+            return None;
+        }
+        let start = (from, start_interval, 0);
+
+        let mut came_from = FnvHashMap::default();
+        let mut visited = FnvHashSet::default();
+        let mut to_visit: PriorityQueue<SafeState, Reverse<u64>> = PriorityQueue::new();
+        to_visit.push(start, Reverse(heuristic(from, 0)));
+
+        let mut arrivals: FnvHashMap<SafeState, usize> = FnvHashMap::default();
+        arrivals.insert(start, start_time);
+
+        while let Some((current, _)) = to_visit.pop() {
+            let (vertex, interval, reached) = current;
+            let arrival = arrivals[&current];
+            let end = self.safe_end(vertex, interval);
+            visited.insert(current);
+
+            let at_goal = goals.get(reached) == Some(&vertex) && arrival + 1 < end;
+            let is_done = match parking {
+                None => at_goal && reached + 1 == goals.len(),
+                Some(parking) => {
+                    reached == goals.len() && parking.contains(&vertex) && end == usize::max_value()
+                }
+            };
+            if is_done {
+                return Some(TimeGraph::reconstruct_safe_path(
+                    &came_from, &arrivals, current, start_time,
+                ));
+            }
+
+            let mut successors = Vec::new();
+            if at_goal {
+                // Staying at the goal for a step
+                successors.push(((vertex, interval, reached + 1), arrival + 1));
+            }
+            for neighbor in self.plan.neighbors(&vertex) {
+                let intervals = self.safe_intervals.get(neighbor);
+                let first = self.safe_intervals.first_from(neighbor, arrival + 1);
+                // Leaving the interval at its last time at the latest
+                for index in (first..intervals.len()).take_while(|&i| intervals[i].start <= end) {
+                    let mut next_arrival = (arrival + 1).max(intervals[index].start);
+                    // Waiting for a robot that comes the other way to pass
+                    while next_arrival <= end && self.is_swap(next_arrival - 1, vertex, neighbor) {
+                        next_arrival += 1;
+                    }
+                    if next_arrival <= end && next_arrival < self.safe_end(neighbor, index) {
+                        successors.push(((neighbor, index, reached), next_arrival));
+                    }
+                }
+            }
+
+            for (next, next_arrival) in successors {
+                if visited.contains(&next) {
+                    continue;
+                }
+
+                if arrivals
+                    .get(&next)
+                    .map_or(true, |&known| next_arrival < known)
+                {
+                    came_from.insert(next, current);
+                    arrivals.insert(next, next_arrival);
+                    let estimate = (next_arrival - start_time) as u64 + heuristic(next.0, next.2);
+                    to_visit.push(next, Reverse(estimate));
+                }
+            }
+        }
+
+        None
+    }
+    /// The end of a safe interval, or the time at which the vertex is parked if that is earlier.
+    fn safe_end(&self, vertex: Vertex, interval: usize) -> usize {
+        let end = self.safe_intervals.get(vertex)[interval].end;
+        self.parked.get(&vertex).map_or(end, |&time| end.min(time))
+    }
+    fn reconstruct_safe_path(
+        came_from: &FnvHashMap<SafeState, SafeState>,
+        arrivals: &FnvHashMap<SafeState, usize>,
+        end: SafeState,
+        start_time: usize,
+    ) -> (Path, Vec<usize>) {
+        let mut states = vec![end];
+        while let Some(&previous) = came_from.get(states.last().unwrap()) {
+            states.push(previous);
+        }
+        states.reverse();
+
+        let mut nodes = vec![states[0].0];
+        // The goals at which the robot stayed
+        let mut goal_times = Vec::new();
+        for (previous, state) in states.iter().zip(states.iter().skip(1)) {
+            if state.2 > previous.2 {
+                goal_times.push(arrivals[state] - 1);
+            }
+            // Waiting until the step to the next vertex
+            let wait = arrivals[state] - (start_time + nodes.len());
+            let previous_vertex = *nodes.last().unwrap();
+            nodes.extend(repeat(previous_vertex).take(wait));
+            nodes.push(state.0);
+        }
+
+        (Path { start_time, nodes }, goal_times)
+    }
     fn reconstruct_path(
         came_from: FnvHashMap<TimeVertex, TimeVertex>,
         (previous_index, previous_vertex): TimeVertex,
@@ -103,59 +330,122 @@ impl<'a> TimeGraph<'a> {
     pub fn remove_path(&mut self, path: &Path) {
         debug_assert!(path.nodes.len() > 1);
 
+        for (time, node) in self.taken_vertices(path) {
+            self.safe_intervals.remove(node, time);
+        }
+        for step in self.taken_moves(path) {
+            self.moves.insert(step);
+        }
+    }
+    /// Makes the vertices of a removed path available again.
+    ///
+    /// Vertices that other paths took as well are freed too, so these paths should be removed
+    /// again afterwards.
+    pub fn restore_path(&mut self, path: &Path) {
+        for (time, node) in self.taken_vertices(path) {
+            self.safe_intervals.insert(node, time);
+        }
+        for step in self.taken_moves(path) {
+            self.moves.remove(&step);
+        }
+    }
+    /// The vertices that a path takes, at the times that are not in the past.
+    ///
+    /// The robot stays at the end of the path for a step, and with the buffer the vertices are
+    /// also taken just before and after the robot is there.
+    fn taken_vertices(&self, path: &Path) -> Vec<(usize, Vertex)> {
         let Path { start_time, nodes } = path;
-        let start_index = start_time - self.earliest_time;
-        for (index, node) in nodes.iter().enumerate() {
-            let time = start_index + index;
-            if time >= 1 {
-                self.vertices[time - 1].remove(node);
-            }
-            self.vertices[time].remove(node);
-            if time + 1 <= self.capacity {
-                self.vertices[time + 1].remove(node);
+        let buffer = if self.buffer { 1 } else { 0 };
+
+        let mut taken = Vec::with_capacity((2 * buffer + 1) * nodes.len() + 1);
+        for (index, &node) in nodes.iter().enumerate() {
+            let time = start_time + index;
+            let last_time = if index + 1 == nodes.len() {
+                time + 1
+            } else {
+                time + buffer
+            };
+            for taken_time in time.saturating_sub(buffer)..(last_time + 1) {
+                if taken_time >= self.earliest_time {
+                    taken.push((taken_time, node));
+                }
             }
         }
-    }
-    fn neighbors(&mut self, vertex: Vertex, index: usize) -> Vec<TimeVertex> {
-        debug_assert!(index <= self.capacity);
 
-        if index == self.capacity - 1 {
-            self.extend(50);
+        taken
+    }
+    /// The moves of a path that are not in the past, which no other path can make the other way
+    /// at the same time. The buffer already keeps robots from swapping.
+    fn taken_moves(&self, path: &Path) -> Vec<(usize, Vertex, Vertex)> {
+        if self.buffer {
+            return Vec::new();
         }
 
+        path.nodes
+            .windows(2)
+            .enumerate()
+            .map(|(index, step)| (path.start_time + index, step[0], step[1]))
+            .filter(|&(time, from, to)| from != to && time >= self.earliest_time)
+            .collect()
+    }
+    /// Takes the vertex from the time on, until it is unparked.
+    pub fn park(&mut self, vertex: Vertex, time: usize) {
+        self.parked.insert(vertex, time);
+    }
+    /// Frees a parked vertex, and returns the time from which it was parked.
+    pub fn unpark(&mut self, vertex: Vertex) -> Option<usize> {
+        self.parked.remove(&vertex)
+    }
+    /// Whether a robot at the vertex can stay there from the time on, because no path uses it.
+    pub fn can_park(&self, vertex: Vertex, time: usize) -> bool {
+        !self.parked.contains_key(&vertex) && self.safe_intervals.free_from(vertex) <= time
+    }
+    fn is_free(&self, index: usize, vertex: Vertex) -> bool {
+        let time = index + self.earliest_time;
+        self.safe_intervals.index_of(vertex, time).is_some()
+            && self
+                .parked
+                .get(&vertex)
+                .map_or(true, |&parked_time| time < parked_time)
+    }
+    /// Whether a move from `from` at the time to `to` a step later swaps with a path.
+    fn is_swap(&self, time: usize, from: Vertex, to: Vertex) -> bool {
+        self.moves.contains(&(time, to, from))
+    }
+    fn neighbors(&self, vertex: Vertex, index: usize) -> Vec<TimeVertex> {
+        let time = index + self.earliest_time;
         let mut plan_neighbors = self.plan.neighbors(&vertex);
         plan_neighbors.push(vertex);
         plan_neighbors
             .into_iter()
-            .filter(|vertex| self.vertices[index + 1].contains(vertex))
-            .map(|vertex| (index + 1, vertex))
+            .filter(|&neighbor| {
+                self.is_free(index + 1, neighbor) && !self.is_swap(time, vertex, neighbor)
+            })
+            .map(|neighbor| (index + 1, neighbor))
             .collect()
     }
-    fn extend(&mut self, extra_capacity: usize) {
-        let plan_vertices = self.plan.vertices().into_iter().collect::<FnvHashSet<_>>();
-        let mut new_layers = repeat(plan_vertices)
-            .take(extra_capacity)
-            .collect::<VecDeque<_>>();
-        self.vertices.append(&mut new_layers);
-        self.capacity += extra_capacity;
-    }
     pub fn clean_front(&mut self, new_earliest_time: usize) {
         debug_assert!(new_earliest_time > self.earliest_time);
 
-        let to_remove = new_earliest_time - self.earliest_time;
-        self.vertices.drain(..to_remove);
-        self.capacity -= to_remove;
+        self.safe_intervals.clean_front(new_earliest_time);
+        self.moves.retain(|&(time, _, _)| time >= new_earliest_time);
 
         self.earliest_time = new_earliest_time;
     }
 }
 
 type TimeVertex = (usize, Vertex);
+/// A vertex with the index of one of its safe intervals, and the number of goals reached
+type SafeState = (Vertex, usize, usize);
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use rand::{Rng, SeedableRng, StdRng};
     use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use std::collections::VecDeque;
+    use std::mem::size_of;
+    use std::time::Instant;
 
     fn new() -> (u64, u64, usize, OneThreeRectangle) {
         let (x_size, y_size, total_time) = (3, 4, 8);
@@ -167,43 +457,21 @@ mod test {
     #[test]
     fn test_vertices() {
         let (x_size, y_size, total_time, plan) = new();
-        let time_graph = TimeGraph::from_plan(&plan, total_time);
+        let time_graph = TimeGraph::from_plan(&plan);
 
-        let nr_vertices = time_graph
-            .vertices
-            .iter()
-            .map(FnvHashSet::len)
-            .sum::<usize>();
-        assert_eq!(
-            nr_vertices as u64,
-            x_size * y_size * (total_time as u64 + 1)
-        );
-
-        let vertices = &time_graph.vertices;
-        // Two arbitrary vertices
-        for time in 0..total_time {
-            assert!(vertices[time].contains(&Vertex { x: 0, y: 0 }));
-            assert!(vertices[time].contains(&Vertex {
-                x: x_size - 1,
-                y: 2,
-            }));
-        }
-        // Vertices are the same for each time
-        assert!(
-            vertices
-                .iter()
-                .map(|s| s.len())
-                .all(|l| l == vertices[0].len())
-        );
-        for time in 0..total_time {
-            assert_eq!(vertices[time].difference(&vertices[0]).count(), 0);
+        // All vertices are free at all times, without storing any of them
+        let vertices = plan.vertices();
+        assert_eq!(vertices.len() as u64, x_size * y_size);
+        for time in 0..(total_time + 1) {
+            assert!(vertices.iter().all(|&vertex| time_graph.is_free(time, vertex)));
         }
+        assert_eq!(time_graph.safe_intervals.all_free_from(), 0);
     }
 
     #[test]
     fn test_neighbors() {
         let (_, _, total_time, plan) = new();
-        let mut time_graph = TimeGraph::from_plan(&plan, total_time);
+        let time_graph = TimeGraph::from_plan(&plan);
 
         macro_rules! test {
             (($t: expr, $x:expr, $y:expr),
@@ -216,12 +484,15 @@ mod test {
             }
         }
 
-        // Middle
-        test!((0, 1, 1), [(1, 2, 1), (1, 0, 1), (1, 1, 2), (1, 1, 0)]);
+        // Middle, staying at the vertex is a neighbor as well
+        test!(
+            (0, 1, 1),
+            [(1, 2, 1), (1, 0, 1), (1, 1, 2), (1, 1, 0), (1, 1, 1)]
+        );
         // Boundary
-        test!((3, 0, 1), [(4, 1, 1), (4, 0, 0), (4, 0, 2)]);
+        test!((3, 0, 1), [(4, 1, 1), (4, 0, 0), (4, 0, 2), (4, 0, 1)]);
         // Corner
-        test!((3, 0, 0), [(4, 1, 0), (4, 0, 1)]);
+        test!((3, 0, 0), [(4, 1, 0), (4, 0, 1), (4, 0, 0)]);
         // Final time period
         test!(
             (total_time, 1, 1),
@@ -229,7 +500,8 @@ mod test {
                 (total_time + 1, 2, 1),
                 (total_time + 1, 0, 1),
                 (total_time + 1, 1, 2),
-                (total_time + 1, 1, 0)
+                (total_time + 1, 1, 0),
+                (total_time + 1, 1, 1)
             ]
         );
     }
@@ -252,8 +524,8 @@ mod test {
 
     #[test]
     fn test_find_path() {
-        let (_, _, total_time, plan) = new();
-        let mut time_graph = TimeGraph::from_plan(&plan, total_time);
+        let (_, _, _, plan) = new();
+        let time_graph = TimeGraph::from_plan(&plan);
 
         macro_rules! test {
             ($start_time:expr,
@@ -297,9 +569,8 @@ mod test {
             x: x_size - 1,
             y: 1,
         };
-        // Time just long enough
-        let (start_time, total_time) = (1, x_size as usize);
-        let mut time_graph = TimeGraph::from_plan(&plan, total_time);
+        let start_time = 1;
+        let time_graph = TimeGraph::from_plan(&plan);
         assert_eq!(
             time_graph.find_path(
                 1,
@@ -318,8 +589,8 @@ mod test {
 
     #[test]
     fn test_remove_path() {
-        let (_, _, total_time, plan) = new();
-        let mut time_graph = TimeGraph::from_plan(&plan, total_time);
+        let (_, _, _, plan) = new();
+        let mut time_graph = TimeGraph::from_plan(&plan).with_buffer();
 
         let from = Vertex { x: 0, y: 1 };
         let to = Vertex { x: 1, y: 1 };
@@ -330,13 +601,408 @@ mod test {
         };
         time_graph.remove_path(&path);
 
-        assert!(!time_graph.vertices[start_time].contains(&from));
-        assert!(!time_graph.vertices[start_time + 1].contains(&from));
-        assert!(time_graph.vertices[start_time + 2].contains(&from));
+        assert!(!time_graph.is_free(start_time, from));
+        assert!(!time_graph.is_free(start_time + 1, from));
+        assert!(time_graph.is_free(start_time + 2, from));
+
+        assert!(time_graph.is_free(start_time - 1, to));
+        assert!(!time_graph.is_free(start_time, to));
+        assert!(!time_graph.is_free(start_time + 1, to));
+        assert!(!time_graph.is_free(start_time + 2, to));
+
+        // Without the buffer, the path only takes the vertices at which the robot is
+        let mut time_graph = TimeGraph::from_plan(&plan);
+        time_graph.remove_path(&path);
+
+        assert!(!time_graph.is_free(start_time, from));
+        assert!(time_graph.is_free(start_time + 1, from));
+
+        assert!(time_graph.is_free(start_time, to));
+        assert!(!time_graph.is_free(start_time + 1, to));
+        assert!(!time_graph.is_free(start_time + 2, to));
+        assert!(time_graph.is_free(start_time + 3, to));
+    }
+
+    #[test]
+    fn test_moves() {
+        let (_, _, _, plan) = new();
+        let mut time_graph = TimeGraph::from_plan(&plan);
+
+        let left = Vertex { x: 0, y: 0 };
+        let right = Vertex { x: 1, y: 0 };
+        let above = Vertex { x: 0, y: 1 };
+        let path = Path {
+            start_time: 1,
+            nodes: vec![left, right],
+        };
+        time_graph.remove_path(&path);
+
+        // Following the robot closely
+        let following = Some(Path {
+            start_time: 1,
+            nodes: vec![above, left],
+        });
+        assert_eq!(time_graph.find_path(1, above, left), following);
+        assert_eq!(time_graph.find_safe_path(1, above, left), following);
+        // But not swapping with it, going around it instead
+        for around in vec![
+            time_graph.find_path(1, right, left),
+            time_graph.find_safe_path(1, right, left),
+        ] {
+            assert_eq!(around.unwrap().end_time(), 4);
+        }
+        time_graph.restore_path(&path);
+        assert!(time_graph.moves.is_empty());
 
-        assert!(time_graph.vertices[start_time - 1].contains(&to));
-        assert!(!time_graph.vertices[start_time].contains(&to));
-        assert!(!time_graph.vertices[start_time + 1].contains(&to));
-        assert!(!time_graph.vertices[start_time + 2].contains(&to));
+        // Keeping a step away with the buffer
+        let mut time_graph = TimeGraph::from_plan(&plan).with_buffer();
+        time_graph.remove_path(&path);
+        assert_eq!(time_graph.find_path(1, above, left).unwrap().end_time(), 3);
+        assert!(time_graph.moves.is_empty());
+    }
+
+    #[test]
+    fn test_park() {
+        let (_, _, _, plan) = new();
+        let mut time_graph = TimeGraph::from_plan(&plan).with_buffer();
+
+        let from = Vertex { x: 0, y: 1 };
+        let parked = Vertex { x: 1, y: 1 };
+        let to = Vertex { x: 2, y: 1 };
+        time_graph.park(parked, 4);
+
+        assert!(!time_graph.can_park(parked, 1));
+        assert_eq!(time_graph.find_path_to_park(1, from, parked), None);
+        // Passing before the robot stays there
+        assert_eq!(
+            time_graph.find_path(1, from, to),
+            Some(Path {
+                start_time: 1,
+                nodes: vec![from, parked, to],
+            })
+        );
+        // Waiting until the path in between is gone, around the parked robot
+        let path = Path {
+            start_time: 1,
+            nodes: vec![to, to, to, Vertex { x: 2, y: 2 }],
+        };
+        time_graph.remove_path(&path);
+        assert!(!time_graph.can_park(to, 1));
+        let path_to_park = time_graph.find_path_to_park(1, from, to).unwrap();
+        assert_eq!(path_to_park.end_time(), 5);
+        assert!(!path_to_park.nodes[3..].contains(&parked));
+        assert!(time_graph.can_park(to, 5));
+        assert_eq!(time_graph.unpark(parked), Some(4));
+        assert!(time_graph.can_park(parked, 1));
+
+        time_graph.restore_path(&path);
+        assert!(time_graph.can_park(to, 1));
+    }
+
+    #[test]
+    fn test_find_safe_path() {
+        let (_, _, _, plan) = new();
+        let mut time_graph = TimeGraph::from_plan(&plan);
+
+        let from = Vertex { x: 0, y: 0 };
+        let to = Vertex { x: 0, y: 2 };
+        let path = time_graph.find_safe_path(1, from, to);
+        assert_eq!(
+            path,
+            Some(Path {
+                start_time: 1,
+                nodes: vec![from, Vertex { x: 0, y: 1 }, to],
+            })
+        );
+
+        // Waiting until the robot at the next vertex is gone
+        let next = Vertex { x: 0, y: 1 };
+        time_graph.remove_path(&Path {
+            start_time: 2,
+            nodes: vec![next, next],
+        });
+        let path = time_graph.find_safe_path(1, from, next);
+        assert_eq!(
+            path,
+            Some(Path {
+                start_time: 1,
+                nodes: vec![from, from, from, from, next],
+            })
+        );
+        assert_eq!(path, time_graph.find_path(1, from, next));
+
+        // Not staying at the end, nor starting, where a robot is parked
+        time_graph.park(to, 4);
+        assert_eq!(time_graph.find_safe_path(1, from, to), None);
+        time_graph.park(from, 1);
+        assert_eq!(time_graph.find_safe_path(1, from, Vertex { x: 2, y: 2 }), None);
+    }
+
+    #[test]
+    fn test_find_safe_tour() {
+        let (_, _, _, plan) = new();
+        let mut time_graph = TimeGraph::from_plan(&plan);
+
+        let from = Vertex { x: 0, y: 0 };
+        let source = Vertex { x: 0, y: 2 };
+        let terminal = Vertex { x: 1, y: 3 };
+        let parked = Vertex { x: 2, y: 3 };
+        let parking = vec![parked].into_iter().collect();
+        let goals = [source, terminal];
+
+        // Not ending where another robot parks later
+        time_graph.park(parked, 9);
+        assert_eq!(time_graph.find_safe_tour(1, from, &goals, &parking), None);
+        time_graph.unpark(parked);
+
+        // Staying at the source and at the terminal for a step
+        let (path, goal_times) = time_graph
+            .find_safe_tour(1, from, &goals, &parking)
+            .unwrap();
+        assert_eq!(goal_times, vec![3, 6]);
+        assert_eq!(
+            path.nodes[..4],
+            [from, Vertex { x: 0, y: 1 }, source, source]
+        );
+        assert_eq!(path.nodes[5..], [terminal, terminal, parked]);
+
+        // Without gaps in which other robots can take the goals
+        time_graph.remove_path(&path);
+        assert!(!time_graph.is_free(4, source));
+        assert!(!time_graph.is_free(7, terminal));
+    }
+
+    /// The reservation table that the time graph kept before the safe intervals, such that the
+    /// benchmarks can compare the two: a layer with the free vertices for every time from the
+    /// earliest time on, extended by full layers when a search reaches the last one.
+    struct Layers<'a> {
+        plan: &'a Plan,
+        layers: VecDeque<FnvHashSet<Vertex>>,
+        earliest_time: usize,
+    }
+
+    impl<'a> Layers<'a> {
+        fn new(plan: &'a Plan, capacity: usize) -> Layers<'a> {
+            let mut layers = Layers {
+                plan,
+                layers: VecDeque::new(),
+                earliest_time: 0,
+            };
+            layers.extend(capacity + 1);
+            layers
+        }
+        fn extend(&mut self, extra_capacity: usize) {
+            let plan_vertices = self.plan.vertices().into_iter().collect::<FnvHashSet<_>>();
+            self.layers
+                .extend(repeat(plan_vertices).take(extra_capacity));
+        }
+        fn is_free(&self, index: usize, vertex: Vertex) -> bool {
+            self.layers
+                .get(index)
+                .map_or(true, |layer| layer.contains(&vertex))
+        }
+        fn neighbors(&mut self, vertex: Vertex, index: usize) -> Vec<TimeVertex> {
+            if index + 1 == self.layers.len() {
+                self.extend(50);
+            }
+
+            let mut plan_neighbors = self.plan.neighbors(&vertex);
+            plan_neighbors.push(vertex);
+            plan_neighbors
+                .into_iter()
+                .filter(|&neighbor| self.is_free(index + 1, neighbor))
+                .map(|neighbor| (index + 1, neighbor))
+                .collect()
+        }
+    }
+
+    /// A reservation table that the benchmarks plan paths around, with the buffer.
+    trait Table {
+        fn find_path(&mut self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path>;
+        fn remove_path(&mut self, path: &Path);
+        fn clean_front(&mut self, new_earliest_time: usize);
+        /// Approximate number of bytes that the reservations take
+        fn memory(&self) -> usize;
+    }
+
+    impl<'a> Table for Layers<'a> {
+        /// The A* search of `TimeGraph::find_path`, through the layers.
+        fn find_path(&mut self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+            let start_index = start_time - self.earliest_time;
+            if !self.is_free(start_index, from) {
+                return None;
+            }
+            let nr_vertices = self.plan.vertices().len();
+            let last_reserved_index = self
+                .layers
+                .iter()
+                .rposition(|layer| layer.len() < nr_vertices)
+                .map_or(0, |index| index + 1);
+            let horizon = last_reserved_index.max(start_index) + nr_vertices;
+
+            let mut came_from = FnvHashMap::default();
+            let mut visited = FnvHashSet::default();
+            let mut to_visit: PriorityQueue<TimeVertex, Reverse<u64>> = PriorityQueue::new();
+            to_visit.push((start_index, from), Reverse(from.distance(to)));
+            let mut distances: FnvHashMap<TimeVertex, u64> = FnvHashMap::default();
+            distances.insert((start_index, from), 0);
+
+            while let Some((current, _)) = to_visit.pop() {
+                let (index, vertex) = current;
+                if vertex == to && self.is_free(index + 1, to) {
+                    return Some(TimeGraph::reconstruct_path(came_from, current, start_time));
+                }
+
+                visited.insert(current);
+                if index == horizon {
+                    continue;
+                }
+
+                for neighbor in self.neighbors(vertex, index) {
+                    if visited.contains(&neighbor) {
+                        continue;
+                    }
+
+                    let new_path_length = distances[&current] + 1;
+                    to_visit.push(neighbor, Reverse(new_path_length + neighbor.1.distance(to)));
+                    if distances
+                        .get(&neighbor)
+                        .map_or(true, |&length| new_path_length < length)
+                    {
+                        came_from.insert(neighbor, current);
+                        distances.insert(neighbor, new_path_length);
+                    }
+                }
+            }
+
+            None
+        }
+        /// Takes the same vertices as the time graph with the buffer.
+        fn remove_path(&mut self, path: &Path) {
+            for (index, &node) in path.nodes.iter().enumerate() {
+                let time = path.start_time + index;
+                for taken_time in time.saturating_sub(1)..(time + 2) {
+                    if taken_time < self.earliest_time {
+                        continue;
+                    }
+                    let taken_index = taken_time - self.earliest_time;
+                    if taken_index >= self.layers.len() {
+                        let extra_capacity = taken_index + 1 - self.layers.len();
+                        self.extend(extra_capacity);
+                    }
+                    self.layers[taken_index].remove(&node);
+                }
+            }
+        }
+        fn clean_front(&mut self, new_earliest_time: usize) {
+            let to_remove = new_earliest_time - self.earliest_time;
+            self.layers.drain(..to_remove);
+            self.earliest_time = new_earliest_time;
+        }
+        fn memory(&self) -> usize {
+            self.layers
+                .iter()
+                .map(|layer| layer.capacity() * (size_of::<Vertex>() + 1))
+                .sum()
+        }
+    }
+
+    impl<'a> Table for TimeGraph<'a> {
+        fn find_path(&mut self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+            TimeGraph::find_path(self, start_time, from, to)
+        }
+        fn remove_path(&mut self, path: &Path) {
+            TimeGraph::remove_path(self, path);
+        }
+        fn clean_front(&mut self, new_earliest_time: usize) {
+            TimeGraph::clean_front(self, new_earliest_time);
+        }
+        fn memory(&self) -> usize {
+            self.safe_intervals.memory()
+        }
+    }
+
+    /// The time graph, searched with `find_safe_path` instead of A*.
+    struct SafeTimeGraph<'a>(TimeGraph<'a>);
+
+    impl<'a> Table for SafeTimeGraph<'a> {
+        fn find_path(&mut self, start_time: usize, from: Vertex, to: Vertex) -> Option<Path> {
+            self.0.find_safe_path(start_time, from, to)
+        }
+        fn remove_path(&mut self, path: &Path) {
+            self.0.remove_path(path);
+        }
+        fn clean_front(&mut self, new_earliest_time: usize) {
+            self.0.clean_front(new_earliest_time);
+        }
+        fn memory(&self) -> usize {
+            self.0.memory()
+        }
+    }
+
+    /// Plans 1000 random paths on a large plan, 5 in each of 200 steps, and prints the time it
+    /// takes to build the table and plan the paths, and the most memory that the reservations
+    /// took. Run with `cargo test --release -- --ignored --nocapture bench`.
+    fn bench_paths<'a, T: Table>(plan: &'a OneThreeRectangle, build: impl FnOnce(&'a Plan) -> T) {
+        let vertices = plan.vertices();
+        let mut rng = StdRng::from_seed([0; 32]);
+
+        let start = Instant::now();
+        let mut table = build(plan);
+        let build_time = start.elapsed();
+
+        let start = Instant::now();
+        let (mut found, mut total_length) = (0, 0);
+        let mut memory = table.memory();
+        for time in 1..201 {
+            table.clean_front(time);
+            for _ in 0..5 {
+                let from = vertices[rng.gen_range(0, vertices.len())];
+                let to = vertices[rng.gen_range(0, vertices.len())];
+                if from == to {
+                    continue;
+                }
+                if let Some(path) = table.find_path(time, from, to) {
+                    table.remove_path(&path);
+                    found += 1;
+                    total_length += path.nodes.len();
+                }
+            }
+            memory = memory.max(table.memory());
+        }
+
+        println!(
+            "{} paths of total length {}, built in {:?}, planned in {:?}, at most {} kB",
+            found,
+            total_length,
+            build_time,
+            start.elapsed(),
+            memory / 1024
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_layers() {
+        // The capacity that the layers were created with before
+        bench_paths(&OneThreeRectangle::new(100, 100), |plan| {
+            Layers::new(plan, 1000)
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_find_path() {
+        bench_paths(&OneThreeRectangle::new(100, 100), |plan| {
+            TimeGraph::from_plan(plan).with_buffer()
+        });
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_find_safe_path() {
+        bench_paths(&OneThreeRectangle::new(100, 100), |plan| {
+            SafeTimeGraph(TimeGraph::from_plan(plan).with_buffer())
+        });
     }
 }
@@ -1,12 +1,14 @@
 use algorithm::assignment::AssignmentAlgorithm;
 use algorithm::path::PathAlgorithm;
+use algorithm::solver::Comparison;
+use algorithm::solver::Model;
+use algorithm::solver::Solution;
+use algorithm::solver::Solver;
+use algorithm::solver::Variable;
 use algorithm::NoSolutionError;
-use algorithm::DAT_FILE_NAME;
-use algorithm::RUN_FILE_NAME;
 use fnv::FnvHashMap;
 use fnv::FnvHashSet;
 use simulation::plan::Plan;
-use simulation::plan::UndirectedEdge;
 use simulation::plan::Vertex;
 use simulation::settings::Settings;
 use simulation::state::History;
@@ -15,34 +17,32 @@ use simulation::Instructions;
 use simulation::MoveInstruction;
 use simulation::PlacementInstruction;
 use simulation::RemovalInstruction;
-use std::env::temp_dir;
-use std::fs::create_dir;
-use std::fs::remove_dir_all;
-use std::fs::File;
-use std::io::Write;
-use std::path::Path;
-use std::path::PathBuf;
-use std::process::Command;
-
-const MOD_FILE_PATH: &str = "/home/bram/git/disjoint-path-routing/src/algorithm/path/ilp/ilp.mod";
-const WORKING_DIRECTORY: &str = "path_ilp";
+use std::iter::repeat;
+use std::sync::Arc;
+use std::time::Duration;
 
+const TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// Plans the movements of all robots a few steps ahead with an integer program, after which only
+/// the first step is executed.
 pub struct ILPSteps<'p, 's, 'a> {
     settings: &'s Settings,
     plan: &'p Plan,
     assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
 
     steps_at_once: u64,
+    solver: Arc<Solver>,
 
     pub assignment: Vec<Vec<usize>>,
-    assignment_initialized: bool,
+    /// Times at which the solver found no movements, such that the robots moved greedily
+    pub failures: Vec<(usize, NoSolutionError)>,
 }
 
 impl<'p, 's, 'a> ILPSteps<'p, 's, 'a> {
     pub fn new(
-        plan: &'p impl Plan,
+        plan: &'p Plan,
         settings: &'s Settings,
-        assignment_algorithm: Box<impl AssignmentAlgorithm<'p, 's> + 'a>,
+        assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
         steps_at_once: u64,
     ) -> ILPSteps<'p, 's, 'a> {
         ILPSteps {
@@ -51,204 +51,282 @@ impl<'p, 's, 'a> ILPSteps<'p, 's, 'a> {
             assignment_algorithm,
 
             steps_at_once,
+            solver: settings.solver.create(TIME_LIMIT),
             assignment: Vec::new(),
-            assignment_initialized: false,
+            failures: Vec::new(),
         }
     }
-    fn get_paths() -> (PathBuf, PathBuf, PathBuf, PathBuf) {
-        let model_path = <&str as AsRef<Path>>::as_ref(&MOD_FILE_PATH).to_path_buf();
-
-        let working_directory = temp_dir().join(WORKING_DIRECTORY);
-        let dat_path = working_directory.join(DAT_FILE_NAME);
-        let run_path = working_directory.join(RUN_FILE_NAME);
+    /// Solves the models with another solver than the one in the settings.
+    pub fn with_solver(mut self, solver: Arc<Solver>) -> ILPSteps<'p, 's, 'a> {
+        self.solver = solver;
+        self
+    }
+    /// Assigns the requests that are not yet picked up, robots carrying a parcel first deliver it.
+    fn update_assignment(&mut self, state: &State) {
+        let carried = state
+            .robot_states
+            .iter()
+            .filter_map(|robot| robot.parcel_id)
+            .collect::<FnvHashSet<_>>();
+        let open_requests = state
+            .requests
+            .iter()
+            .filter(|&(id, _)| !carried.contains(id))
+            .map(|(&id, &request)| (id, request))
+            .collect::<FnvHashMap<_, _>>();
+        let availability = state
+            .robot_states
+            .iter()
+            .map(|robot| {
+                let vertex = robot.vertex.unwrap();
+                match robot.parcel_id {
+                    Some(parcel) => {
+                        let to = state.requests.get(&parcel).unwrap().to;
+                        (self.plan.path_length(vertex, to) as usize + 1, to)
+                    }
+                    None => (0, vertex),
+                }
+            })
+            .collect::<Vec<_>>();
 
-        (working_directory, dat_path, run_path, model_path)
+        self.assignment = if open_requests.len() > 0 {
+            self.assignment_algorithm
+                .calculate_assignment(&open_requests, &availability)
+        } else {
+            repeat(Vec::with_capacity(0))
+                .take(self.settings.nr_robots)
+                .collect()
+        };
+        for robot in state.robot_states.iter() {
+            if let Some(parcel) = robot.parcel_id {
+                self.assignment[robot.robot_id].insert(0, parcel);
+            }
+        }
     }
-    /// Robot, Time, List of locations
-    /// Robot, list of (location, cost)
+    /// Locations each robot can be at in each of the coming steps, and the cost of ending at
+    /// each of the last locations: the distance to the next vertex the robot needs to go to.
     fn calculate_parameters(
         &self,
         state: &State,
-    ) -> (
-        Vec<Vec<Vec<Vertex>>>,
-        FnvHashSet<UndirectedEdge>,
-        Vec<Vec<(Vertex, u64)>>,
-    ) {
-        let locations = (0..self.settings.nr_robots)
+    ) -> (Vec<Vec<Vec<Vertex>>>, Vec<FnvHashMap<Vertex, u64>>) {
+        let locations = state
+            .robot_states
+            .iter()
             .map(|robot| {
-                (0..(self.steps_at_once) + 1)
+                (0..(self.steps_at_once + 1))
                     .map(|time| {
-                        self.plan
-                            .neighborhood(state.robot_states[robot].vertex.unwrap(), time)
+                        let mut locations = self.plan.neighborhood(robot.vertex.unwrap(), time);
+                        locations.sort_by_key(|&Vertex { x, y }| (x, y));
+                        locations
                     })
                     .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
 
-        let edges = self.plan.edges();
+        let costs = locations
+            .iter()
+            .enumerate()
+            .map(|(robot, times)| {
+                let goal = self.goal(state, robot);
+                times
+                    .last()
+                    .unwrap()
+                    .iter()
+                    .map(|&location| {
+                        let cost = goal.map_or(0, |goal| self.plan.path_length(location, goal));
+                        (location, cost)
+                    })
+                    .collect()
+            })
+            .collect();
 
-        let mut costs = Vec::new();
-        for (robot, times) in locations.iter().enumerate() {
-            let mut robot_locations_costs = Vec::new();
-            if let Some(request_id) = self.assignment[robot].first() {
-                let request = state.requests.get(&request_id).unwrap();
-                let goal_vertex = if state.robot_states[robot].parcel_id.is_some() {
-                    request.to
-                } else {
-                    request.from
-                };
-                for &location in times.last().unwrap() {
-                    robot_locations_costs
-                        .push((location, self.plan.path_length(location, goal_vertex)));
-                }
+        (locations, costs)
+    }
+    /// The vertex that a robot needs to go to next, if it has a request assigned.
+    fn goal(&self, state: &State, robot: usize) -> Option<Vertex> {
+        self.assignment[robot].first().map(|request_id| {
+            let request = state.requests.get(&request_id).unwrap();
+            if state.robot_states[robot].parcel_id.is_some() {
+                request.to
             } else {
-                for &location in times.last().unwrap() {
-                    robot_locations_costs.push((location, 0));
-                }
+                request.from
             }
-            costs.push(robot_locations_costs);
-        }
-
-        (locations, edges, costs)
+        })
     }
-    fn write_data_file(
+    /// Robots move to a neighboring vertex or stay in each step, without colliding or following
+    /// each other.
+    ///
+    /// The cost is that of the original AMPL model: the distance of each robot to its goal after
+    /// the last step, weighted by the sum of `steps - time + 1` over all times. Waiting before
+    /// moving towards the goal can cost the same, so the distances at the earlier times break
+    /// ties. They are scaled such that together they stay below one, and never outweigh a
+    /// difference in the original cost.
+    fn create_model(
         &self,
-        path: impl AsRef<Path>,
-        locations: Vec<Vec<Vec<Vertex>>>,
-        edges: FnvHashSet<UndirectedEdge>,
-        robot_costs: Vec<Vec<(Vertex, u64)>>,
-    ) -> FnvHashMap<usize, Vertex> {
-        let mut file = File::create(path).unwrap();
-
-        writeln!(file, "param T := {};", self.steps_at_once);
-
-        writeln!(file, "set ROBOTS :=");
-        for r in 0..self.settings.nr_robots {
-            writeln!(file, "  {},", r);
-        }
-        writeln!(file, ";");
+        locations: &Vec<Vec<Vec<Vertex>>>,
+        costs: &Vec<FnvHashMap<Vertex, u64>>,
+    ) -> (Model, Vec<Vec<FnvHashMap<Vertex, Variable>>>) {
+        let steps = self.steps_at_once as usize;
+        let mut model = Model::new();
 
-        let (vertex_to_usize, usize_to_vertex) = ILPSteps::get_vertex_enumeration(&locations);
-        writeln!(file, "set LOCATIONS :=");
-        for location_id in usize_to_vertex.keys() {
-            writeln!(file, "  {},", location_id);
-        }
-        writeln!(file, ";");
-
-        writeln!(file, "set EDGES :=");
-        for UndirectedEdge { first, second } in edges {
-            if vertex_to_usize.contains_key(&first) && vertex_to_usize.contains_key(&second) {
-                let v1 = vertex_to_usize.get(&first).unwrap();
-                let v2 = vertex_to_usize.get(&second).unwrap();
-                writeln!(file, "  {} {},", v1, v2);
-                writeln!(file, "  {} {},", v2, v1);
-            }
-        }
-        for &id in usize_to_vertex.keys() {
-            writeln!(file, "  {} {},", id, id);
-        }
-        writeln!(file, ";");
+        let weight = (0..(steps + 1)).map(|time| steps - time + 1).sum::<usize>() as f64;
+        let largest_tie_break = locations
+            .iter()
+            .enumerate()
+            .flat_map(|(robot, times)| {
+                times[..steps].iter().map(move |locations| {
+                    locations
+                        .iter()
+                        .map(|location| costs[robot][location])
+                        .max()
+                        .unwrap_or(0)
+                })
+            })
+            .sum::<u64>();
+        let tie_break_weight = 1_f64 / (largest_tie_break + 1) as f64;
 
-        for (robot, times) in locations.iter().enumerate() {
+        let positions = locations
+            .iter()
+            .enumerate()
+            .map(|(robot, times)| {
+                times
+                    .iter()
+                    .enumerate()
+                    .map(|(time, locations)| {
+                        let weight = if time == steps {
+                            weight
+                        } else {
+                            tie_break_weight
+                        };
+                        locations
+                            .iter()
+                            .map(|&location| {
+                                let cost = weight * costs[robot][&location] as f64;
+                                let variable = model.add_binary(cost);
+                                // Staying in place is always possible
+                                model.set_start(
+                                    variable,
+                                    if location == times[0][0] {
+                                        1_f64
+                                    } else {
+                                        0_f64
+                                    },
+                                );
+                                (location, variable)
+                            })
+                            .collect::<FnvHashMap<_, _>>()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        for (robot, times) in positions.iter().enumerate() {
             for (time, locations) in times.iter().enumerate() {
-                writeln!(file, "set TIMES_ROBOTS_LOCATIONS[{}, {}] :=", time, robot);
-                for location in locations {
-                    writeln!(file, "  {},", vertex_to_usize.get(location).unwrap());
+                model.add_constraint(
+                    locations
+                        .values()
+                        .map(|&variable| (variable, 1_f64))
+                        .collect(),
+                    Comparison::Equal,
+                    1_f64,
+                );
+
+                if time == steps {
+                    continue;
+                }
+                let next = &times[time + 1];
+                for (&location, &variable) in locations.iter() {
+                    let mut terms = vec![(variable, 1_f64)];
+                    let reachable = self
+                        .plan
+                        .neighbors(&location)
+                        .into_iter()
+                        .chain(Some(location))
+                        .filter_map(|neighbor| next.get(&neighbor));
+                    terms.extend(reachable.map(|&variable| (variable, -1_f64)));
+                    model.add_constraint(terms, Comparison::LessEqual, 0_f64);
+
+                    // Robots don't move to a location that is left in the same step
+                    let mut terms = vec![(variable, 1_f64)];
+                    for other in (0..positions.len()).filter(|&other| other != robot) {
+                        if let Some(&variable) = positions[other][time + 1].get(&location) {
+                            terms.push((variable, 1_f64));
+                        }
+                    }
+                    if terms.len() > 1 {
+                        model.add_constraint(terms, Comparison::LessEqual, 1_f64);
+                    }
                 }
-                writeln!(file, ";");
             }
         }
 
-        writeln!(file, "param cost :=");
-        for (robot, location_costs) in robot_costs.iter().enumerate() {
-            for &(location, cost) in location_costs {
-                writeln!(
-                    file,
-                    "{}\t{}\t{}",
-                    robot,
-                    vertex_to_usize.get(&location).unwrap(),
-                    cost
-                );
+        for time in 0..(steps + 1) {
+            let mut robots_at_location = FnvHashMap::default();
+            for times in positions.iter() {
+                for (&location, &variable) in times[time].iter() {
+                    robots_at_location
+                        .entry(location)
+                        .or_insert_with(Vec::new)
+                        .push((variable, 1_f64));
+                }
             }
-        }
-        writeln!(file, ";");
-
-        usize_to_vertex
-    }
-    fn get_vertex_enumeration(
-        locations: &Vec<Vec<Vec<Vertex>>>,
-    ) -> (FnvHashMap<Vertex, usize>, FnvHashMap<usize, Vertex>) {
-        let mut all_locations = FnvHashSet::default();
-        for robots in locations.iter() {
-            for &location in robots.last().unwrap().iter() {
-                all_locations.insert(location);
+            for (_, terms) in robots_at_location {
+                if terms.len() > 1 {
+                    model.add_constraint(terms, Comparison::LessEqual, 1_f64);
+                }
             }
         }
 
-        let (mut vertex_to_usize, mut usize_to_vertex) =
-            (FnvHashMap::default(), FnvHashMap::default());
-        for (id, &location) in all_locations.iter().enumerate() {
-            usize_to_vertex.insert(id, location);
-            vertex_to_usize.insert(location, id);
-        }
-
-        (vertex_to_usize, usize_to_vertex)
-    }
-    fn write_run_file<T>(path: T, model_path: T, data_path: T)
-    where
-        T: AsRef<Path>,
-    {
-        let mut file = File::create(path).unwrap();
-
-        writeln!(file, "model '{}';", model_path.as_ref().to_str().unwrap());
-        writeln!(file, "data '{}';", data_path.as_ref().to_str().unwrap());
-        writeln!(
-            file,
-            "option solver '/home/bram/Downloads/amplide.linux64/gurobi';"
-        );
-        writeln!(file, "option show_stats 0;");
-        writeln!(file, "option gurobi_options 'timelim 1';");
-        writeln!(file, "solve;");
-        writeln!(file, "option omit_zero_rows 1;");
-        file.write("display {r in ROBOTS, i in TIMES_ROBOTS_LOCATIONS[1, r]} Time_Robot_Location[1, r, i];\n".as_bytes());
+        (model, positions)
     }
-    fn execute_model(run_file_path: impl AsRef<Path>) -> String {
-        String::from_utf8(
-            Command::new("/home/bram/Downloads/amplide.linux64/ampl")
-                .arg(run_file_path.as_ref())
-                .output()
-                .unwrap()
-                .stdout,
-        ).unwrap()
+    /// The location of each robot after the first step.
+    fn next_locations(
+        solution: &Solution,
+        positions: &Vec<Vec<FnvHashMap<Vertex, Variable>>>,
+    ) -> Vec<Vertex> {
+        positions
+            .iter()
+            .map(|times| {
+                let next = &times[1.min(times.len() - 1)];
+                *next
+                    .iter()
+                    .find(|&(_, &variable)| solution.is_set(variable))
+                    .unwrap()
+                    .0
+            })
+            .collect()
     }
-    fn parse_ampl_output(output: String) -> Vec<usize> {
-        let first_lines = output.lines().collect::<Vec<_>>();
-        let first_split = first_lines.split(|&line| line == ";").next().unwrap();
-        let lines = first_split
+    /// The location of each robot after a step in which robots move closer to their goal when
+    /// they can, for when the solver finds no movements.
+    ///
+    /// Robots only move to vertices that no robot is at, such that they can't collide or follow
+    /// each other.
+    fn greedy_locations(&self, state: &State) -> Vec<Vertex> {
+        let mut occupied = state
+            .robot_states
             .iter()
-            .skip_while(|line| !line.starts_with("Time_Robot_Location["))
-            .skip(1)
-            .collect::<Vec<_>>();
-
-        let mut end_locations = lines
-            .into_iter()
-            .map(
-                |line| match line.split_whitespace().collect::<Vec<_>>().as_slice() {
-                    [robot, location, "1"] => {
-                        let robot = robot.parse::<usize>().unwrap();
-                        let location = location.parse::<usize>().unwrap();
+            .map(|robot| robot.vertex.unwrap())
+            .collect::<FnvHashSet<_>>();
 
-                        (robot, location)
+        (0..state.robot_states.len())
+            .map(|robot| {
+                let vertex = state.robot_states[robot].vertex.unwrap();
+                let next = self.goal(state, robot).and_then(|goal| {
+                    let distance = self.plan.path_length(vertex, goal);
+                    self.plan
+                        .neighbors(&vertex)
+                        .into_iter()
+                        .filter(|neighbor| !occupied.contains(neighbor))
+                        .find(|&neighbor| self.plan.path_length(neighbor, goal) < distance)
+                });
+                match next {
+                    Some(next) => {
+                        occupied.insert(next);
+                        next
                     }
-                    _ => panic!(),
-                },
-            )
-            .collect::<Vec<_>>();
-
-        end_locations.sort_by_key(|&(robot, _)| robot);
-        end_locations
-            .into_iter()
-            .map(|(_, location)| location)
+                    None => vertex,
+                }
+            })
             .collect()
     }
     fn get_instructions(&mut self, history: &History, new_locations: Vec<Vertex>) -> Instructions {
@@ -314,42 +392,20 @@ impl<'p, 's, 'a> PathAlgorithm<'p, 's, 'a> for ILPSteps<'p, 's, 'a> {
     }
 
     fn next_step(&mut self, history: &History) -> Instructions {
-        if !self.assignment_initialized {
-            let requests = &history.last_state().requests;
-            let availability = history
-                .last_state()
-                .robot_states
-                .iter()
-                .map(|state| (0, state.vertex.unwrap()))
-                .collect::<Vec<_>>();
-            self.assignment = self
-                .assignment_algorithm
-                .calculate_assignment(requests, &availability);
-            self.assignment_initialized = true;
-            //            println!("{:?}", self.assignment);
+        if self.contains_new_requests(history) {
+            self.update_assignment(history.last_state());
         }
 
-        let (locations, edges, costs) = self.calculate_parameters(history.last_state());
-        let (working_directory, data_path, run_file_path, model_path) = ILPSteps::get_paths();
-        if working_directory.exists() {
-            remove_dir_all(working_directory.as_path());
-        }
-        create_dir(working_directory);
-        let vertex_map = self.write_data_file(data_path.as_path(), locations, edges, costs);
-        //        for (id, vertex) in vertex_map.iter() {
-        //            println!("{}, {:?}", id, vertex);
-        //        }
-        ILPSteps::write_run_file(
-            run_file_path.as_path(),
-            model_path.as_path(),
-            data_path.as_path(),
-        );
-        let output = ILPSteps::execute_model(run_file_path.as_path());
-        let new_positions = ILPSteps::parse_ampl_output(output);
-        let new_positions = new_positions
-            .into_iter()
-            .map(|id| *vertex_map.get(&id).unwrap())
-            .collect();
+        let (locations, costs) = self.calculate_parameters(history.last_state());
+        let (model, positions) = self.create_model(&locations, &costs);
+        let new_positions = match self.solver.solve(&model) {
+            Ok(solution) => ILPSteps::next_locations(&solution, &positions),
+            Err(error) => {
+                self.failures
+                    .push((history.time(), NoSolutionError::from(error)));
+                self.greedy_locations(history.last_state())
+            }
+        };
         let instructions = self.get_instructions(history, new_positions);
 
         instructions
@@ -365,7 +421,15 @@ mod test {
     use algorithm::assignment::multiple_vehicle_ilp::MultiVehicleIlpFormulation;
     use algorithm::path::ilp::ILPSteps;
     use algorithm::path::PathAlgorithm;
+    use algorithm::solver::ampl::mock::MockAmpl;
+    use algorithm::solver::branch_and_bound::BranchAndBound;
+    use algorithm::solver::Model;
+    use algorithm::solver::Solution;
+    use algorithm::solver::SolveError;
+    use algorithm::solver::Solver;
+    use algorithm::solver::SolverSettings;
     use simulation::demand::Request;
+    use simulation::placement::Placement;
     use simulation::plan::one_three_rectangle::OneThreeRectangle;
     use simulation::plan::Vertex;
     use simulation::settings::Settings;
@@ -373,6 +437,7 @@ mod test {
     use simulation::state::RobotState;
     use simulation::state::State;
     use simulation::MoveInstruction;
+    use std::sync::Arc;
 
     #[test]
     fn test_have_parcel() {
@@ -382,6 +447,9 @@ mod test {
             nr_robots: 1,
             nr_requests: 1,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let requests = map!
         [
@@ -422,6 +490,97 @@ mod test {
             nr_robots: 1,
             nr_requests: 1,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        };
+        let requests = map!
+        [
+            0 => Request {
+                from: Vertex { x: 0, y: 0 },
+                to: Vertex { x: 2, y: 2 },
+            },
+        ];
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm = ILPSteps::new(&plan, &settings, assignment_algorithm, 3);
+
+        let history = History {
+            states: vec![State {
+                robot_states: vec![RobotState {
+                    robot_id: 0,
+                    parcel_id: None,
+                    vertex: Some(Vertex { x: 2, y: 0 }),
+                }],
+                requests,
+            }],
+            calculation_times: Vec::new(),
+        };
+
+        assert_eq!(
+            algorithm.next_step(&history).movements,
+            vec![MoveInstruction {
+                robot_id: 0,
+                vertex: Vertex { x: 1, y: 0 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ampl() {
+        let plan = OneThreeRectangle::new(10, 10);
+        let settings = Settings {
+            total_time: 10,
+            nr_robots: 1,
+            nr_requests: 1,
+            output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        };
+        let requests = map!
+        [
+            0 => Request {
+                from: Vertex { x: 0, y: 0 },
+                to: Vertex { x: 2, y: 2 },
+            },
+        ];
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm = ILPSteps::new(&plan, &settings, assignment_algorithm, 3)
+            .with_solver(Arc::new(MockAmpl::new()));
+
+        let history = History {
+            states: vec![State {
+                robot_states: vec![RobotState {
+                    robot_id: 0,
+                    parcel_id: Some(0),
+                    vertex: Some(Vertex { x: 2, y: 0 }),
+                }],
+                requests,
+            }],
+            calculation_times: Vec::new(),
+        };
+
+        assert_eq!(
+            algorithm.next_step(&history).movements,
+            vec![MoveInstruction {
+                robot_id: 0,
+                vertex: Vertex { x: 2, y: 1 },
+            }]
+        );
+        assert!(algorithm.failures.is_empty());
+    }
+
+    #[test]
+    fn test_objective() {
+        let plan = OneThreeRectangle::new(10, 10);
+        let settings = Settings {
+            total_time: 10,
+            nr_robots: 1,
+            nr_requests: 1,
+            output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let requests = map!
         [
@@ -433,6 +592,60 @@ mod test {
         let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
         let mut algorithm = ILPSteps::new(&plan, &settings, assignment_algorithm, 3);
 
+        let history = History {
+            states: vec![State {
+                robot_states: vec![RobotState {
+                    robot_id: 0,
+                    parcel_id: None,
+                    vertex: Some(Vertex { x: 2, y: 0 }),
+                }],
+                requests,
+            }],
+            calculation_times: Vec::new(),
+        };
+        algorithm.next_step(&history);
+
+        let (locations, costs) = algorithm.calculate_parameters(history.last_state());
+        let (model, _) = algorithm.create_model(&locations, &costs);
+        // The goal is reached within the steps, so the original cost is zero. The distances at
+        // the earlier times are at most 2, 3 and 4, so they are weighted by 1 / 10. Moving to
+        // the goal right away passes distances 2, 1 and 0, while waiting a step first would
+        // pass 2, 2 and 1.
+        let solution = BranchAndBound::new(None).solve(&model).unwrap();
+        assert!((solution.objective() - 0.3).abs() < 1e-9);
+    }
+
+    struct FailingSolver;
+
+    impl Solver for FailingSolver {
+        fn solve(&self, _model: &Model) -> Result<Solution, SolveError> {
+            Err(SolveError::Failed("No license".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_solver_failure() {
+        let plan = OneThreeRectangle::new(10, 10);
+        let settings = Settings {
+            total_time: 10,
+            nr_robots: 1,
+            nr_requests: 1,
+            output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        };
+        let requests = map!
+        [
+            0 => Request {
+                from: Vertex { x: 0, y: 0 },
+                to: Vertex { x: 2, y: 2 },
+            },
+        ];
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm = ILPSteps::new(&plan, &settings, assignment_algorithm, 3)
+            .with_solver(Arc::new(FailingSolver));
+
         let history = History {
             states: vec![State {
                 robot_states: vec![RobotState {
@@ -452,6 +665,11 @@ mod test {
                 vertex: Vertex { x: 1, y: 0 },
             }]
         );
+        assert_eq!(algorithm.failures.len(), 1);
+        assert_eq!(
+            algorithm.failures[0].1.message(),
+            "The solver failed: No license"
+        );
     }
 
     #[test]
@@ -462,6 +680,9 @@ mod test {
             nr_robots: 2,
             nr_requests: 2,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         };
         let requests = map!
         [
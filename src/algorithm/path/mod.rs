@@ -3,8 +3,12 @@ use fnv::FnvHashSet;
 use simulation::state::History;
 use simulation::Instructions;
 
+pub mod cbs;
+pub mod goals;
 pub mod greedy_shortest_paths;
 pub mod ilp;
+pub mod token_passing;
+pub mod whca;
 
 pub trait PathAlgorithm<'p, 's, 'a> {
     fn initialize(&mut self) -> Result<(), NoSolutionError>;
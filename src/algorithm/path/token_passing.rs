@@ -0,0 +1,536 @@
+use algorithm::path::greedy_shortest_paths::time_graph::TimeGraph;
+use algorithm::path::greedy_shortest_paths::Path;
+use algorithm::path::PathAlgorithm;
+use algorithm::NoSolutionError;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use simulation::demand::Request;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::settings::Settings;
+use simulation::state::History;
+use simulation::state::State;
+use simulation::Instructions;
+use simulation::MoveInstruction;
+use simulation::PlacementInstruction;
+use simulation::RemovalInstruction;
+use std::iter::repeat;
+
+/// Token Passing for lifelong pickup and delivery: the robots without a request take the token
+/// one after the other, and take the request they can pick up first, planning a path through the
+/// pickup to the delivery around the paths in the token.
+///
+/// The token is the time graph, from which all paths are removed and in which each robot is
+/// parked where its path ends, until it plans again. Requests of which the pickup or delivery is
+/// where another robot stays are not taken, such that each path can end where it is parked.
+///
+/// Robots without a request move to a parking vertex, away from the pickups and deliveries, such
+/// that they don't block the robots that serve requests.
+///
+/// With task swaps, a robot can also take a request from a robot that didn't pick it up yet, if
+/// it would be at the pickup earlier. That robot then takes the token, without taking requests
+/// from others.
+pub struct TokenPassing<'p> {
+    plan: &'p Plan,
+    task_swaps: bool,
+    /// Vertices at which robots without a request stay
+    parking: Vec<Vertex>,
+
+    time_graph: TimeGraph<'p>,
+    /// Last path of each robot, which ends where the robot stays until it plans again
+    paths: Vec<Option<Path>>,
+    /// Request that each robot serves
+    tasks: Vec<Option<Task>>,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Task {
+    /// The robot is on its way to the pickup, and then to the delivery
+    Pickup(usize),
+    /// The robot picked up the parcel
+    Delivery(usize),
+}
+
+impl Task {
+    fn request(&self) -> usize {
+        match self {
+            Task::Pickup(request) | Task::Delivery(request) => *request,
+        }
+    }
+}
+
+impl<'p> TokenPassing<'p> {
+    pub fn new(plan: &'p Plan, settings: &Settings) -> TokenPassing<'p> {
+        let time_graph = TimeGraph::from_plan(plan);
+        TokenPassing {
+            plan,
+            task_swaps: false,
+            parking: parking(plan, 1),
+
+            time_graph: if settings.forbid_following {
+                time_graph.with_buffer()
+            } else {
+                time_graph
+            },
+            paths: Vec::new(),
+            tasks: Vec::new(),
+        }
+    }
+    /// Allows robots to take requests from robots that would pick them up later.
+    pub fn with_task_swaps(mut self) -> Self {
+        self.task_swaps = true;
+        self
+    }
+    /// The vertex at which the robot stays after its path.
+    fn endpoint(&self, robot: usize, state: &State) -> Vertex {
+        match self.paths[robot] {
+            Some(ref path) => *path.nodes.last().unwrap(),
+            None => state.robot_states[robot].vertex.unwrap(),
+        }
+    }
+    /// The earliest time at which the robot can start a new path, as the vertex at which its path
+    /// ends is removed for a step after.
+    fn free_from(&self, robot: usize, time: usize) -> usize {
+        self.paths[robot]
+            .as_ref()
+            .map_or(time, |path| time.max(path.end_time() + 2))
+    }
+    fn path_ended(&self, robot: usize, time: usize) -> bool {
+        self.paths[robot]
+            .as_ref()
+            .map_or(true, |path| path.end_time() <= time)
+    }
+    /// The vertex that the robot at the vertex moves to in this step.
+    fn next_vertex(&self, robot: usize, time: usize, vertex: Vertex) -> Vertex {
+        match self.paths[robot] {
+            Some(ref path) if path.start_time <= time && time < path.end_time() => {
+                path.nodes[time + 1 - path.start_time]
+            }
+            _ => vertex,
+        }
+    }
+    /// Robots that stay at the pickup or delivery of their request pick up or deliver the parcel.
+    fn serve(&mut self, state: &State, time: usize, instructions: &mut Instructions) {
+        for robot in state.robot_states.iter() {
+            let robot_id = robot.robot_id;
+            let vertex = robot.vertex.unwrap();
+            if self.next_vertex(robot_id, time, vertex) != vertex {
+                continue;
+            }
+
+            match self.tasks[robot_id] {
+                Some(Task::Pickup(parcel)) if state.requests[&parcel].from == vertex => {
+                    instructions.placements.push(PlacementInstruction {
+                        robot_id,
+                        parcel,
+                        vertex,
+                    });
+                    self.tasks[robot_id] = Some(Task::Delivery(parcel));
+                }
+                Some(Task::Delivery(parcel))
+                    if state.requests[&parcel].to == vertex && robot.parcel_id == Some(parcel) =>
+                {
+                    instructions.removals.push(RemovalInstruction {
+                        robot_id,
+                        parcel,
+                        vertex,
+                    });
+                    self.tasks[robot_id] = None;
+                }
+                _ => (),
+            }
+        }
+    }
+    /// Gives the token to a robot without a request, which takes the open request that it can
+    /// pick up first. Robots that take no request move to a free parking vertex, when they aren't
+    /// at one, when they stay at the pickup or delivery of an open request, or when they can't
+    /// stay where they are.
+    ///
+    /// A robot of which the path was released because another robot took its request isn't
+    /// parked, and doesn't take requests from others.
+    ///
+    /// Returns whether the robot has a path that it can follow and stay at the end of.
+    fn take_token(&mut self, robot: usize, state: &State, time: usize, released: bool) -> bool {
+        let task_swaps = self.task_swaps && !released;
+        let vertex = state.robot_states[robot].vertex.unwrap();
+        let start_time = self.free_from(robot, time);
+        let endpoints = (0..self.paths.len())
+            .filter(|&other| other != robot)
+            .map(|other| (self.endpoint(other, state), other))
+            .collect::<FnvHashMap<_, _>>();
+        let carried = state
+            .robot_states
+            .iter()
+            .filter_map(|robot| robot.parcel_id)
+            .collect::<FnvHashSet<_>>();
+        let owners = self
+            .tasks
+            .iter()
+            .enumerate()
+            .filter_map(|(other, task)| task.map(|task| (task.request(), other)))
+            .collect::<FnvHashMap<_, _>>();
+
+        let mut candidates = state
+            .requests
+            .iter()
+            .filter(|&(id, _)| !carried.contains(id))
+            .filter_map(|(&id, &request)| {
+                let owner = owners.get(&id).cloned();
+                let is_free = |vertex| {
+                    endpoints
+                        .get(&vertex)
+                        .map_or(true, |&other| Some(other) == owner)
+                };
+                if !is_free(request.from) || !is_free(request.to) {
+                    return None;
+                }
+
+                let arrival = start_time + self.plan.path_length(vertex, request.from) as usize;
+                if let Some(other) = owner {
+                    if !task_swaps || self.tasks[other] != Some(Task::Pickup(id)) {
+                        return None;
+                    }
+                    let path = self.paths[other].as_ref()?;
+                    if arrival >= pickup_time(path, request.from, time) {
+                        return None;
+                    }
+                }
+                Some((arrival, id, request, owner))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|&(arrival, id, _, _)| (arrival, id));
+
+        let previous = self.paths[robot].clone();
+        let park_time = if released {
+            None
+        } else {
+            self.time_graph.unpark(vertex)
+        };
+        for (_, id, request, owner) in candidates {
+            match owner {
+                None => {
+                    if let Some(path) = self.plan_request(start_time, vertex, request) {
+                        self.follow(robot, path);
+                        self.tasks[robot] = Some(Task::Pickup(id));
+                        return true;
+                    }
+                }
+                Some(other) => {
+                    let path = self.release(other).unwrap();
+                    let other_arrival = pickup_time(&path, request.from, time);
+                    let swap = match self.plan_request(start_time, vertex, request) {
+                        Some(new_path)
+                            if pickup_time(&new_path, request.from, time) < other_arrival =>
+                        {
+                            self.follow(robot, new_path);
+                            self.tasks[robot] = Some(Task::Pickup(id));
+                            self.tasks[other] = None;
+                            self.take_token(other, state, time, true)
+                        }
+                        _ => false,
+                    };
+                    if swap {
+                        return true;
+                    }
+
+                    if self.tasks[robot].is_some() {
+                        self.release(robot);
+                        self.restore(robot, previous.clone());
+                        self.tasks[robot] = None;
+                    }
+                    self.follow(other, path);
+                    self.tasks[other] = Some(Task::Pickup(id));
+                }
+            }
+        }
+
+        let blocks_request = state.requests.iter().any(|(id, request)| {
+            !carried.contains(id)
+                && !owners.contains_key(id)
+                && (request.from == vertex || request.to == vertex)
+        });
+        let can_stay = park_time.is_some() || self.time_graph.can_park(vertex, start_time);
+        if blocks_request || !can_stay || !self.parking.contains(&vertex) {
+            let mut targets = self
+                .parking
+                .iter()
+                .cloned()
+                .filter(|target| *target != vertex && !endpoints.contains_key(target))
+                .collect::<Vec<_>>();
+            targets.sort_by_key(|&target| self.plan.path_length(vertex, target));
+
+            for target in targets {
+                if let Some(path) = self
+                    .time_graph
+                    .find_path_to_park(start_time, vertex, target)
+                {
+                    self.follow(robot, path);
+                    return true;
+                }
+            }
+        }
+
+        if can_stay {
+            self.time_graph
+                .park(vertex, park_time.unwrap_or(start_time));
+        }
+        can_stay
+    }
+    /// A path to the pickup of the request, where the robot stays for a step to pick up the
+    /// parcel, and on to the delivery, where it can stay.
+    fn plan_request(
+        &mut self,
+        start_time: usize,
+        vertex: Vertex,
+        request: Request,
+    ) -> Option<Path> {
+        let mut nodes = if vertex == request.from {
+            vec![vertex]
+        } else {
+            self.time_graph
+                .find_path(start_time, vertex, request.from)?
+                .nodes
+        };
+        let delivery = self.time_graph.find_path_to_park(
+            start_time + nodes.len(),
+            request.from,
+            request.to,
+        )?;
+        nodes.extend(delivery.nodes);
+
+        Some(Path { start_time, nodes })
+    }
+    /// Removes the path from the token, continuing the last path of the robot, and parks the
+    /// robot at its end.
+    fn follow(&mut self, robot: usize, path: Path) {
+        let path = match self.paths[robot].take() {
+            // Keeping the wait in between reserved when the paths are released
+            Some(Path {
+                start_time,
+                mut nodes,
+            }) => {
+                let end = *nodes.last().unwrap();
+                let wait = path.start_time - (start_time + nodes.len());
+                nodes.extend(repeat(end).take(wait));
+                nodes.extend(path.nodes);
+                Path { start_time, nodes }
+            }
+            None => path,
+        };
+
+        self.time_graph.remove_path(&path);
+        self.time_graph
+            .park(*path.nodes.last().unwrap(), path.end_time());
+        self.paths[robot] = Some(path);
+    }
+    /// Gives back the vertices of the path of the robot to the token.
+    fn release(&mut self, robot: usize) -> Option<Path> {
+        let path = self.paths[robot].take()?;
+        self.time_graph.restore_path(&path);
+        self.time_graph.unpark(*path.nodes.last().unwrap());
+        // Paths that used the same vertices just before or after
+        for other_path in self.paths.iter().flatten() {
+            self.time_graph.remove_path(other_path);
+        }
+
+        Some(path)
+    }
+    /// Removes the previous path of the robot from the token again, after it was released.
+    fn restore(&mut self, robot: usize, previous: Option<Path>) {
+        if let Some(ref path) = previous {
+            self.time_graph.remove_path(path);
+        }
+        self.paths[robot] = previous;
+    }
+}
+
+/// The vertices that are no pickup or delivery and have none as neighbor, or if there are fewer
+/// than `minimum` of them, those that are no pickup or delivery.
+pub fn parking(plan: &Plan, minimum: usize) -> Vec<Vertex> {
+    let endpoints = plan
+        .sources()
+        .into_iter()
+        .chain(plan.terminals())
+        .collect::<FnvHashSet<_>>();
+    let free = plan
+        .vertices()
+        .into_iter()
+        .filter(|vertex| !endpoints.contains(vertex))
+        .collect::<Vec<_>>();
+    let parking = free
+        .iter()
+        .cloned()
+        .filter(|vertex| {
+            plan.neighbors(vertex)
+                .iter()
+                .all(|neighbor| !endpoints.contains(neighbor))
+        })
+        .collect::<Vec<_>>();
+
+    if parking.len() < minimum {
+        free
+    } else {
+        parking
+    }
+}
+
+/// The first time from the current time on at which the path stays at the pickup.
+fn pickup_time(path: &Path, pickup: Vertex, time: usize) -> usize {
+    let first_index = time.saturating_sub(path.start_time);
+    (first_index..path.nodes.len())
+        .find(|&index| path.nodes[index] == pickup && path.nodes.get(index + 1) == Some(&pickup))
+        .map_or(usize::max_value(), |index| path.start_time + index)
+}
+
+impl<'p, 's, 'a> PathAlgorithm<'p, 's, 'a> for TokenPassing<'p> {
+    fn initialize(&mut self) -> Result<(), NoSolutionError> {
+        Ok(())
+    }
+    fn next_step(&mut self, history: &History) -> Instructions {
+        let time = history.time();
+        let state = history.last_state();
+
+        self.time_graph.clean_front(time);
+        if self.paths.is_empty() {
+            for robot in state.robot_states.iter() {
+                self.time_graph.park(robot.vertex.unwrap(), time);
+            }
+            self.paths = vec![None; state.robot_states.len()];
+            self.tasks = vec![None; state.robot_states.len()];
+        }
+        // Only the paths from the previous step on are still in the token
+        for path in self.paths.iter_mut().flatten() {
+            let passed = (time - 1)
+                .saturating_sub(path.start_time)
+                .min(path.nodes.len() - 2);
+            path.nodes.drain(..passed);
+            path.start_time += passed;
+        }
+
+        let mut instructions = Instructions {
+            movements: Vec::new(),
+            placements: Vec::new(),
+            removals: Vec::new(),
+            robot_removals: Vec::new(),
+        };
+        for robot in 0..self.paths.len() {
+            if self.tasks[robot].is_none() && self.path_ended(robot, time) {
+                self.take_token(robot, state, time, false);
+            }
+        }
+        self.serve(state, time, &mut instructions);
+
+        for robot in state.robot_states.iter() {
+            let vertex = robot.vertex.unwrap();
+            let next_vertex = self.next_vertex(robot.robot_id, time, vertex);
+            if next_vertex != vertex {
+                instructions.movements.push(MoveInstruction {
+                    robot_id: robot.robot_id,
+                    vertex: next_vertex,
+                });
+            }
+        }
+
+        instructions
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use fnv::FnvHashMap;
+
+    use algorithm::path::token_passing::TokenPassing;
+    use algorithm::path::PathAlgorithm;
+    use algorithm::solver::SolverSettings;
+    use simulation::demand::Request;
+    use simulation::placement::Placement;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use simulation::plan::Vertex;
+    use simulation::settings::Settings;
+    use simulation::state::History;
+    use simulation::state::RobotState;
+    use simulation::state::State;
+    use simulation::MoveInstruction;
+
+    fn settings(forbid_following: bool) -> Settings {
+        Settings {
+            total_time: 20,
+            nr_robots: 2,
+            nr_requests: 1,
+            output_file: None,
+            forbid_following,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        }
+    }
+
+    fn history(robots: Vec<Vertex>) -> History {
+        History {
+            states: vec![State {
+                robot_states: robots
+                    .into_iter()
+                    .enumerate()
+                    .map(|(robot_id, vertex)| RobotState {
+                        robot_id,
+                        parcel_id: None,
+                        vertex: Some(vertex),
+                    })
+                    .collect(),
+                requests: map!
+                [
+                    0 => Request {
+                        from: Vertex { x: 0, y: 2 },
+                        to: Vertex { x: 9, y: 2 },
+                    },
+                ],
+            }],
+            calculation_times: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_next_step() {
+        let plan = OneThreeRectangle::new(10, 10);
+        for &forbid_following in &[false, true] {
+            let settings = settings(forbid_following);
+            let mut algorithm = TokenPassing::new(&plan, &settings);
+
+            let instructions = algorithm.next_step(&history(vec![Vertex { x: 2, y: 2 }]));
+            assert_eq!(
+                instructions.movements,
+                vec![MoveInstruction {
+                    robot_id: 0,
+                    vertex: Vertex { x: 1, y: 2 },
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn test_task_swaps() {
+        let plan = OneThreeRectangle::new(10, 10);
+        let robots = vec![Vertex { x: 5, y: 5 }, Vertex { x: 1, y: 2 }];
+        let settings = settings(true);
+
+        // The first robot takes the request, the second one moves to a parking vertex
+        let mut algorithm = TokenPassing::new(&plan, &settings);
+        let instructions = algorithm.next_step(&history(robots.clone()));
+        assert_eq!(instructions.movements.len(), 2);
+        assert!(instructions.movements.contains(&MoveInstruction {
+            robot_id: 1,
+            vertex: Vertex { x: 2, y: 2 },
+        }));
+
+        // The second robot takes the request from the first one, which is parked already
+        let mut algorithm = TokenPassing::new(&plan, &settings).with_task_swaps();
+        let instructions = algorithm.next_step(&history(robots));
+        assert_eq!(
+            instructions.movements,
+            vec![MoveInstruction {
+                robot_id: 1,
+                vertex: Vertex { x: 0, y: 2 },
+            }]
+        );
+    }
+}
@@ -0,0 +1,438 @@
+use algorithm::assignment::AssignmentAlgorithm;
+use algorithm::path::goals::assign_requests;
+use algorithm::path::goals::goals;
+use algorithm::path::goals::instructions;
+use algorithm::path::goals::position;
+use algorithm::path::goals::Goal;
+use algorithm::path::PathAlgorithm;
+use algorithm::NoSolutionError;
+use fnv::FnvHashMap;
+use priority_queue::PriorityQueue;
+use simulation::plan::breadth_first_distances;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::settings::Settings;
+use simulation::state::History;
+use simulation::state::State;
+use simulation::Instructions;
+use std::cmp::Reverse;
+
+/// Windowed Hierarchical Cooperative A*: the robots plan their paths one after the other, each
+/// avoiding the paths of the robots that planned before it, but only for the steps of a window.
+///
+/// Beyond the window the true distance to the goal is used as the heuristic, such that robots
+/// still head in the right direction. The paths are planned again every few steps, and earlier
+/// when a robot gets a new goal, each time with the next robot going first.
+pub struct WindowedCooperativeAStar<'p, 's, 'a> {
+    settings: &'s Settings,
+    plan: &'p Plan,
+    assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
+
+    window: usize,
+    replan_interval: usize,
+    /// Shortest path lengths towards each goal, the heuristic of the searches
+    distances: FnvHashMap<Vertex, FnvHashMap<Vertex, u64>>,
+
+    pub assignment: Vec<Vec<usize>>,
+    /// Goals towards which the current paths were planned
+    goals: Vec<Goal>,
+    /// Position of each robot in each step of the window, since the time the paths were planned at
+    paths: Vec<Vec<Vertex>>,
+    planned_at: usize,
+    /// Number of steps of the paths that are followed before planning again
+    steps_to_follow: usize,
+    /// Number of times the paths were planned, which decides the robot that goes first
+    nr_plans: usize,
+    /// Times at which robots found no path within the window, such that they waited
+    pub failures: Vec<(usize, Vec<usize>)>,
+}
+
+/// The robot at each vertex at times since the paths were planned.
+#[derive(Default)]
+struct Reservations {
+    vertices: FnvHashMap<(Vertex, usize), usize>,
+}
+
+impl Reservations {
+    fn reserve(&mut self, robot: usize, vertex: Vertex, time: usize) {
+        self.vertices.insert((vertex, time), robot);
+    }
+    fn release(&mut self, robot: usize) {
+        self.vertices
+            .retain(|_, &mut reserved_by| reserved_by != robot);
+    }
+    fn other_robot(&self, robot: usize, vertex: Vertex, time: usize) -> Option<usize> {
+        self.vertices
+            .get(&(vertex, time))
+            .cloned()
+            .filter(|&other| other != robot)
+    }
+    /// Whether the robot can move from a vertex to another (or the same) vertex, arriving at the
+    /// time, without colliding with another robot, swapping positions with it or, when that is
+    /// forbidden, following it.
+    fn allows(
+        &self,
+        robot: usize,
+        from: Vertex,
+        to: Vertex,
+        time: usize,
+        forbid_following: bool,
+    ) -> bool {
+        if self.other_robot(robot, to, time).is_some() {
+            return false;
+        }
+        // Another robot that planned before can't follow this one either
+        if forbid_following && self.other_robot(robot, to, time + 1).is_some() {
+            return false;
+        }
+        if from != to {
+            if let Some(other) = self.other_robot(robot, to, time - 1) {
+                if forbid_following || self.other_robot(robot, from, time) == Some(other) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl<'p, 's, 'a> WindowedCooperativeAStar<'p, 's, 'a> {
+    pub fn new(
+        plan: &'p Plan,
+        settings: &'s Settings,
+        assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
+        window: usize,
+        replan_interval: usize,
+    ) -> WindowedCooperativeAStar<'p, 's, 'a> {
+        debug_assert!(0 < replan_interval && replan_interval <= window);
+
+        WindowedCooperativeAStar {
+            settings,
+            plan,
+            assignment_algorithm,
+
+            window,
+            replan_interval,
+            distances: FnvHashMap::default(),
+
+            assignment: Vec::new(),
+            goals: Vec::new(),
+            paths: Vec::new(),
+            planned_at: 0,
+            steps_to_follow: 0,
+            nr_plans: 0,
+            failures: Vec::new(),
+        }
+    }
+    /// Whether the robots are where the current paths say, and the paths tell where to go next.
+    fn follows_paths(&self, state: &State, step: usize) -> bool {
+        step < self.steps_to_follow
+            && self.paths.len() == state.robot_states.len()
+            && state.robot_states.iter().all(|robot| {
+                let path = &self.paths[robot.robot_id];
+                let continues = match self.goals[robot.robot_id] {
+                    Goal::Serve(_) => step + 1 < path.len(),
+                    Goal::Park(_) => true,
+                };
+                continues && robot.vertex == Some(path[step.min(path.len() - 1)])
+            })
+    }
+    /// Paths through the window for the robots, in order of priority, and the robots that found
+    /// no path.
+    ///
+    /// Robots that didn't plan yet are expected to stay for the first step, such that robots that
+    /// find no path can always wait.
+    fn find_paths(
+        &mut self,
+        starts: &Vec<Vertex>,
+        goals: &Vec<Goal>,
+    ) -> (Vec<Vec<Vertex>>, Vec<usize>) {
+        for goal in goals.iter() {
+            let plan = self.plan;
+            self.distances
+                .entry(goal.vertex())
+                .or_insert_with(|| breadth_first_distances(plan, goal.vertex()));
+        }
+
+        let mut reservations = Reservations::default();
+        for (robot, &start) in starts.iter().enumerate() {
+            reservations.reserve(robot, start, 0);
+            reservations.reserve(robot, start, 1);
+        }
+
+        let nr_robots = starts.len();
+        let mut paths = vec![Vec::new(); nr_robots];
+        let mut failed = Vec::new();
+        for robot in (0..nr_robots).map(|robot| (robot + self.nr_plans) % nr_robots) {
+            let path = match self.find_path(robot, starts[robot], goals[robot], &reservations) {
+                Some(path) => path,
+                None => {
+                    failed.push(robot);
+                    vec![starts[robot], starts[robot]]
+                }
+            };
+
+            reservations.release(robot);
+            for time in 0..(self.window + 1) {
+                if let Some(vertex) = position(&path, goals[robot], time) {
+                    reservations.reserve(robot, vertex, time);
+                }
+            }
+            paths[robot] = path;
+        }
+        self.nr_plans += 1;
+
+        (paths, failed)
+    }
+    /// A path for a single robot towards its goal, that avoids the reservations of the other
+    /// robots within the window.
+    ///
+    /// The path ends at the end of the window, or earlier when the robot serves or parks at its
+    /// goal.
+    fn find_path(
+        &self,
+        robot: usize,
+        start: Vertex,
+        goal: Goal,
+        reservations: &Reservations,
+    ) -> Option<Vec<Vertex>> {
+        let forbid_following = self.settings.forbid_following;
+        let distances = &self.distances[&goal.vertex()];
+
+        let mut came_from = FnvHashMap::default();
+        let mut to_visit: PriorityQueue<(usize, Vertex), (Reverse<u64>, usize)> =
+            PriorityQueue::new();
+        to_visit.push((0, start), (Reverse(*distances.get(&start)?), 0));
+
+        while let Some((current, _)) = to_visit.pop() {
+            let (time, vertex) = current;
+            if vertex == goal.vertex() {
+                match goal {
+                    Goal::Serve(_)
+                        if reservations.allows(
+                            robot,
+                            vertex,
+                            vertex,
+                            time + 1,
+                            forbid_following,
+                        ) =>
+                    {
+                        let mut path = reconstruct_path(&came_from, current);
+                        path.push(vertex);
+                        return Some(path);
+                    }
+                    Goal::Park(_)
+                        if ((time + 1)..(self.window + 1)).all(|later| {
+                            reservations.other_robot(robot, vertex, later).is_none()
+                        }) =>
+                    {
+                        return Some(reconstruct_path(&came_from, current));
+                    }
+                    _ => (),
+                }
+            }
+            if time == self.window {
+                return Some(reconstruct_path(&came_from, current));
+            }
+
+            let next_time = time + 1;
+            for neighbor in self.plan.neighbors(&vertex).into_iter().chain(Some(vertex)) {
+                let next = (next_time, neighbor);
+                if came_from.contains_key(&next)
+                    || !reservations.allows(robot, vertex, neighbor, next_time, forbid_following)
+                {
+                    continue;
+                }
+                if let Some(&distance) = distances.get(&neighbor) {
+                    came_from.insert(next, current);
+                    let estimate = next_time as u64 + distance;
+                    to_visit.push(next, (Reverse(estimate), next_time));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn reconstruct_path(
+    came_from: &FnvHashMap<(usize, Vertex), (usize, Vertex)>,
+    end: (usize, Vertex),
+) -> Vec<Vertex> {
+    let mut path = vec![end.1];
+    let mut current = end;
+    while let Some(&previous) = came_from.get(&current) {
+        path.push(previous.1);
+        current = previous;
+    }
+    path.reverse();
+
+    path
+}
+
+impl<'p, 's, 'a> PathAlgorithm<'p, 's, 'a> for WindowedCooperativeAStar<'p, 's, 'a> {
+    fn initialize(&mut self) -> Result<(), NoSolutionError> {
+        Ok(())
+    }
+
+    fn next_step(&mut self, history: &History) -> Instructions {
+        let state = history.last_state();
+        if self.contains_new_requests(history) {
+            self.assignment = assign_requests(
+                self.plan,
+                self.settings,
+                &mut *self.assignment_algorithm,
+                state,
+            );
+        }
+
+        let goals = goals(&self.assignment, state, &self.goals);
+        let step = history.time() - self.planned_at;
+        if goals != self.goals || !self.follows_paths(state, step) {
+            let starts = state
+                .robot_states
+                .iter()
+                .map(|robot| robot.vertex.unwrap())
+                .collect();
+            let (paths, failed) = self.find_paths(&starts, &goals);
+            self.paths = paths;
+            self.planned_at = history.time();
+            self.goals = goals;
+            self.steps_to_follow = if failed.is_empty() {
+                self.replan_interval
+            } else {
+                self.failures.push((history.time(), failed));
+                // The robots that wait are only avoided in the first step
+                1
+            };
+        }
+
+        let step = history.time() - self.planned_at;
+        let new_locations = self
+            .paths
+            .iter()
+            .map(|path| path[(step + 1).min(path.len() - 1)])
+            .collect();
+        instructions(&mut self.assignment, history, new_locations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use fnv::FnvHashMap;
+
+    use algorithm::assignment::greedy_makespan::GreedyMakespan;
+    use algorithm::path::goals::Goal;
+    use algorithm::path::whca::Reservations;
+    use algorithm::path::whca::WindowedCooperativeAStar;
+    use algorithm::path::PathAlgorithm;
+    use algorithm::solver::SolverSettings;
+    use simulation::demand::Request;
+    use simulation::placement::Placement;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use simulation::plan::Vertex;
+    use simulation::settings::Settings;
+    use simulation::state::History;
+    use simulation::state::RobotState;
+    use simulation::state::State;
+    use simulation::MoveInstruction;
+
+    fn settings(nr_robots: usize) -> Settings {
+        Settings {
+            total_time: 10,
+            nr_robots,
+            nr_requests: 1,
+            output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        }
+    }
+
+    #[test]
+    fn test_reservations() {
+        let (a, b, c) = (
+            Vertex { x: 0, y: 0 },
+            Vertex { x: 1, y: 0 },
+            Vertex { x: 2, y: 0 },
+        );
+        let mut reservations = Reservations::default();
+        reservations.reserve(0, a, 0);
+        reservations.reserve(0, b, 1);
+
+        assert!(!reservations.allows(1, c, b, 1, false));
+        assert!(reservations.allows(0, a, b, 1, false));
+        // Swapping positions
+        assert!(!reservations.allows(1, b, a, 1, false));
+        // Following
+        assert!(reservations.allows(1, c, a, 1, false));
+        assert!(!reservations.allows(1, c, a, 1, true));
+        // Being followed
+        reservations.reserve(0, c, 2);
+        assert!(reservations.allows(1, b, c, 1, false));
+        assert!(!reservations.allows(1, b, c, 1, true));
+
+        reservations.release(0);
+        assert!(reservations.allows(1, c, b, 1, true));
+    }
+
+    #[test]
+    fn test_find_path() {
+        let plan = OneThreeRectangle::new(10, 10);
+        let settings = settings(2);
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm =
+            WindowedCooperativeAStar::new(&plan, &settings, assignment_algorithm, 3, 2);
+
+        let starts = vec![Vertex { x: 2, y: 0 }, Vertex { x: 0, y: 0 }];
+        let goals = vec![
+            Goal::Park(Vertex { x: 2, y: 0 }),
+            Goal::Serve(Vertex { x: 9, y: 0 }),
+        ];
+        let (paths, failed) = algorithm.find_paths(&starts, &goals);
+
+        assert!(failed.is_empty());
+        assert_eq!(paths[0], vec![Vertex { x: 2, y: 0 }]);
+        // The second robot only plans through the window, around the parked robot
+        assert_eq!(paths[1].len(), 3 + 1);
+        assert_eq!(paths[1][3].distance(Vertex { x: 9, y: 0 }), 8);
+        assert!(!paths[1].contains(&Vertex { x: 2, y: 0 }));
+    }
+
+    #[test]
+    fn test_next_step() {
+        let plan = OneThreeRectangle::new(10, 10);
+        let settings = settings(1);
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let mut algorithm =
+            WindowedCooperativeAStar::new(&plan, &settings, assignment_algorithm, 4, 2);
+
+        let history = History {
+            states: vec![State {
+                robot_states: vec![RobotState {
+                    robot_id: 0,
+                    parcel_id: Some(0),
+                    vertex: Some(Vertex { x: 2, y: 0 }),
+                }],
+                requests: map!
+                [
+                    0 => Request {
+                        from: Vertex { x: 0, y: 0 },
+                        to: Vertex { x: 2, y: 2 },
+                    },
+                ],
+            }],
+            calculation_times: Vec::new(),
+        };
+
+        assert_eq!(
+            algorithm.next_step(&history).movements,
+            vec![MoveInstruction {
+                robot_id: 0,
+                vertex: Vertex { x: 2, y: 1 },
+            }]
+        );
+    }
+}
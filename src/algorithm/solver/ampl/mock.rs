@@ -0,0 +1,351 @@
+//! A stand-in for AMPL, such that the files that `Ampl` writes and the output it reads are tested
+//! without an installation.
+//!
+//! The mock reads the data file back into a model, solves it with `BranchAndBound` and executes
+//! the statements of the run file. Files that AMPL would not accept make it panic, such that the
+//! test using it fails instead of the algorithm falling back silently.
+use algorithm::solver::ampl::Ampl;
+use algorithm::solver::ampl::MODEL;
+use algorithm::solver::branch_and_bound::BranchAndBound;
+use algorithm::solver::Comparison;
+use algorithm::solver::Constraint;
+use algorithm::solver::Model;
+use algorithm::solver::Solution;
+use algorithm::solver::SolveError;
+use algorithm::solver::Solver;
+use algorithm::solver::Status;
+use algorithm::solver::Variable;
+use fnv::FnvHashMap;
+use std::f64;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::time::Duration;
+
+/// Solves models through the files and the output of `Ampl`, without running AMPL.
+pub struct MockAmpl {
+    /// Solve result number and message that are reported instead of those of the solve
+    result: Option<(i64, String)>,
+}
+
+impl MockAmpl {
+    pub fn new() -> MockAmpl {
+        MockAmpl { result: None }
+    }
+    /// Reports this solve result, like a solver that is infeasible or stopped at a limit would.
+    pub fn with_result(number: i64, message: &str) -> MockAmpl {
+        MockAmpl {
+            result: Some((number, message.to_string())),
+        }
+    }
+    /// Executes the statements of a run file and returns what AMPL would print.
+    fn run(&self, run_path: &Path) -> Result<String, String> {
+        let run_file = read_to_string(run_path).map_err(|error| error.to_string())?;
+        let mut model = None;
+        let mut options = FnvHashMap::default();
+        let mut result = None;
+        let mut output = String::new();
+
+        for statement in run_file.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+            let (command, rest) = match statement.find(char::is_whitespace) {
+                Some(index) => (&statement[..index], statement[index..].trim()),
+                None => (statement, ""),
+            };
+            match command {
+                "model" => {
+                    if read_to_string(unquote(rest)?).map_err(|e| e.to_string())? != MODEL {
+                        return Err("The model file differs from the model".to_string());
+                    }
+                }
+                "data" => {
+                    let text = read_to_string(unquote(rest)?).map_err(|e| e.to_string())?;
+                    model = Some(read_data(&text)?);
+                }
+                "option" => {
+                    let mut parts = rest.splitn(2, char::is_whitespace);
+                    let name = parts.next().unwrap().to_string();
+                    let value = parts
+                        .next()
+                        .ok_or(format!("Option {} has no value", name))?;
+                    options.insert(name, unquote(value.trim()).unwrap_or(value).to_string());
+                }
+                "solve" => {
+                    let model = model.as_ref().ok_or("Solve before the data is read")?;
+                    if !options.contains_key("solver") {
+                        return Err("Solve without a solver".to_string());
+                    }
+                    result = Some(self.solve_data(model, &options));
+                }
+                "printf" => {
+                    let model = model.as_ref().ok_or("Printf before the data is read")?;
+                    let result = result.as_ref().ok_or("Printf before the solve")?;
+                    output.push_str(&printf(rest, model, result)?);
+                }
+                _ => return Err(format!("Unknown statement \"{}\"", statement)),
+            }
+        }
+
+        Ok(output)
+    }
+    /// The solve result number, the message and the values of the variables.
+    fn solve_data(
+        &self,
+        model: &Model,
+        options: &FnvHashMap<String, String>,
+    ) -> (i64, String, Vec<f64>) {
+        // The limits are given as solver options, such as "timelim=30 mipgap=0.01"
+        let (mut time_limit, mut relative_gap) = (None, 0_f64);
+        for (name, value) in options
+            .iter()
+            .filter(|(name, _)| name.ends_with("_options"))
+        {
+            for option in value.split_whitespace() {
+                let mut parts = option.splitn(2, '=');
+                let (key, value) = (parts.next().unwrap(), parts.next());
+                let value = value
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .unwrap_or_else(|| panic!("Invalid option \"{}\" in {}", option, name));
+                match key {
+                    "timelim" | "time" => time_limit = Some(Duration::from_secs_f64(value)),
+                    "mipgap" => relative_gap = value,
+                    _ => (),
+                }
+            }
+        }
+        let solver = BranchAndBound::new(time_limit).with_relative_gap(relative_gap);
+
+        // Like AMPL, the values are those from the start when there is no solution
+        let start = model
+            .start
+            .iter()
+            .map(|value| value.unwrap_or(0_f64))
+            .collect();
+        let (number, message, values) = match solver.solve(model) {
+            Ok(Solution {
+                status: Status::Optimal,
+                values,
+                ..
+            }) => (0, "optimal solution", values),
+            Ok(Solution { values, .. }) => (400, "time limit", values),
+            Err(SolveError::Infeasible) => (200, "infeasible problem", start),
+            Err(SolveError::Unbounded) => (300, "unbounded problem", start),
+            Err(SolveError::NoSolutionFound) => (400, "time limit", start),
+            Err(_) => (500, "failure", start),
+        };
+        match self.result {
+            Some((number, ref message)) => (number, message.clone(), values),
+            None => (number, message.to_string(), values),
+        }
+    }
+}
+
+impl Solver for MockAmpl {
+    fn solve(&self, model: &Model) -> Result<Solution, SolveError> {
+        let (_directory, run_path) = Ampl::new("ampl", "gurobi")
+            .with_time_limit(Some(Duration::from_secs(30)))
+            .write_files(model)?;
+        let output = self
+            .run(&run_path)
+            .unwrap_or_else(|error| panic!("AMPL would not accept the files: {}", error));
+
+        Ampl::parse_output(model, &output)
+    }
+}
+
+fn unquote(text: &str) -> Result<&str, String> {
+    if text.len() >= 2 && text.starts_with('\'') && text.ends_with('\'') {
+        Ok(&text[1..(text.len() - 1)])
+    } else {
+        Err(format!("Expected a quoted string, not \"{}\"", text))
+    }
+}
+
+/// Reads a data file, as written by `Ampl`, into a model.
+fn read_data(text: &str) -> Result<Model, String> {
+    let mut values = FnvHashMap::default();
+    for statement in text.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+        let index = statement
+            .find(":=")
+            .ok_or(format!("Statement \"{}\" has no :=", statement))?;
+        let name = statement[..index].split_whitespace().collect::<Vec<_>>();
+        let tokens = statement[(index + 2)..]
+            .split_whitespace()
+            .map(|token| match token.parse::<f64>() {
+                // AMPL writes infinity as "Infinity", it doesn't read Rust's "inf"
+                Ok(value) if value.is_finite() => Ok(value),
+                _ => Err(format!("Invalid number \"{}\"", token)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.insert(name.join(" "), tokens).is_some() {
+            return Err(format!("Statement \"{}\" is repeated", name.join(" ")));
+        }
+    }
+    let mut take = |name: &str, width: usize| -> Result<Vec<Vec<f64>>, String> {
+        let tokens = values.remove(name).unwrap_or_default();
+        if tokens.len() % width != 0 {
+            return Err(format!("{} has an incomplete row", name));
+        }
+        Ok(tokens.chunks(width).map(|row| row.to_vec()).collect())
+    };
+
+    let nr_variables = take("param nr_variables", 1)?[0][0] as usize;
+    let nr_constraints = take("param nr_constraints", 1)?[0][0] as usize;
+    let variable = |value: f64| -> Result<usize, String> {
+        if value >= 0_f64 && (value as usize) < nr_variables && value == value.round() {
+            Ok(value as usize)
+        } else {
+            Err(format!("Variable {} doesn't exist", value))
+        }
+    };
+    let constraint = |value: f64| -> Result<usize, String> {
+        if value >= 0_f64 && (value as usize) < nr_constraints && value == value.round() {
+            Ok(value as usize)
+        } else {
+            Err(format!("Constraint {} doesn't exist", value))
+        }
+    };
+
+    let mut model = Model::new();
+    model.integer = vec![false; nr_variables];
+    model.lower = vec![f64::NEG_INFINITY; nr_variables];
+    model.upper = vec![f64::INFINITY; nr_variables];
+    model.cost = vec![0_f64; nr_variables];
+    model.start = vec![None; nr_variables];
+    for row in take("set INTEGER", 1)? {
+        model.integer[variable(row[0])?] = true;
+    }
+    for row in take("param lower", 2)? {
+        model.lower[variable(row[0])?] = row[1];
+    }
+    for row in take("param upper", 2)? {
+        model.upper[variable(row[0])?] = row[1];
+    }
+    for row in take("param cost", 2)? {
+        model.cost[variable(row[0])?] = row[1];
+    }
+
+    let mut comparisons = vec![Comparison::Equal; nr_constraints];
+    for row in take("set LESS_EQUAL", 1)? {
+        comparisons[constraint(row[0])?] = Comparison::LessEqual;
+    }
+    for row in take("set GREATER_EQUAL", 1)? {
+        comparisons[constraint(row[0])?] = Comparison::GreaterEqual;
+    }
+    let mut terms = vec![Vec::new(); nr_constraints];
+    for row in take("param: TERMS: coefficient", 3)? {
+        terms[constraint(row[0])?].push((Variable(variable(row[1])?), row[2]));
+    }
+    let mut rhs = vec![0_f64; nr_constraints];
+    for row in take("param rhs", 2)? {
+        rhs[constraint(row[0])?] = row[1];
+    }
+    model.constraints = terms
+        .into_iter()
+        .zip(comparisons.into_iter().zip(rhs.into_iter()))
+        .map(|(terms, (comparison, rhs))| Constraint {
+            terms,
+            comparison,
+            rhs,
+        })
+        .collect();
+
+    for &(name, integer) in &[("var Integer", true), ("var Continuous", false)] {
+        for row in take(name, 2)? {
+            let variable = variable(row[0])?;
+            if model.integer[variable] != integer {
+                return Err(format!("{} has a start for variable {}", name, variable));
+            }
+            model.start[variable] = Some(row[1]);
+        }
+    }
+
+    match values.keys().next() {
+        Some(name) => Err(format!("Unknown statement \"{}\"", name)),
+        None => Ok(model),
+    }
+}
+
+/// Executes a `printf` statement, with an optional indexing over the integer or continuous
+/// variables.
+fn printf(
+    statement: &str,
+    model: &Model,
+    result: &(i64, String, Vec<f64>),
+) -> Result<String, String> {
+    let (set, statement) = if statement.starts_with('{') {
+        let end = statement.find('}').ok_or("Unclosed indexing")?;
+        let indexing = statement[1..end].split_whitespace().collect::<Vec<_>>();
+        match indexing.as_slice() {
+            ["v", "in", "INTEGER"] => (Some(true), statement[(end + 1)..].trim()),
+            ["v", "in", "CONTINUOUS"] => (Some(false), statement[(end + 1)..].trim()),
+            _ => return Err(format!("Unknown indexing \"{}\"", &statement[..=end])),
+        }
+    } else {
+        (None, statement)
+    };
+    if !statement.starts_with('"') {
+        return Err(format!("Printf without a format: {}", statement));
+    }
+    let end = statement[1..].find('"').ok_or("Unclosed format")? + 1;
+    let format = &statement[1..end];
+    let arguments = statement[(end + 1)..]
+        .split(',')
+        .map(str::trim)
+        .filter(|argument| !argument.is_empty())
+        .collect::<Vec<_>>();
+
+    let (number, ref message, ref values) = *result;
+    let indices = match set {
+        Some(integer) => (0..model.nr_variables())
+            .filter(|&v| model.integer[v] == integer)
+            .map(Some)
+            .collect(),
+        None => vec![None],
+    };
+    let mut output = String::new();
+    for index in indices {
+        let mut arguments = arguments.iter();
+        let mut characters = format.chars();
+        while let Some(character) = characters.next() {
+            match character {
+                '\\' => match characters.next() {
+                    Some('n') => output.push('\n'),
+                    other => return Err(format!("Unknown escape {:?}", other)),
+                },
+                '%' => {
+                    let mut conversion = String::new();
+                    for character in characters.by_ref() {
+                        conversion.push(character);
+                        if character.is_alphabetic() {
+                            break;
+                        }
+                    }
+                    let argument = arguments.next().ok_or("Too few arguments")?;
+                    let value = match (conversion.as_str(), *argument, index) {
+                        ("d", "solve_result_num", _) => number.to_string(),
+                        ("s", "solve_message", _) => message.clone(),
+                        ("d", "v", Some(v)) => v.to_string(),
+                        (".17g", "Integer[v]", Some(v)) if model.integer[v] => {
+                            values[v].to_string()
+                        }
+                        (".17g", "Continuous[v]", Some(v)) if !model.integer[v] => {
+                            values[v].to_string()
+                        }
+                        _ => {
+                            return Err(format!(
+                                "Can't print \"{}\" with %{}",
+                                argument, conversion
+                            ))
+                        }
+                    };
+                    output.push_str(&value);
+                }
+                character => output.push(character),
+            }
+        }
+        if arguments.next().is_some() {
+            return Err("Too many arguments".to_string());
+        }
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,479 @@
+use algorithm::solver::Comparison;
+use algorithm::solver::Model;
+use algorithm::solver::Solution;
+use algorithm::solver::SolveError;
+use algorithm::solver::Solver;
+use algorithm::solver::Status;
+use algorithm::WorkingDirectory;
+use algorithm::DAT_FILE_NAME;
+use algorithm::RUN_FILE_NAME;
+use fnv::FnvHashMap;
+use std::f64;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+#[cfg(test)]
+pub mod mock;
+
+const MOD_FILE_NAME: &str = "model.mod";
+const WORKING_DIRECTORY: &str = "ampl";
+const RESULT_PREFIX: &str = "solve_result_num:";
+const MESSAGE_PREFIX: &str = "solve_message:";
+const VALUE_PREFIX: &str = "value:";
+
+/// A model in which the variables, constraints and objective are all data, such that any `Model`
+/// can be written as an AMPL data file for it.
+const MODEL: &str = include_str!("model.mod");
+
+/// Solves models by running AMPL with a solver such as Gurobi or CPLEX.
+pub struct Ampl {
+    ampl_path: String,
+    /// Name of the solver or the path to its executable
+    solver: String,
+    time_limit: Option<Duration>,
+    mip_gap: f64,
+    threads: Option<usize>,
+}
+
+impl Ampl {
+    pub fn new(ampl_path: &str, solver: &str) -> Ampl {
+        Ampl {
+            ampl_path: ampl_path.to_string(),
+            solver: solver.to_string(),
+            time_limit: None,
+            mip_gap: 0_f64,
+            threads: None,
+        }
+    }
+    pub fn with_time_limit(mut self, time_limit: Option<Duration>) -> Ampl {
+        self.time_limit = time_limit;
+        self
+    }
+    pub fn with_mip_gap(mut self, mip_gap: f64) -> Ampl {
+        self.mip_gap = mip_gap;
+        self
+    }
+    pub fn with_threads(mut self, threads: Option<usize>) -> Ampl {
+        self.threads = threads;
+        self
+    }
+    /// The AMPL option that holds the options of the solver, and the solver options for the
+    /// limits.
+    ///
+    /// Solvers name their options differently, those that are not known are assumed to take
+    /// the same options as Gurobi.
+    fn solver_options(&self) -> (String, Vec<String>) {
+        let name = Path::new(&self.solver)
+            .file_stem()
+            .map_or(self.solver.clone(), |stem| {
+                stem.to_string_lossy().into_owned()
+            });
+        let (time_limit, mip_gap, threads) = match name.as_str() {
+            "cplex" => ("time", "mipgap", "threads"),
+            _ => ("timelim", "mipgap", "threads"),
+        };
+
+        let mut options = Vec::new();
+        if let Some(limit) = self.time_limit {
+            options.push(format!("{}={}", time_limit, limit.as_secs_f64()));
+        }
+        if self.mip_gap > 0_f64 {
+            options.push(format!("{}={}", mip_gap, self.mip_gap));
+        }
+        if let Some(number) = self.threads {
+            options.push(format!("{}={}", threads, number));
+        }
+
+        (format!("{}_options", name), options)
+    }
+    fn write_data_file(path: impl AsRef<Path>, model: &Model) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(file, "param nr_variables := {};", model.nr_variables())?;
+        writeln!(file, "param nr_constraints := {};", model.nr_constraints())?;
+
+        writeln!(file, "set INTEGER :=")?;
+        for variable in (0..model.nr_variables()).filter(|&v| model.integer[v]) {
+            writeln!(file, "  {}", variable)?;
+        }
+        writeln!(file, ";")?;
+        // Only values that differ from the defaults in the model are written
+        for &(name, values, default) in &[
+            ("lower", &model.lower, f64::NEG_INFINITY),
+            ("upper", &model.upper, f64::INFINITY),
+            ("cost", &model.cost, 0_f64),
+        ] {
+            writeln!(file, "param {} :=", name)?;
+            for (variable, &value) in values.iter().enumerate() {
+                if value != default {
+                    writeln!(file, "  {} {}", variable, value)?;
+                }
+            }
+            writeln!(file, ";")?;
+        }
+
+        for (name, comparison) in &[
+            ("LESS_EQUAL", Comparison::LessEqual),
+            ("GREATER_EQUAL", Comparison::GreaterEqual),
+        ] {
+            writeln!(file, "set {} :=", name)?;
+            for (index, constraint) in model.constraints.iter().enumerate() {
+                if constraint.comparison == *comparison {
+                    writeln!(file, "  {}", index)?;
+                }
+            }
+            writeln!(file, ";")?;
+        }
+        writeln!(file, "param: TERMS: coefficient :=")?;
+        for (index, constraint) in model.constraints.iter().enumerate() {
+            // A variable can only appear once in each row of the data
+            let mut coefficients = FnvHashMap::default();
+            for &(variable, coefficient) in constraint.terms.iter() {
+                *coefficients.entry(variable.0).or_insert(0_f64) += coefficient;
+            }
+            let mut coefficients = coefficients.into_iter().collect::<Vec<_>>();
+            coefficients.sort_by_key(|&(variable, _)| variable);
+            for (variable, coefficient) in coefficients {
+                writeln!(file, "  {} {} {}", index, variable, coefficient)?;
+            }
+        }
+        writeln!(file, ";")?;
+        writeln!(file, "param rhs :=")?;
+        for (index, constraint) in model.constraints.iter().enumerate() {
+            if constraint.rhs != 0_f64 {
+                writeln!(file, "  {} {}", index, constraint.rhs)?;
+            }
+        }
+        writeln!(file, ";")?;
+
+        for &(name, integer) in &[("Integer", true), ("Continuous", false)] {
+            let starts = model
+                .start
+                .iter()
+                .enumerate()
+                .filter(|&(variable, _)| model.integer[variable] == integer)
+                .filter_map(|(variable, start)| start.map(|value| (variable, value)))
+                .collect::<Vec<_>>();
+            if !starts.is_empty() {
+                writeln!(file, "var {} :=", name)?;
+                for (variable, value) in starts {
+                    writeln!(file, "  {} {}", variable, value)?;
+                }
+                writeln!(file, ";")?;
+            }
+        }
+
+        Ok(())
+    }
+    fn write_run_file(
+        &self,
+        path: impl AsRef<Path>,
+        model_path: impl AsRef<Path>,
+        data_path: impl AsRef<Path>,
+    ) -> io::Result<()> {
+        let mut file = File::create(path)?;
+
+        writeln!(file, "model '{}';", model_path.as_ref().display())?;
+        writeln!(file, "data '{}';", data_path.as_ref().display())?;
+        writeln!(file, "option solver '{}';", self.solver)?;
+        writeln!(file, "option show_stats 0;")?;
+        let (option, values) = self.solver_options();
+        if !values.is_empty() {
+            writeln!(file, "option {} '{}';", option, values.join(" "))?;
+        }
+        writeln!(file, "solve;")?;
+        // Every value is printed on its own line with a prefix, such that the output of the solver
+        // can't be mistaken for it
+        writeln!(
+            file,
+            "printf \"{} %d\\n\", solve_result_num;",
+            RESULT_PREFIX
+        )?;
+        writeln!(file, "printf \"{} %s\\n\", solve_message;", MESSAGE_PREFIX)?;
+        for name in &["Integer", "Continuous"] {
+            writeln!(
+                file,
+                "printf {{v in {}}} \"{} %d %.17g\\n\", v, {}[v];",
+                name.to_uppercase(),
+                VALUE_PREFIX,
+                name
+            )?;
+        }
+
+        Ok(())
+    }
+    /// Reads the solve result and the values of the variables that the run file prints.
+    ///
+    /// Other lines, such as those of the solver, are skipped. The solve result is a number of
+    /// which the hundreds tell the outcome, see the AMPL documentation on `solve_result_num`.
+    fn parse_output(model: &Model, output: &str) -> Result<Solution, SolveError> {
+        let mut result = None;
+        let mut message = String::new();
+        let mut values = vec![None; model.nr_variables()];
+
+        for line in output.lines() {
+            let unexpected = || SolveError::Failed(format!("Unexpected line \"{}\"", line));
+            if line.starts_with(RESULT_PREFIX) {
+                let number = line[RESULT_PREFIX.len()..].trim().parse::<i64>();
+                result = Some(number.map_err(|_| unexpected())?);
+            } else if line.starts_with(MESSAGE_PREFIX) {
+                message = line[MESSAGE_PREFIX.len()..].trim().to_string();
+            } else if line.starts_with(VALUE_PREFIX) {
+                let fields = line[VALUE_PREFIX.len()..]
+                    .split_whitespace()
+                    .collect::<Vec<_>>();
+                match fields.as_slice() {
+                    [variable, value] => match (variable.parse::<usize>(), value.parse::<f64>()) {
+                        (Ok(variable), Ok(value)) if variable < values.len() => {
+                            values[variable] = Some(value)
+                        }
+                        _ => return Err(unexpected()),
+                    },
+                    _ => return Err(unexpected()),
+                }
+            }
+        }
+
+        let result = match result {
+            Some(result) => result,
+            None => {
+                return Err(SolveError::Failed(
+                    "AMPL didn't report a solve result".to_string(),
+                ))
+            }
+        };
+        let status = match result {
+            0..=99 => Status::Optimal,
+            100..=199 => Status::Feasible,
+            200..=299 => return Err(SolveError::Infeasible),
+            300..=399 => return Err(SolveError::Unbounded),
+            // A limit was reached, the values are those of the best solution if there is one
+            400..=499 => Status::Feasible,
+            _ => return Err(SolveError::Failed(format!("{} ({})", message, result))),
+        };
+        let values = match values.into_iter().collect::<Option<Vec<_>>>() {
+            Some(values) => values,
+            None => {
+                return Err(SolveError::Failed(
+                    "AMPL didn't print the value of every variable".to_string(),
+                ))
+            }
+        };
+        if !model.is_feasible(&values) {
+            return Err(match status {
+                Status::Feasible => SolveError::NoSolutionFound,
+                Status::Optimal => {
+                    SolveError::Failed("The solution doesn't satisfy the model".to_string())
+                }
+            });
+        }
+
+        let objective = values
+            .iter()
+            .zip(model.cost.iter())
+            .map(|(value, cost)| value * cost)
+            .sum();
+        let gap = match status {
+            Status::Optimal => 0_f64,
+            Status::Feasible => f64::NAN,
+        };
+        Ok(Solution {
+            values,
+            objective,
+            status,
+            gap,
+        })
+    }
+    /// Writes the model, data and run files to an empty working directory and returns it, along
+    /// with the path of the run file. The files are removed when the directory is dropped.
+    fn write_files(&self, model: &Model) -> Result<(WorkingDirectory, PathBuf), SolveError> {
+        let written = (|| {
+            let directory = WorkingDirectory::create(WORKING_DIRECTORY)?;
+            let model_path = directory.path().join(MOD_FILE_NAME);
+            let data_path = directory.path().join(DAT_FILE_NAME);
+            let run_path = directory.path().join(RUN_FILE_NAME);
+
+            File::create(&model_path)?.write_all(MODEL.as_bytes())?;
+            Ampl::write_data_file(&data_path, model)?;
+            self.write_run_file(&run_path, &model_path, &data_path)?;
+            Ok((directory, run_path))
+        })();
+
+        written.map_err(|error: io::Error| {
+            SolveError::Failed(format!("Can't write the AMPL files: {}", error))
+        })
+    }
+}
+
+impl Solver for Ampl {
+    fn solve(&self, model: &Model) -> Result<Solution, SolveError> {
+        let (_directory, run_path) = self.write_files(model)?;
+        let output = Command::new(&self.ampl_path)
+            .arg(&run_path)
+            .output()
+            .map_err(|error| {
+                SolveError::Failed(format!("Can't run AMPL at {}: {}", self.ampl_path, error))
+            })?;
+        Ampl::parse_output(model, &String::from_utf8_lossy(&output.stdout)).map_err(|error| {
+            // AMPL writes its own errors, such as a missing license, to the standard error
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match error {
+                SolveError::Failed(ref message) if !stderr.trim().is_empty() => {
+                    SolveError::Failed(format!("{}: {}", message, stderr.trim()))
+                }
+                error => error,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::solver::ampl::mock::MockAmpl;
+    use algorithm::solver::ampl::Ampl;
+    use algorithm::solver::branch_and_bound::BranchAndBound;
+    use algorithm::solver::Comparison;
+    use algorithm::solver::Model;
+    use algorithm::solver::SolveError;
+    use algorithm::solver::Solver;
+    use algorithm::solver::Status;
+    use std::f64;
+    use std::time::Duration;
+
+    fn model() -> Model {
+        let mut model = Model::new();
+        let x = model.add_binary(3_f64);
+        let y = model.add_continuous(0_f64, 10_f64, 1_f64);
+        model.add_binary(1_f64);
+        model.add_constraint(vec![(x, 1_f64), (y, 1_f64)], Comparison::Equal, 2.5);
+
+        model
+    }
+
+    #[test]
+    fn test_parse_output() {
+        let model = model();
+        let output = "Gurobi 8.0.0: optimal solution; objective 4.5\n\
+                      solve_result_num: 0\n\
+                      solve_message: Gurobi 8.0.0: optimal solution; objective 4.5\n\
+                      value: 0 1\n\
+                      value: 2 0\n\
+                      value: 1 1.5\n";
+        let solution = Ampl::parse_output(&model, output).unwrap();
+        assert_eq!(solution.values, vec![1_f64, 1.5, 0_f64]);
+        assert_eq!(solution.objective(), 4.5);
+        assert_eq!(solution.status(), Status::Optimal);
+        assert_eq!(solution.gap(), 0_f64);
+
+        // Stopped at the time limit with a solution
+        let output = output.replace("solve_result_num: 0", "solve_result_num: 400");
+        let solution = Ampl::parse_output(&model, &output).unwrap();
+        assert_eq!(solution.status(), Status::Feasible);
+        assert!(solution.gap().is_nan());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let model = model();
+        let result = |number: i64| {
+            format!(
+                "solve_result_num: {}\nsolve_message: message\nvalue: 0 0\nvalue: 1 0\nvalue: 2 0\n",
+                number
+            )
+        };
+
+        assert_eq!(
+            Ampl::parse_output(&model, &result(200)),
+            Err(SolveError::Infeasible)
+        );
+        assert_eq!(
+            Ampl::parse_output(&model, &result(300)),
+            Err(SolveError::Unbounded)
+        );
+        // The values at the limit don't satisfy the constraint, so no solution was found
+        assert_eq!(
+            Ampl::parse_output(&model, &result(400)),
+            Err(SolveError::NoSolutionFound)
+        );
+        assert_eq!(
+            Ampl::parse_output(&model, &result(500)),
+            Err(SolveError::Failed("message (500)".to_string()))
+        );
+        assert!(Ampl::parse_output(&model, "Can't find a license\n").is_err());
+        assert!(Ampl::parse_output(&model, "solve_result_num: 0\nvalue: 0 1\n").is_err());
+        assert!(Ampl::parse_output(&model, "solve_result_num: 0\nvalue: 0 1 2\n").is_err());
+    }
+
+    #[test]
+    fn test_mock() {
+        // The knapsack of the branch and bound tests, through the files of AMPL
+        let mut model = Model::new();
+        let items = [
+            (5_f64, 10_f64),
+            (4_f64, 7_f64),
+            (3_f64, 5_f64),
+            (2_f64, 3_f64),
+        ]
+        .iter()
+        .map(|&(weight, value)| (model.add_binary(-value), weight))
+        .collect::<Vec<_>>();
+        let slack = model.add_continuous(0_f64, f64::INFINITY, 0.5);
+        let mut terms = items.clone();
+        terms.push((slack, 1_f64));
+        model.add_constraint(terms, Comparison::Equal, 9_f64);
+        model.set_start(items[3].0, 1_f64);
+
+        let solution = MockAmpl::new().solve(&model).unwrap();
+        assert_eq!(solution, BranchAndBound::new(None).solve(&model).unwrap());
+        assert_eq!(solution.objective(), -17_f64);
+
+        assert_eq!(
+            MockAmpl::with_result(200, "infeasible problem").solve(&model),
+            Err(SolveError::Infeasible)
+        );
+        let solution = MockAmpl::with_result(400, "time limit")
+            .solve(&model)
+            .unwrap();
+        assert_eq!(solution.status(), Status::Feasible);
+        assert_eq!(solution.objective(), -17_f64);
+    }
+
+    #[test]
+    fn test_files_removed() {
+        let (directory, run_path) = Ampl::new("ampl", "gurobi")
+            .write_files(&model())
+            .unwrap();
+        assert!(run_path.exists());
+        drop(directory);
+        assert!(!run_path.parent().unwrap().exists());
+    }
+
+    #[test]
+    fn test_solver_options() {
+        let ampl = Ampl::new("ampl", "/opt/ampl/cplex")
+            .with_time_limit(Some(Duration::from_secs(30)))
+            .with_threads(Some(2));
+        assert_eq!(
+            ampl.solver_options(),
+            (
+                "cplex_options".to_string(),
+                vec!["time=30".to_string(), "threads=2".to_string()]
+            )
+        );
+
+        let ampl = Ampl::new("ampl", "gurobi").with_mip_gap(0.01);
+        assert_eq!(
+            ampl.solver_options(),
+            (
+                "gurobi_options".to_string(),
+                vec!["mipgap=0.01".to_string()]
+            )
+        );
+    }
+}
@@ -0,0 +1,33 @@
+param nr_variables integer >= 0;
+param nr_constraints integer >= 0;
+set VARIABLES = 0..(nr_variables - 1);
+set CONSTRAINTS = 0..(nr_constraints - 1);
+
+set INTEGER within VARIABLES;
+set CONTINUOUS = VARIABLES diff INTEGER;
+param lower {VARIABLES} default -Infinity;
+param upper {VARIABLES} default Infinity;
+param cost {VARIABLES} default 0;
+
+set LESS_EQUAL within CONSTRAINTS;
+set GREATER_EQUAL within CONSTRAINTS;
+set EQUAL = CONSTRAINTS diff (LESS_EQUAL union GREATER_EQUAL);
+set TERMS within CONSTRAINTS cross VARIABLES;
+param coefficient {TERMS};
+param rhs {CONSTRAINTS} default 0;
+
+var Integer {v in INTEGER} integer >= lower[v], <= upper[v];
+var Continuous {v in CONTINUOUS} >= lower[v], <= upper[v];
+
+minimize Objective:
+  sum {v in INTEGER} cost[v] * Integer[v] + sum {v in CONTINUOUS} cost[v] * Continuous[v];
+
+subject to Less_Equal {c in LESS_EQUAL}:
+  sum {v in INTEGER: (c, v) in TERMS} coefficient[c, v] * Integer[v]
+  + sum {v in CONTINUOUS: (c, v) in TERMS} coefficient[c, v] * Continuous[v] <= rhs[c];
+subject to Greater_Equal {c in GREATER_EQUAL}:
+  sum {v in INTEGER: (c, v) in TERMS} coefficient[c, v] * Integer[v]
+  + sum {v in CONTINUOUS: (c, v) in TERMS} coefficient[c, v] * Continuous[v] >= rhs[c];
+subject to Equal {c in EQUAL}:
+  sum {v in INTEGER: (c, v) in TERMS} coefficient[c, v] * Integer[v]
+  + sum {v in CONTINUOUS: (c, v) in TERMS} coefficient[c, v] * Continuous[v] = rhs[c];
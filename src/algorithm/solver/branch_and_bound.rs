@@ -0,0 +1,280 @@
+use algorithm::solver::simplex::LpStatus;
+use algorithm::solver::simplex::Simplex;
+use algorithm::solver::Model;
+use algorithm::solver::Solution;
+use algorithm::solver::SolveError;
+use algorithm::solver::Solver;
+use algorithm::solver::Status;
+use std::f64;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Largest model the dense simplex implementation is used for, by number of constraints.
+pub const MAXIMUM_CONSTRAINTS: usize = 2_000;
+const INTEGER_TOLERANCE: f64 = 1e-6;
+
+/// Solves models with a depth first branch and bound over linear relaxations.
+///
+/// Nodes branch on the most fractional integer variable and first explore the side the value is
+/// closest to. When the model has start values, the solution they lead to is the first
+/// incumbent.
+pub struct BranchAndBound {
+    time_limit: Option<Duration>,
+    /// Nodes that can't improve on the incumbent by more than this fraction are not explored
+    relative_gap: f64,
+}
+
+struct Node {
+    /// Bounds of the integer variables that differ from those of the model
+    bounds: Vec<(usize, f64, f64)>,
+    /// Objective of the relaxation of the parent node
+    estimate: f64,
+}
+
+impl BranchAndBound {
+    pub fn new(time_limit: Option<Duration>) -> BranchAndBound {
+        BranchAndBound {
+            time_limit,
+            relative_gap: 0_f64,
+        }
+    }
+    /// Stops improving a solution once it is within this fraction of the best possible one.
+    pub fn with_relative_gap(mut self, relative_gap: f64) -> BranchAndBound {
+        self.relative_gap = relative_gap;
+        self
+    }
+    /// Starting values for the variables that have them, the solver chooses the others.
+    fn solve_start(
+        model: &Model,
+        simplex: &mut Simplex,
+        deadline: Option<Instant>,
+    ) -> Option<(f64, Vec<f64>)> {
+        if model.start.iter().all(Option::is_none) {
+            return None;
+        }
+
+        for (variable, start) in model.start.iter().enumerate() {
+            if let &Some(value) = start {
+                simplex.set_bounds(variable, value, value);
+            }
+        }
+        let solution = match simplex.solve(deadline) {
+            LpStatus::Optimal if BranchAndBound::fractional(model, simplex.values()).is_none() => {
+                Some((
+                    simplex.objective(),
+                    BranchAndBound::rounded(model, simplex.values()),
+                ))
+            }
+            _ => None,
+        };
+        for variable in 0..model.nr_variables() {
+            simplex.set_bounds(variable, model.lower[variable], model.upper[variable]);
+        }
+
+        solution
+    }
+    /// The integer variable with a value that is furthest from an integer.
+    fn fractional(model: &Model, values: &[f64]) -> Option<(usize, f64)> {
+        let mut best = None;
+        let mut best_distance = INTEGER_TOLERANCE;
+        for (variable, &value) in values.iter().enumerate() {
+            if model.integer[variable] {
+                let distance = (value - value.round()).abs();
+                if distance > best_distance {
+                    best = Some((variable, value));
+                    best_distance = distance;
+                }
+            }
+        }
+
+        best
+    }
+    fn rounded(model: &Model, values: &[f64]) -> Vec<f64> {
+        values
+            .iter()
+            .zip(model.integer.iter())
+            .map(|(&value, &integer)| if integer { value.round() } else { value })
+            .collect()
+    }
+    /// Whether a node with a relaxation of this objective can be skipped.
+    fn can_prune(&self, model: &Model, bound: f64, incumbent: &Option<(f64, Vec<f64>)>) -> bool {
+        match incumbent {
+            Some((objective, _)) => {
+                let bound = if model.has_integral_objective() {
+                    (bound - INTEGER_TOLERANCE).ceil()
+                } else {
+                    bound
+                };
+                bound >= objective - self.relative_gap * objective.abs() - INTEGER_TOLERANCE
+            }
+            None => false,
+        }
+    }
+}
+
+impl Solver for BranchAndBound {
+    fn solve(&self, model: &Model) -> Result<Solution, SolveError> {
+        if model.nr_constraints() > MAXIMUM_CONSTRAINTS {
+            return Err(SolveError::TooLarge(model.nr_constraints()));
+        }
+        let deadline = self.time_limit.map(|limit| Instant::now() + limit);
+
+        let mut simplex = Simplex::new(model);
+        let mut incumbent = BranchAndBound::solve_start(model, &mut simplex, deadline);
+
+        let mut nodes = vec![Node {
+            bounds: Vec::new(),
+            estimate: f64::NEG_INFINITY,
+        }];
+        // Lowest objective of the nodes that were not explored because of the deadline
+        let mut stopped_at = None;
+        while let Some(node) = nodes.pop() {
+            if deadline.map_or(false, |deadline| Instant::now() > deadline) {
+                stopped_at = Some(node.estimate);
+                break;
+            }
+            if self.can_prune(model, node.estimate, &incumbent) {
+                continue;
+            }
+
+            for variable in 0..model.nr_variables() {
+                if model.integer[variable] {
+                    simplex.set_bounds(variable, model.lower[variable], model.upper[variable]);
+                }
+            }
+            for &(variable, lower, upper) in node.bounds.iter() {
+                simplex.set_bounds(variable, lower, upper);
+            }
+
+            match simplex.solve(deadline) {
+                LpStatus::Optimal => (),
+                LpStatus::Infeasible => continue,
+                LpStatus::Unbounded => return Err(SolveError::Unbounded),
+                LpStatus::Stopped => {
+                    stopped_at = Some(node.estimate);
+                    break;
+                }
+            }
+            let objective = simplex.objective();
+            if self.can_prune(model, objective, &incumbent) {
+                continue;
+            }
+
+            let (variable, value) = match BranchAndBound::fractional(model, simplex.values()) {
+                Some(branch) => branch,
+                None => {
+                    incumbent = Some((objective, BranchAndBound::rounded(model, simplex.values())));
+                    continue;
+                }
+            };
+            let (lower, upper) = simplex.bounds(variable);
+            let mut down = node.bounds.clone();
+            down.push((variable, lower, value.floor()));
+            let mut up = node.bounds;
+            up.push((variable, value.ceil(), upper));
+            let (down, up) = (
+                Node {
+                    bounds: down,
+                    estimate: objective,
+                },
+                Node {
+                    bounds: up,
+                    estimate: objective,
+                },
+            );
+            // The last node pushed is explored first
+            if value - value.floor() < 0.5 {
+                nodes.push(up);
+                nodes.push(down);
+            } else {
+                nodes.push(down);
+                nodes.push(up);
+            }
+        }
+
+        match incumbent {
+            Some((objective, values)) => {
+                let (status, gap) = match stopped_at {
+                    None => (Status::Optimal, 0_f64),
+                    Some(estimate) => {
+                        let bound = nodes
+                            .iter()
+                            .map(|node| node.estimate)
+                            .fold(estimate, f64::min);
+                        let gap = if objective == 0_f64 {
+                            0_f64
+                        } else {
+                            ((objective - bound) / objective.abs()).max(0_f64)
+                        };
+                        (Status::Feasible, gap)
+                    }
+                };
+                Ok(Solution {
+                    values,
+                    objective,
+                    status,
+                    gap,
+                })
+            }
+            None if stopped_at.is_some() => Err(SolveError::NoSolutionFound),
+            None => Err(SolveError::Infeasible),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::solver::branch_and_bound::BranchAndBound;
+    use algorithm::solver::Comparison;
+    use algorithm::solver::Model;
+    use algorithm::solver::SolveError;
+    use algorithm::solver::Solver;
+    use algorithm::solver::Status;
+
+    #[test]
+    fn test_knapsack() {
+        // Weights 5, 4, 3 and 2 with a capacity of 9, maximize the values 10, 7, 5 and 3
+        let mut model = Model::new();
+        let items = [
+            (5_f64, 10_f64),
+            (4_f64, 7_f64),
+            (3_f64, 5_f64),
+            (2_f64, 3_f64),
+        ]
+        .iter()
+        .map(|&(weight, value)| (model.add_binary(-value), weight))
+        .collect::<Vec<_>>();
+        model.add_constraint(items.clone(), Comparison::LessEqual, 9_f64);
+        model.set_start(items[3].0, 1_f64);
+
+        let solution = BranchAndBound::new(None).solve(&model).unwrap();
+        assert_eq!(solution.status(), Status::Optimal);
+        assert_eq!(solution.objective(), -17_f64);
+        let chosen = items
+            .iter()
+            .map(|&(item, _)| solution.is_set(item))
+            .collect::<Vec<_>>();
+        assert_eq!(chosen, vec![true, true, false, false]);
+    }
+
+    #[test]
+    fn test_integer() {
+        // min -x - y s.t. 2x + 2y <= 7, x - y = 0
+        let mut model = Model::new();
+        let x = model.add_integer(0_f64, 10_f64, -1_f64);
+        let y = model.add_integer(0_f64, 10_f64, -1_f64);
+        model.add_constraint(vec![(x, 2_f64), (y, 2_f64)], Comparison::LessEqual, 7_f64);
+        model.add_constraint(vec![(x, 1_f64), (y, -1_f64)], Comparison::Equal, 0_f64);
+
+        let solution = BranchAndBound::new(None).solve(&model).unwrap();
+        assert_eq!(solution.value(x), 1_f64);
+        assert_eq!(solution.value(y), 1_f64);
+
+        model.add_constraint(vec![(x, 1_f64)], Comparison::GreaterEqual, 2_f64);
+        assert_eq!(
+            BranchAndBound::new(None).solve(&model),
+            Err(SolveError::Infeasible)
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Mixed integer linear programs and the solvers for them.
+//!
+//! The ILP based algorithms build a `Model` in code and hand it to a `Solver`. The default solver
+//! is `BranchAndBound`, which is written in Rust and needs nothing else to be installed. The
+//! `Ampl` solver runs the same models through an AMPL installation instead. Which of the two is
+//! used, and with which limits, is configured with `SolverSettings`.
+use algorithm::solver::ampl::Ampl;
+use algorithm::solver::branch_and_bound::BranchAndBound;
+use std::f64;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub mod ampl;
+pub mod branch_and_bound;
+mod simplex;
+
+const FEASIBILITY_TOLERANCE: f64 = 1e-6;
+
+/// A variable of a model, only valid for the model that created it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Variable(usize);
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    LessEqual,
+    Equal,
+    GreaterEqual,
+}
+
+struct Constraint {
+    terms: Vec<(Variable, f64)>,
+    comparison: Comparison,
+    rhs: f64,
+}
+
+/// A mixed integer linear program that minimizes its objective.
+pub struct Model {
+    integer: Vec<bool>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    /// Objective coefficient of each variable
+    cost: Vec<f64>,
+    start: Vec<Option<f64>>,
+    constraints: Vec<Constraint>,
+}
+
+impl Model {
+    pub fn new() -> Model {
+        Model {
+            integer: Vec::new(),
+            lower: Vec::new(),
+            upper: Vec::new(),
+            cost: Vec::new(),
+            start: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+    pub fn add_binary(&mut self, cost: f64) -> Variable {
+        self.add_variable(true, 0_f64, 1_f64, cost)
+    }
+    pub fn add_integer(&mut self, lower: f64, upper: f64, cost: f64) -> Variable {
+        self.add_variable(true, lower, upper, cost)
+    }
+    pub fn add_continuous(&mut self, lower: f64, upper: f64, cost: f64) -> Variable {
+        self.add_variable(false, lower, upper, cost)
+    }
+    fn add_variable(&mut self, integer: bool, lower: f64, upper: f64, cost: f64) -> Variable {
+        debug_assert!(lower <= upper);
+
+        self.integer.push(integer);
+        self.lower.push(lower);
+        self.upper.push(upper);
+        self.cost.push(cost);
+        self.start.push(None);
+        Variable(self.cost.len() - 1)
+    }
+    pub fn add_constraint(
+        &mut self,
+        terms: Vec<(Variable, f64)>,
+        comparison: Comparison,
+        rhs: f64,
+    ) {
+        self.constraints.push(Constraint {
+            terms,
+            comparison,
+            rhs,
+        });
+    }
+    /// Suggests a value for a variable, a solver may use the values of a known solution to
+    /// start from.
+    ///
+    /// The variables without a value are chosen by the solver, the start is ignored when that
+    /// doesn't lead to a feasible solution.
+    pub fn set_start(&mut self, variable: Variable, value: f64) {
+        self.start[variable.0] = Some(value);
+    }
+    pub fn nr_variables(&self) -> usize {
+        self.cost.len()
+    }
+    pub fn nr_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+    /// Whether the values satisfy the bounds, integrality and constraints, up to a small tolerance.
+    fn is_feasible(&self, values: &[f64]) -> bool {
+        let within_bounds = values.iter().enumerate().all(|(variable, &value)| {
+            value >= self.lower[variable] - FEASIBILITY_TOLERANCE
+                && value <= self.upper[variable] + FEASIBILITY_TOLERANCE
+                && (!self.integer[variable]
+                    || (value - value.round()).abs() <= FEASIBILITY_TOLERANCE)
+        });
+        within_bounds
+            && self.constraints.iter().all(|constraint| {
+                let lhs = constraint
+                    .terms
+                    .iter()
+                    .map(|&(variable, coefficient)| coefficient * values[variable.0])
+                    .sum::<f64>();
+                match constraint.comparison {
+                    Comparison::LessEqual => lhs <= constraint.rhs + FEASIBILITY_TOLERANCE,
+                    Comparison::Equal => (lhs - constraint.rhs).abs() <= FEASIBILITY_TOLERANCE,
+                    Comparison::GreaterEqual => lhs >= constraint.rhs - FEASIBILITY_TOLERANCE,
+                }
+            })
+    }
+    /// Whether every solution has an integer objective value, such that solutions can only
+    /// improve by at least one.
+    fn has_integral_objective(&self) -> bool {
+        self.cost
+            .iter()
+            .zip(self.integer.iter())
+            .all(|(&cost, &integer)| cost == 0_f64 || (integer && cost == cost.round()))
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Status {
+    /// The solution is proven to be optimal, within the gap the solver was asked for
+    Optimal,
+    /// The solver stopped at a limit before it could prove optimality
+    Feasible,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Solution {
+    values: Vec<f64>,
+    objective: f64,
+    status: Status,
+    /// Relative difference between the objective and the best known bound on it, NaN when the
+    /// solver doesn't report the bound
+    gap: f64,
+}
+
+impl Solution {
+    pub fn value(&self, variable: Variable) -> f64 {
+        self.values[variable.0]
+    }
+    /// Whether a binary variable is set.
+    pub fn is_set(&self, variable: Variable) -> bool {
+        self.value(variable) > 0.5
+    }
+    pub fn objective(&self) -> f64 {
+        self.objective
+    }
+    pub fn status(&self) -> Status {
+        self.status
+    }
+    pub fn gap(&self) -> f64 {
+        self.gap
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolveError {
+    Infeasible,
+    Unbounded,
+    /// The solver stopped at a limit before it found any solution
+    NoSolutionFound,
+    /// The model has more constraints than the solver handles
+    TooLarge(usize),
+    /// The solver could not be run or gave output that can't be read
+    Failed(String),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::Infeasible => write!(f, "The model is infeasible"),
+            SolveError::Unbounded => write!(f, "The model is unbounded"),
+            SolveError::NoSolutionFound => write!(f, "No solution was found within the limits"),
+            SolveError::TooLarge(size) => {
+                write!(f, "The model has too many constraints: {}", size)
+            }
+            SolveError::Failed(message) => write!(f, "The solver failed: {}", message),
+        }
+    }
+}
+
+pub trait Solver: Send + Sync {
+    fn solve(&self, model: &Model) -> Result<Solution, SolveError>;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Backend {
+    /// `BranchAndBound`
+    Native,
+    /// `Ampl` with a solver, given by its name or the path to its executable
+    Ampl { solver: String },
+}
+
+/// The solver that the ILP based algorithms use, part of the `Settings`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SolverSettings {
+    pub backend: Backend,
+    /// The AMPL executable, which is looked up in the `PATH` when it's only a name
+    pub ampl_path: String,
+    /// Replaces the time limit that each algorithm has for a single model
+    pub time_limit: Option<Duration>,
+    /// Relative gap between a solution and the bound on the objective at which it is accepted
+    pub mip_gap: f64,
+    /// Number of threads an AMPL solver may use, it chooses itself when this isn't given
+    pub threads: Option<usize>,
+}
+
+impl SolverSettings {
+    /// The configured solver, with the time limit of the algorithm unless another one is set.
+    pub fn create(&self, time_limit: Duration) -> Arc<Solver> {
+        let time_limit = Some(self.time_limit.unwrap_or(time_limit));
+        match self.backend {
+            Backend::Native => {
+                Arc::new(BranchAndBound::new(time_limit).with_relative_gap(self.mip_gap))
+            }
+            Backend::Ampl { ref solver } => Arc::new(
+                Ampl::new(&self.ampl_path, solver)
+                    .with_time_limit(time_limit)
+                    .with_mip_gap(self.mip_gap)
+                    .with_threads(self.threads),
+            ),
+        }
+    }
+}
+
+impl Default for SolverSettings {
+    fn default() -> Self {
+        SolverSettings {
+            backend: Backend::Native,
+            ampl_path: "ampl".to_string(),
+            time_limit: None,
+            mip_gap: 0_f64,
+            threads: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::solver::Comparison;
+    use algorithm::solver::Model;
+
+    #[test]
+    fn test_integral_objective() {
+        let mut model = Model::new();
+        let x = model.add_binary(2_f64);
+        model.add_continuous(0_f64, 1_f64, 0_f64);
+        model.add_constraint(vec![(x, 1_f64)], Comparison::LessEqual, 1_f64);
+        assert!(model.has_integral_objective());
+        assert_eq!(model.nr_variables(), 2);
+        assert_eq!(model.nr_constraints(), 1);
+
+        model.add_continuous(0_f64, 1_f64, 1_f64);
+        assert!(!model.has_integral_objective());
+    }
+
+    #[test]
+    fn test_is_feasible() {
+        let mut model = Model::new();
+        let x = model.add_integer(0_f64, 3_f64, 1_f64);
+        let y = model.add_continuous(0_f64, 1_f64, 1_f64);
+        model.add_constraint(vec![(x, 1_f64), (y, 1_f64)], Comparison::GreaterEqual, 2.5);
+
+        assert!(model.is_feasible(&[2_f64, 0.5]));
+        assert!(!model.is_feasible(&[1.5, 1_f64]));
+        assert!(!model.is_feasible(&[1_f64, 1_f64]));
+        assert!(!model.is_feasible(&[4_f64, 0_f64]));
+    }
+}
@@ -0,0 +1,759 @@
+//! Revised simplex method for linear programs with bounded variables.
+//!
+//! The basis inverse is kept as a dense matrix, which is only suitable for the small models that
+//! the algorithms solve. Every constraint row gets a slack variable, such that the rows are
+//! equalities and all variables only have bounds. A solve starts from the basis of the previous
+//! one, which makes solving again after changing bounds, as branch and bound does, cheap: the
+//! dual simplex method restores feasibility in a few iterations.
+use algorithm::solver::Comparison;
+use algorithm::solver::Model;
+use std::f64;
+use std::time::Instant;
+
+const PRIMAL_TOLERANCE: f64 = 1e-7;
+const DUAL_TOLERANCE: f64 = 1e-7;
+const PIVOT_TOLERANCE: f64 = 1e-9;
+/// Number of basis changes after which the inverse is calculated again, to limit the rounding
+/// errors that build up
+const REFACTOR_INTERVAL: usize = 100;
+/// Number of iterations without progress after which the smallest index rule is used, which
+/// can't cycle
+const DEGENERATE_LIMIT: usize = 50;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LpStatus {
+    Optimal,
+    Infeasible,
+    Unbounded,
+    /// The deadline passed or the iteration limit was reached
+    Stopped,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Position {
+    Basic,
+    Lower,
+    Upper,
+    /// A free variable that isn't basic, its value is zero
+    Zero,
+}
+
+pub struct Simplex {
+    nr_rows: usize,
+    nr_structural: usize,
+    /// Sparse columns of the structural variables, the slack columns are unit vectors
+    columns: Vec<Vec<(usize, f64)>>,
+    rhs: Vec<f64>,
+    /// Costs, bounds, values and positions of all structural and slack variables
+    cost: Vec<f64>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    values: Vec<f64>,
+    positions: Vec<Position>,
+    /// Variable that is basic in each row
+    basis: Vec<usize>,
+    /// Row major inverse of the basis matrix
+    inverse: Vec<f64>,
+    updates: usize,
+}
+
+impl Simplex {
+    pub fn new(model: &Model) -> Simplex {
+        let nr_rows = model.constraints.len();
+        let nr_structural = model.cost.len();
+
+        let mut columns = vec![Vec::new(); nr_structural];
+        let mut rhs = Vec::with_capacity(nr_rows);
+        let mut slack_lower = Vec::with_capacity(nr_rows);
+        let mut slack_upper = Vec::with_capacity(nr_rows);
+        for (row, constraint) in model.constraints.iter().enumerate() {
+            for &(variable, coefficient) in constraint.terms.iter() {
+                if coefficient != 0_f64 {
+                    columns[variable.0].push((row, coefficient));
+                }
+            }
+            rhs.push(constraint.rhs);
+            let (lower, upper) = match constraint.comparison {
+                Comparison::LessEqual => (0_f64, f64::INFINITY),
+                Comparison::Equal => (0_f64, 0_f64),
+                Comparison::GreaterEqual => (f64::NEG_INFINITY, 0_f64),
+            };
+            slack_lower.push(lower);
+            slack_upper.push(upper);
+        }
+        // Terms of the same variable in a row are added together
+        for column in columns.iter_mut() {
+            column.sort_by_key(|&(row, _)| row);
+            column.dedup_by(
+                |&mut (row, coefficient), &mut (previous_row, ref mut sum)| {
+                    if row == previous_row {
+                        *sum += coefficient;
+                    }
+                    row == previous_row
+                },
+            );
+        }
+
+        let mut cost = model.cost.clone();
+        cost.extend((0..nr_rows).map(|_| 0_f64));
+        let mut lower = model.lower.clone();
+        lower.extend(slack_lower);
+        let mut upper = model.upper.clone();
+        upper.extend(slack_upper);
+
+        let mut positions = Vec::with_capacity(nr_structural + nr_rows);
+        let mut values = Vec::with_capacity(nr_structural + nr_rows);
+        for j in 0..nr_structural {
+            let (position, value) = Simplex::nonbasic_position(lower[j], upper[j]);
+            positions.push(position);
+            values.push(value);
+        }
+        for _ in 0..nr_rows {
+            positions.push(Position::Basic);
+            values.push(0_f64);
+        }
+
+        let mut inverse = vec![0_f64; nr_rows * nr_rows];
+        for row in 0..nr_rows {
+            inverse[row * nr_rows + row] = 1_f64;
+        }
+
+        let mut simplex = Simplex {
+            nr_rows,
+            nr_structural,
+            columns,
+            rhs,
+            cost,
+            lower,
+            upper,
+            values,
+            positions,
+            basis: (nr_structural..(nr_structural + nr_rows)).collect(),
+            inverse,
+            updates: 0,
+        };
+        simplex.compute_basic_values();
+        simplex
+    }
+    pub fn set_bounds(&mut self, variable: usize, lower: f64, upper: f64) {
+        self.lower[variable] = lower;
+        self.upper[variable] = upper;
+    }
+    pub fn bounds(&self, variable: usize) -> (f64, f64) {
+        (self.lower[variable], self.upper[variable])
+    }
+    /// Values of the structural variables.
+    pub fn values(&self) -> &[f64] {
+        &self.values[..self.nr_structural]
+    }
+    pub fn objective(&self) -> f64 {
+        (0..self.nr_structural)
+            .map(|j| self.cost[j] * self.values[j])
+            .sum()
+    }
+    /// Solves the program with the current bounds, starting from the current basis.
+    pub fn solve(&mut self, deadline: Option<Instant>) -> LpStatus {
+        if (0..self.cost.len()).any(|j| self.lower[j] > self.upper[j] + PRIMAL_TOLERANCE) {
+            return LpStatus::Infeasible;
+        }
+
+        let dual_feasible = self.place_nonbasic();
+        self.compute_basic_values();
+
+        let mut status = if dual_feasible {
+            match self.dual(deadline) {
+                Some(status) => status,
+                None => self.primal(deadline),
+            }
+        } else {
+            self.primal(deadline)
+        };
+
+        // Rounding errors might have made the solution slightly infeasible, the primal method
+        // repairs that from a freshly calculated inverse
+        for _ in 0..2 {
+            if status != LpStatus::Optimal {
+                break;
+            }
+            self.compute_basic_values();
+            if self.max_infeasibility().is_none() {
+                break;
+            }
+            self.refactor();
+            status = self.primal(deadline);
+        }
+
+        status
+    }
+    /// Moves every nonbasic variable to the bound that matches the sign of its reduced cost and
+    /// returns whether the basis is dual feasible.
+    fn place_nonbasic(&mut self) -> bool {
+        let duals = self.duals(false);
+        let mut dual_feasible = true;
+
+        for j in 0..self.cost.len() {
+            if self.positions[j] == Position::Basic {
+                continue;
+            }
+            let (lower, upper) = (self.lower[j], self.upper[j]);
+            let reduced_cost = self.cost[j] - self.dot(&duals, j);
+
+            let position = if lower == upper || (reduced_cost > DUAL_TOLERANCE && lower.is_finite())
+            {
+                Position::Lower
+            } else if reduced_cost < -DUAL_TOLERANCE && upper.is_finite() {
+                Position::Upper
+            } else {
+                if reduced_cost.abs() > DUAL_TOLERANCE {
+                    dual_feasible = false;
+                }
+                match self.positions[j] {
+                    Position::Lower if lower.is_finite() => Position::Lower,
+                    Position::Upper if upper.is_finite() => Position::Upper,
+                    _ => Simplex::nonbasic_position(lower, upper).0,
+                }
+            };
+
+            self.positions[j] = position;
+            self.values[j] = match position {
+                Position::Lower => lower,
+                Position::Upper => upper,
+                _ => 0_f64,
+            };
+        }
+
+        dual_feasible
+    }
+    fn nonbasic_position(lower: f64, upper: f64) -> (Position, f64) {
+        if lower.is_finite() {
+            (Position::Lower, lower)
+        } else if upper.is_finite() {
+            (Position::Upper, upper)
+        } else {
+            (Position::Zero, 0_f64)
+        }
+    }
+    /// The primal simplex method, which first minimizes the sum of the bound violations of the
+    /// basic variables and then the objective.
+    fn primal(&mut self, deadline: Option<Instant>) -> LpStatus {
+        let iteration_limit = 50_000 + 20 * self.cost.len();
+        let mut degenerate = 0;
+
+        for iteration in 0..iteration_limit {
+            if iteration % 32 == 0 && deadline.map_or(false, |deadline| Instant::now() > deadline) {
+                return LpStatus::Stopped;
+            }
+
+            let infeasible = self.max_infeasibility().is_some();
+            let duals = self.duals(infeasible);
+            let entering = self.price(&duals, infeasible, degenerate > DEGENERATE_LIMIT);
+            let (entering, direction) = match entering {
+                Some(entering) => entering,
+                None if infeasible => return LpStatus::Infeasible,
+                None => return LpStatus::Optimal,
+            };
+
+            let column = self.column(entering);
+            let (step, leaving) = self.primal_ratio_test(&column, direction);
+            let flip = self.upper[entering] - self.lower[entering];
+            let step = match leaving {
+                Some(_) if step < flip => step,
+                _ if flip.is_finite() => flip,
+                _ => return LpStatus::Unbounded,
+            };
+
+            self.values[entering] += direction * step;
+            for (row, &alpha) in column.iter().enumerate() {
+                self.values[self.basis[row]] -= direction * step * alpha;
+            }
+            match leaving {
+                Some((row, bound_position)) if step < flip => {
+                    let variable = self.basis[row];
+                    self.values[variable] = match bound_position {
+                        Position::Upper => self.upper[variable],
+                        _ => self.lower[variable],
+                    };
+                    self.positions[variable] = bound_position;
+                    self.pivot(row, entering, &column);
+                }
+                _ => {
+                    let (position, value) = if direction > 0_f64 {
+                        (Position::Upper, self.upper[entering])
+                    } else {
+                        (Position::Lower, self.lower[entering])
+                    };
+                    self.positions[entering] = position;
+                    self.values[entering] = value;
+                }
+            }
+
+            if step < 1e-12 {
+                degenerate += 1;
+            } else {
+                degenerate = 0;
+            }
+        }
+
+        LpStatus::Stopped
+    }
+    /// Chooses the nonbasic variable to enter the basis and the direction it moves in.
+    fn price(&self, duals: &[f64], infeasible: bool, smallest_index: bool) -> Option<(usize, f64)> {
+        let mut best: Option<(usize, f64, f64)> = None;
+
+        for j in 0..self.cost.len() {
+            let position = self.positions[j];
+            if position == Position::Basic || self.lower[j] == self.upper[j] {
+                continue;
+            }
+            let cost = if infeasible { 0_f64 } else { self.cost[j] };
+            let reduced_cost = cost - self.dot(duals, j);
+            let direction = match position {
+                Position::Lower if reduced_cost < -DUAL_TOLERANCE => 1_f64,
+                Position::Upper if reduced_cost > DUAL_TOLERANCE => -1_f64,
+                Position::Zero if reduced_cost.abs() > DUAL_TOLERANCE => -reduced_cost.signum(),
+                _ => continue,
+            };
+
+            if smallest_index {
+                return Some((j, direction));
+            }
+            if best.map_or(true, |(_, _, size)| reduced_cost.abs() > size) {
+                best = Some((j, direction, reduced_cost.abs()));
+            }
+        }
+
+        best.map(|(j, direction, _)| (j, direction))
+    }
+    /// The largest step the entering variable can make and the basic variable that limits it,
+    /// with the bound it ends up at.
+    ///
+    /// Basic variables that violate a bound may move until they reach it. The row is chosen
+    /// among those that are almost limiting by the size of the pivot (Harris' ratio test).
+    fn primal_ratio_test(
+        &self,
+        column: &[f64],
+        direction: f64,
+    ) -> (f64, Option<(usize, Position)>) {
+        let limit = |row: usize, tolerance: f64| -> Option<(f64, Position)> {
+            let alpha = column[row];
+            if alpha.abs() <= PIVOT_TOLERANCE {
+                return None;
+            }
+            let variable = self.basis[row];
+            let (value, lower, upper) = (
+                self.values[variable],
+                self.lower[variable],
+                self.upper[variable],
+            );
+            let rate = -direction * alpha;
+
+            if rate < 0_f64 {
+                if value > upper + PRIMAL_TOLERANCE {
+                    Some(((value - upper) / -rate, Position::Upper))
+                } else if value >= lower - PRIMAL_TOLERANCE && lower.is_finite() {
+                    Some((
+                        ((value - lower).max(0_f64) + tolerance) / -rate,
+                        Position::Lower,
+                    ))
+                } else {
+                    None
+                }
+            } else {
+                if value < lower - PRIMAL_TOLERANCE {
+                    Some(((lower - value) / rate, Position::Lower))
+                } else if value <= upper + PRIMAL_TOLERANCE && upper.is_finite() {
+                    Some((
+                        ((upper - value).max(0_f64) + tolerance) / rate,
+                        Position::Upper,
+                    ))
+                } else {
+                    None
+                }
+            }
+        };
+
+        let relaxed = (0..self.nr_rows)
+            .filter_map(|row| limit(row, PRIMAL_TOLERANCE))
+            .map(|(step, _)| step)
+            .fold(f64::INFINITY, f64::min);
+        if relaxed.is_infinite() {
+            return (f64::INFINITY, None);
+        }
+
+        let mut best: Option<(usize, Position, f64)> = None;
+        for row in 0..self.nr_rows {
+            if let Some((step, position)) = limit(row, 0_f64) {
+                let better = best.map_or(true, |(best_row, _, _)| {
+                    column[row].abs() > column[best_row].abs()
+                });
+                if step <= relaxed && better {
+                    best = Some((row, position, step));
+                }
+            }
+        }
+
+        match best {
+            Some((row, position, step)) => (step.max(0_f64), Some((row, position))),
+            None => (f64::INFINITY, None),
+        }
+    }
+    /// The dual simplex method, for a basis that is dual feasible.
+    ///
+    /// Returns `None` when the iteration limit is reached, in which case the primal method should
+    /// continue.
+    fn dual(&mut self, deadline: Option<Instant>) -> Option<LpStatus> {
+        let iteration_limit = 10_000 + 10 * self.cost.len();
+
+        for iteration in 0..iteration_limit {
+            if iteration % 32 == 0 && deadline.map_or(false, |deadline| Instant::now() > deadline) {
+                return Some(LpStatus::Stopped);
+            }
+
+            let row = match self.max_infeasibility() {
+                Some(row) => row,
+                None => return Some(LpStatus::Optimal),
+            };
+            let leaving = self.basis[row];
+            let value = self.values[leaving];
+            let (target, increase) = if value < self.lower[leaving] {
+                (self.lower[leaving], true)
+            } else {
+                (self.upper[leaving], false)
+            };
+
+            let duals = self.duals(false);
+            let inverse_row = self.inverse[row * self.nr_rows..(row + 1) * self.nr_rows].to_vec();
+            let mut candidates = Vec::new();
+            for j in 0..self.cost.len() {
+                let position = self.positions[j];
+                if position == Position::Basic || self.lower[j] == self.upper[j] {
+                    continue;
+                }
+                let alpha = self.dot(&inverse_row, j);
+                let eligible = match position {
+                    Position::Lower => {
+                        (increase && alpha < -PIVOT_TOLERANCE)
+                            || (!increase && alpha > PIVOT_TOLERANCE)
+                    }
+                    Position::Upper => {
+                        (increase && alpha > PIVOT_TOLERANCE)
+                            || (!increase && alpha < -PIVOT_TOLERANCE)
+                    }
+                    _ => alpha.abs() > PIVOT_TOLERANCE,
+                };
+                if eligible {
+                    let reduced_cost = self.cost[j] - self.dot(&duals, j);
+                    candidates.push((j, alpha, reduced_cost.abs()));
+                }
+            }
+
+            let relaxed = candidates
+                .iter()
+                .map(|&(_, alpha, reduced_cost)| (reduced_cost + DUAL_TOLERANCE) / alpha.abs())
+                .fold(f64::INFINITY, f64::min);
+            let entering = candidates
+                .iter()
+                .filter(|&&(_, alpha, reduced_cost)| reduced_cost / alpha.abs() <= relaxed)
+                .max_by(|&&(_, first, _), &&(_, second, _)| {
+                    first.abs().partial_cmp(&second.abs()).unwrap()
+                })
+                .map(|&(j, _, _)| j);
+            let entering = match entering {
+                Some(entering) => entering,
+                None => return Some(LpStatus::Infeasible),
+            };
+
+            let column = self.column(entering);
+            if column[row].abs() <= PIVOT_TOLERANCE {
+                return None;
+            }
+            let step = (value - target) / column[row];
+            self.values[entering] += step;
+            for (basis_row, &alpha) in column.iter().enumerate() {
+                self.values[self.basis[basis_row]] -= step * alpha;
+            }
+            self.values[leaving] = target;
+            self.positions[leaving] = if increase {
+                Position::Lower
+            } else {
+                Position::Upper
+            };
+            self.pivot(row, entering, &column);
+        }
+
+        None
+    }
+    /// Row of the basic variable that violates its bounds the most.
+    fn max_infeasibility(&self) -> Option<usize> {
+        let mut worst = None;
+        let mut worst_violation = PRIMAL_TOLERANCE;
+        for (row, &variable) in self.basis.iter().enumerate() {
+            let value = self.values[variable];
+            let violation = (self.lower[variable] - value).max(value - self.upper[variable]);
+            if violation > worst_violation {
+                worst = Some(row);
+                worst_violation = violation;
+            }
+        }
+
+        worst
+    }
+    /// Simplex multipliers for the real costs, or for the costs of minimizing bound violations.
+    fn duals(&self, infeasibility: bool) -> Vec<f64> {
+        let mut duals = vec![0_f64; self.nr_rows];
+        for (row, &variable) in self.basis.iter().enumerate() {
+            let cost = if infeasibility {
+                let value = self.values[variable];
+                if value < self.lower[variable] - PRIMAL_TOLERANCE {
+                    -1_f64
+                } else if value > self.upper[variable] + PRIMAL_TOLERANCE {
+                    1_f64
+                } else {
+                    0_f64
+                }
+            } else {
+                self.cost[variable]
+            };
+            if cost != 0_f64 {
+                let inverse_row = &self.inverse[row * self.nr_rows..(row + 1) * self.nr_rows];
+                for (dual, &entry) in duals.iter_mut().zip(inverse_row) {
+                    *dual += cost * entry;
+                }
+            }
+        }
+
+        duals
+    }
+    /// Inner product of a row vector with the column of a variable.
+    fn dot(&self, vector: &[f64], variable: usize) -> f64 {
+        if variable < self.nr_structural {
+            self.columns[variable]
+                .iter()
+                .map(|&(row, coefficient)| vector[row] * coefficient)
+                .sum()
+        } else {
+            vector[variable - self.nr_structural]
+        }
+    }
+    /// The column of a variable, expressed in the current basis.
+    fn column(&self, variable: usize) -> Vec<f64> {
+        let m = self.nr_rows;
+        let mut column = vec![0_f64; m];
+        let mut add = |row: usize, coefficient: f64| {
+            for (i, value) in column.iter_mut().enumerate() {
+                *value += self.inverse[i * m + row] * coefficient;
+            }
+        };
+        if variable < self.nr_structural {
+            for &(row, coefficient) in self.columns[variable].iter() {
+                add(row, coefficient);
+            }
+        } else {
+            add(variable - self.nr_structural, 1_f64);
+        }
+
+        column
+    }
+    fn pivot(&mut self, row: usize, entering: usize, column: &[f64]) {
+        let m = self.nr_rows;
+        let pivot = column[row];
+        for k in 0..m {
+            self.inverse[row * m + k] /= pivot;
+        }
+        for i in 0..m {
+            let factor = column[i];
+            if i == row || factor == 0_f64 {
+                continue;
+            }
+            for k in 0..m {
+                let update = factor * self.inverse[row * m + k];
+                self.inverse[i * m + k] -= update;
+            }
+        }
+
+        self.basis[row] = entering;
+        self.positions[entering] = Position::Basic;
+        self.updates += 1;
+        if self.updates >= REFACTOR_INTERVAL {
+            self.refactor();
+            self.compute_basic_values();
+        }
+    }
+    /// Calculates the basis inverse from scratch with Gauss-Jordan elimination.
+    ///
+    /// Basic variables with a column that depends on the others are replaced by slacks.
+    fn refactor(&mut self) {
+        let m = self.nr_rows;
+        loop {
+            let mut matrix = vec![0_f64; m * m];
+            for (k, &variable) in self.basis.iter().enumerate() {
+                if variable < self.nr_structural {
+                    for &(row, coefficient) in self.columns[variable].iter() {
+                        matrix[row * m + k] = coefficient;
+                    }
+                } else {
+                    matrix[(variable - self.nr_structural) * m + k] = 1_f64;
+                }
+            }
+            let mut inverse = vec![0_f64; m * m];
+            for row in 0..m {
+                inverse[row * m + row] = 1_f64;
+            }
+
+            // The rows are permuted while eliminating, `pivot_rows[k]` holds the row that
+            // belongs to the basic variable in position `k`
+            let mut pivoted = vec![false; m];
+            let mut pivot_rows = vec![None; m];
+            for k in 0..m {
+                let best = (0..m)
+                    .filter(|&row| !pivoted[row])
+                    .max_by(|&first, &second| {
+                        matrix[first * m + k]
+                            .abs()
+                            .partial_cmp(&matrix[second * m + k].abs())
+                            .unwrap()
+                    });
+                let row = match best {
+                    Some(row) if matrix[row * m + k].abs() > 1e-11 => row,
+                    _ => continue,
+                };
+                pivoted[row] = true;
+                pivot_rows[k] = Some(row);
+
+                let pivot = matrix[row * m + k];
+                for c in 0..m {
+                    matrix[row * m + c] /= pivot;
+                    inverse[row * m + c] /= pivot;
+                }
+                for other in 0..m {
+                    let factor = matrix[other * m + k];
+                    if other == row || factor == 0_f64 {
+                        continue;
+                    }
+                    for c in 0..m {
+                        matrix[other * m + c] -= factor * matrix[row * m + c];
+                        inverse[other * m + c] -= factor * inverse[row * m + c];
+                    }
+                }
+            }
+
+            let mut unpivoted = (0..m).filter(|&row| !pivoted[row]);
+            let mut singular = false;
+            for k in 0..m {
+                if pivot_rows[k].is_none() {
+                    singular = true;
+                    let variable = self.basis[k];
+                    let (position, value) = if self.values[variable] <= self.lower[variable] {
+                        Simplex::nonbasic_position(self.lower[variable], self.upper[variable])
+                    } else if self.upper[variable].is_finite() {
+                        (Position::Upper, self.upper[variable])
+                    } else {
+                        Simplex::nonbasic_position(self.lower[variable], self.upper[variable])
+                    };
+                    self.positions[variable] = position;
+                    self.values[variable] = value;
+
+                    let slack = self.nr_structural + unpivoted.next().unwrap();
+                    self.basis[k] = slack;
+                    self.positions[slack] = Position::Basic;
+                }
+            }
+            if singular {
+                continue;
+            }
+
+            // Row `pivot_rows[k]` of the eliminated inverse belongs to position `k`
+            self.inverse = vec![0_f64; m * m];
+            for k in 0..m {
+                let row = pivot_rows[k].unwrap();
+                self.inverse[k * m..(k + 1) * m].copy_from_slice(&inverse[row * m..(row + 1) * m]);
+            }
+            self.updates = 0;
+            return;
+        }
+    }
+    /// Calculates the values of the basic variables from those of the nonbasic ones.
+    fn compute_basic_values(&mut self) {
+        let m = self.nr_rows;
+        let mut remainder = self.rhs.clone();
+        for j in 0..self.cost.len() {
+            if self.positions[j] == Position::Basic || self.values[j] == 0_f64 {
+                continue;
+            }
+            let value = self.values[j];
+            if j < self.nr_structural {
+                for &(row, coefficient) in self.columns[j].iter() {
+                    remainder[row] -= coefficient * value;
+                }
+            } else {
+                remainder[j - self.nr_structural] -= value;
+            }
+        }
+
+        for row in 0..m {
+            let inverse_row = &self.inverse[row * m..(row + 1) * m];
+            self.values[self.basis[row]] = inverse_row
+                .iter()
+                .zip(remainder.iter())
+                .map(|(entry, value)| entry * value)
+                .sum();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::solver::simplex::LpStatus;
+    use algorithm::solver::simplex::Simplex;
+    use algorithm::solver::Comparison;
+    use algorithm::solver::Model;
+
+    #[test]
+    fn test_solve() {
+        // max 3x + 2y s.t. x + y <= 4, x + 3y <= 6, x <= 3
+        let mut model = Model::new();
+        let x = model.add_continuous(0_f64, 3_f64, -3_f64);
+        let y = model.add_continuous(0_f64, f64::INFINITY, -2_f64);
+        model.add_constraint(vec![(x, 1_f64), (y, 1_f64)], Comparison::LessEqual, 4_f64);
+        model.add_constraint(vec![(x, 1_f64), (y, 3_f64)], Comparison::LessEqual, 6_f64);
+
+        let mut simplex = Simplex::new(&model);
+        assert_eq!(simplex.solve(None), LpStatus::Optimal);
+        assert!((simplex.objective() + 11_f64).abs() < 1e-9);
+        assert!((simplex.values()[0] - 3_f64).abs() < 1e-9);
+        assert!((simplex.values()[1] - 1_f64).abs() < 1e-9);
+
+        // Branching on y
+        simplex.set_bounds(1, 2_f64, f64::INFINITY);
+        assert_eq!(simplex.solve(None), LpStatus::Optimal);
+        assert!((simplex.objective() + 4_f64).abs() < 1e-9);
+        simplex.set_bounds(1, 2_f64, 1_f64);
+        assert_eq!(simplex.solve(None), LpStatus::Infeasible);
+    }
+
+    #[test]
+    fn test_phase_one() {
+        // min x + y s.t. x + y >= 2, x - y = 1, y free
+        let mut model = Model::new();
+        let x = model.add_continuous(0_f64, f64::INFINITY, 1_f64);
+        let y = model.add_continuous(f64::NEG_INFINITY, f64::INFINITY, 1_f64);
+        model.add_constraint(
+            vec![(x, 1_f64), (y, 1_f64)],
+            Comparison::GreaterEqual,
+            2_f64,
+        );
+        model.add_constraint(vec![(x, 1_f64), (y, -1_f64)], Comparison::Equal, 1_f64);
+
+        let mut simplex = Simplex::new(&model);
+        assert_eq!(simplex.solve(None), LpStatus::Optimal);
+        assert!((simplex.values()[0] - 1.5).abs() < 1e-9);
+        assert!((simplex.values()[1] - 0.5).abs() < 1e-9);
+
+        // Without the lower bound on the sum, the objective is unbounded
+        let mut model = Model::new();
+        let x = model.add_continuous(f64::NEG_INFINITY, f64::INFINITY, 1_f64);
+        model.add_constraint(vec![(x, 1_f64)], Comparison::LessEqual, 2_f64);
+        assert_eq!(Simplex::new(&model).solve(None), LpStatus::Unbounded);
+    }
+}
@@ -0,0 +1,822 @@
+use algorithm::assignment::greedy_makespan::GreedyMakespan;
+use algorithm::assignment::makespan_single_vehicle_ilp::MakespanSingleVehicleILP;
+use algorithm::assignment::multiple_vehicle_ilp::MultiVehicleIlpFormulation;
+use algorithm::assignment::AssignmentAlgorithm;
+use algorithm::path::cbs::ConflictBasedSearch;
+use algorithm::path::greedy_shortest_paths::GreedyShortestPaths;
+use algorithm::path::ilp::ILPSteps;
+use algorithm::path::token_passing::TokenPassing;
+use algorithm::path::whca::WindowedCooperativeAStar;
+use algorithm::path::PathAlgorithm;
+use algorithm::solver::Backend;
+use simulation::demand::poisson::Poisson;
+use simulation::demand::uniform::Uniform;
+use simulation::demand::Demand;
+use simulation::live_view::LiveView;
+use simulation::placement::Placement;
+use simulation::plan::e_plan::EPlan;
+use simulation::plan::grid_map::GridMap;
+use simulation::plan::middle_terminals::MiddleTerminals;
+use simulation::plan::one_three_rectangle::OneThreeRectangle;
+use simulation::plan::Plan;
+use simulation::settings::Settings;
+use simulation::simulation::Simulation;
+use simulation::statistics::Statistics;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+pub mod sweep;
+
+/// A single simulation run, described by values instead of constructed objects.
+///
+/// The plan, demand and algorithms are only created when the experiment is ran, such that an
+/// experiment can be parsed from the command line and be moved between threads.
+pub struct Experiment {
+    pub plan: PlanType,
+    pub demand: DemandType,
+    pub seed: [u8; 32],
+    pub path_algorithm: PathAlgorithmType,
+    pub assignment_algorithm: AssignmentAlgorithmType,
+    pub settings: Settings,
+    /// Whether to draw the simulation in the terminal while it runs, see `LiveView`
+    pub live_view: bool,
+}
+
+impl Experiment {
+    pub fn run(&self) -> Result<Statistics, ExperimentError> {
+        let plan = self.plan.create()?;
+        let assignment_algorithm = self.assignment_algorithm.create(&*plan, &self.settings);
+        let path_algorithm =
+            self.path_algorithm
+                .create(&*plan, &self.settings, assignment_algorithm);
+        let demand = self.demand.create(self.seed);
+
+        let mut simulation = Simulation::new(path_algorithm, &*plan, demand, &self.settings);
+        if self.live_view {
+            simulation.add_observer(Box::new(LiveView::from_stdin(&*plan)));
+        }
+        simulation.initialize().map_err(ExperimentError::Output)?;
+        let history = simulation
+            .run()
+            .map_err(|error| ExperimentError::IllegalInstruction {
+                message: error.message().clone(),
+                time: error.time(),
+            })?;
+
+        Ok(history.calculate_statistics())
+    }
+}
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    /// The plan could not be created
+    Plan(String),
+    /// The output file could not be written
+    Output(io::Error),
+    /// The path algorithm gave an instruction that the simulation doesn't allow
+    IllegalInstruction { message: String, time: usize },
+}
+
+impl fmt::Display for ExperimentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExperimentError::Plan(message) => write!(f, "Can't create the plan: {}", message),
+            ExperimentError::Output(error) => write!(f, "Can't write output: {}", error),
+            ExperimentError::IllegalInstruction { message, time } => {
+                write!(f, "Illegal instruction at time {}: {}", time, message)
+            }
+        }
+    }
+}
+
+/// Expands a number into a seed, such that seeds can be given as plain numbers.
+pub fn seed_from(number: u64) -> [u8; 32] {
+    let mut seed = [0; 32];
+    for (index, byte) in seed.iter_mut().take(8).enumerate() {
+        *byte = (number >> (8 * index)) as u8;
+    }
+
+    seed
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlanType {
+    OneThreeRectangle {
+        x_size: u64,
+        y_size: u64,
+    },
+    EPlan {
+        x_size: u64,
+        y_size: u64,
+        vertical_piece_width: u64,
+        horizontal_piece_height: u64,
+    },
+    MiddleTerminals {
+        x_size: u64,
+        y_size: u64,
+        padding: u64,
+        interval: u64,
+    },
+    /// A `GridMap` read from a file
+    Map {
+        path: String,
+    },
+}
+
+impl PlanType {
+    pub fn create(&self) -> Result<Box<Plan>, ExperimentError> {
+        Ok(match *self {
+            PlanType::OneThreeRectangle { x_size, y_size } => {
+                Box::new(OneThreeRectangle::new(x_size, y_size))
+            }
+            PlanType::EPlan {
+                x_size,
+                y_size,
+                vertical_piece_width,
+                horizontal_piece_height,
+            } => Box::new(EPlan::new(
+                x_size,
+                y_size,
+                vertical_piece_width,
+                horizontal_piece_height,
+            )),
+            PlanType::MiddleTerminals {
+                x_size,
+                y_size,
+                padding,
+                interval,
+            } => Box::new(MiddleTerminals::new(x_size, y_size, padding, interval)),
+            PlanType::Map { ref path } => Box::new(
+                GridMap::read(path).map_err(|error| ExperimentError::Plan(error.to_string()))?,
+            ),
+        })
+    }
+}
+
+impl fmt::Display for PlanType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlanType::OneThreeRectangle { x_size, y_size } => {
+                write!(f, "one-three-rectangle:{},{}", x_size, y_size)
+            }
+            PlanType::EPlan {
+                x_size,
+                y_size,
+                vertical_piece_width,
+                horizontal_piece_height,
+            } => write!(
+                f,
+                "e-plan:{},{},{},{}",
+                x_size, y_size, vertical_piece_width, horizontal_piece_height
+            ),
+            PlanType::MiddleTerminals {
+                x_size,
+                y_size,
+                padding,
+                interval,
+            } => write!(
+                f,
+                "middle-terminals:{},{},{},{}",
+                x_size, y_size, padding, interval
+            ),
+            PlanType::Map { path } => write!(f, "map:{}", path),
+        }
+    }
+}
+
+/// Parses `one-three-rectangle:x,y`, `e-plan:x,y,width,height`,
+/// `middle-terminals:x,y,padding,interval` and `map:path`.
+impl FromStr for PlanType {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.starts_with("map:") && text.len() > "map:".len() {
+            return Ok(PlanType::Map {
+                path: text["map:".len()..].to_string(),
+            });
+        }
+
+        let (name, parameters) = split_parameters(text);
+        match name {
+            "one-three-rectangle" => {
+                let values = parse_parameters::<u64>(text, &parameters, 2)?;
+                if values.contains(&0) {
+                    return Err(ParseError::new(format!("Plan \"{}\" is empty", text)));
+                }
+                Ok(PlanType::OneThreeRectangle {
+                    x_size: values[0],
+                    y_size: values[1],
+                })
+            }
+            "e-plan" => {
+                let values = parse_parameters::<u64>(text, &parameters, 4)?;
+                let (y_size, horizontal_piece_height) = (values[1], values[3]);
+                if y_size < horizontal_piece_height
+                    || (y_size - horizontal_piece_height) % (horizontal_piece_height + 2) != 0
+                {
+                    return Err(ParseError::new(format!(
+                        "The height of plan \"{}\" doesn't fit a whole number of pieces",
+                        text
+                    )));
+                }
+                Ok(PlanType::EPlan {
+                    x_size: values[0],
+                    y_size,
+                    vertical_piece_width: values[2],
+                    horizontal_piece_height,
+                })
+            }
+            "middle-terminals" => {
+                let values = parse_parameters::<u64>(text, &parameters, 4)?;
+                let (x_size, y_size, padding, interval) =
+                    (values[0], values[1], values[2], values[3]);
+                if padding * 2 >= x_size || padding * 2 >= y_size || interval < 2 {
+                    return Err(ParseError::new(format!(
+                        "Plan \"{}\" needs padding smaller than half its size and an interval of \
+                         at least 2",
+                        text
+                    )));
+                }
+                Ok(PlanType::MiddleTerminals {
+                    x_size,
+                    y_size,
+                    padding,
+                    interval,
+                })
+            }
+            _ => Err(ParseError::new(format!("Unknown plan \"{}\"", text))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DemandType {
+    Uniform,
+    Poisson { rate: f64 },
+}
+
+impl DemandType {
+    pub fn create<'a>(&self, seed: [u8; 32]) -> Box<Demand + 'a> {
+        match *self {
+            DemandType::Uniform => Box::new(<Uniform as Demand>::create(seed)),
+            DemandType::Poisson { rate } => Box::new(Poisson::new(seed, rate)),
+        }
+    }
+}
+
+impl fmt::Display for DemandType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DemandType::Uniform => write!(f, "uniform"),
+            DemandType::Poisson { rate } => write!(f, "poisson:{}", rate),
+        }
+    }
+}
+
+/// Parses `uniform` and `poisson:rate`.
+impl FromStr for DemandType {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, parameters) = split_parameters(text);
+        match name {
+            "uniform" => {
+                parse_parameters::<u64>(text, &parameters, 0)?;
+                Ok(DemandType::Uniform)
+            }
+            "poisson" => {
+                let rate = parse_parameters::<f64>(text, &parameters, 1)?[0];
+                if rate.is_nan() || rate < 0_f64 {
+                    return Err(ParseError::new(format!(
+                        "Demand \"{}\" should have a rate of at least zero",
+                        text
+                    )));
+                }
+                Ok(DemandType::Poisson { rate })
+            }
+            _ => Err(ParseError::new(format!("Unknown demand \"{}\"", text))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathAlgorithmType {
+    GreedyShortestPaths,
+    GreedyShortestPathsWithBuffer,
+    ILPSteps { steps_at_once: u64 },
+    ConflictBasedSearch,
+    EnhancedConflictBasedSearch { suboptimality: f64 },
+    WindowedCooperativeAStar { window: usize, replan_interval: usize },
+    TokenPassing,
+    TokenPassingWithTaskSwaps,
+}
+
+impl PathAlgorithmType {
+    pub fn create<'p, 's, 'a>(
+        &self,
+        plan: &'p Plan,
+        settings: &'s Settings,
+        assignment_algorithm: Box<AssignmentAlgorithm<'p, 's> + 'a>,
+    ) -> Box<PathAlgorithm<'p, 's, 'a> + 'a>
+    where
+        'p: 'a,
+        's: 'a,
+    {
+        match *self {
+            PathAlgorithmType::GreedyShortestPaths => Box::new(GreedyShortestPaths::new(
+                plan,
+                settings,
+                assignment_algorithm,
+            )),
+            PathAlgorithmType::GreedyShortestPathsWithBuffer => Box::new(
+                GreedyShortestPaths::new(plan, settings, assignment_algorithm).with_buffer(),
+            ),
+            PathAlgorithmType::ILPSteps { steps_at_once } => Box::new(ILPSteps::new(
+                plan,
+                settings,
+                assignment_algorithm,
+                steps_at_once,
+            )),
+            PathAlgorithmType::ConflictBasedSearch => Box::new(ConflictBasedSearch::new(
+                plan,
+                settings,
+                assignment_algorithm,
+            )),
+            PathAlgorithmType::EnhancedConflictBasedSearch { suboptimality } => Box::new(
+                ConflictBasedSearch::new(plan, settings, assignment_algorithm)
+                    .with_suboptimality(suboptimality),
+            ),
+            PathAlgorithmType::WindowedCooperativeAStar {
+                window,
+                replan_interval,
+            } => Box::new(WindowedCooperativeAStar::new(
+                plan,
+                settings,
+                assignment_algorithm,
+                window,
+                replan_interval,
+            )),
+            // Token passing assigns the requests itself
+            PathAlgorithmType::TokenPassing => Box::new(TokenPassing::new(plan, settings)),
+            PathAlgorithmType::TokenPassingWithTaskSwaps => {
+                Box::new(TokenPassing::new(plan, settings).with_task_swaps())
+            }
+        }
+    }
+}
+
+impl fmt::Display for PathAlgorithmType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathAlgorithmType::GreedyShortestPaths => write!(f, "greedy-shortest-paths"),
+            PathAlgorithmType::GreedyShortestPathsWithBuffer => {
+                write!(f, "greedy-shortest-paths-buffer")
+            }
+            PathAlgorithmType::ILPSteps { steps_at_once } => {
+                write!(f, "ilp-steps:{}", steps_at_once)
+            }
+            PathAlgorithmType::ConflictBasedSearch => write!(f, "cbs"),
+            PathAlgorithmType::EnhancedConflictBasedSearch { suboptimality } => {
+                write!(f, "ecbs:{}", suboptimality)
+            }
+            PathAlgorithmType::WindowedCooperativeAStar {
+                window,
+                replan_interval,
+            } => write!(f, "whca:{},{}", window, replan_interval),
+            PathAlgorithmType::TokenPassing => write!(f, "tp"),
+            PathAlgorithmType::TokenPassingWithTaskSwaps => write!(f, "tpts"),
+        }
+    }
+}
+
+/// Parses `greedy-shortest-paths`, `greedy-shortest-paths-buffer`, `ilp-steps:steps_at_once`,
+/// `cbs`, `ecbs:suboptimality`, `whca:window,replan_interval`, `tp` and `tpts`.
+impl FromStr for PathAlgorithmType {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, parameters) = split_parameters(text);
+        match name {
+            "greedy-shortest-paths" => {
+                parse_parameters::<u64>(text, &parameters, 0)?;
+                Ok(PathAlgorithmType::GreedyShortestPaths)
+            }
+            "greedy-shortest-paths-buffer" => {
+                parse_parameters::<u64>(text, &parameters, 0)?;
+                Ok(PathAlgorithmType::GreedyShortestPathsWithBuffer)
+            }
+            "ilp-steps" => {
+                let steps_at_once = parse_parameters::<u64>(text, &parameters, 1)?[0];
+                if steps_at_once == 0 {
+                    return Err(ParseError::new(format!(
+                        "Path algorithm \"{}\" should take at least one step at once",
+                        text
+                    )));
+                }
+                Ok(PathAlgorithmType::ILPSteps { steps_at_once })
+            }
+            "cbs" => {
+                parse_parameters::<u64>(text, &parameters, 0)?;
+                Ok(PathAlgorithmType::ConflictBasedSearch)
+            }
+            "ecbs" => {
+                let suboptimality = parse_parameters::<f64>(text, &parameters, 1)?[0];
+                if !(suboptimality >= 1_f64) || suboptimality.is_infinite() {
+                    return Err(ParseError::new(format!(
+                        "Path algorithm \"{}\" should have a suboptimality factor of at least one",
+                        text
+                    )));
+                }
+                Ok(PathAlgorithmType::EnhancedConflictBasedSearch { suboptimality })
+            }
+            "whca" => {
+                let values = parse_parameters::<usize>(text, &parameters, 2)?;
+                let (window, replan_interval) = (values[0], values[1]);
+                if replan_interval == 0 || replan_interval > window {
+                    return Err(ParseError::new(format!(
+                        "Path algorithm \"{}\" should plan again at least once per window",
+                        text
+                    )));
+                }
+                Ok(PathAlgorithmType::WindowedCooperativeAStar {
+                    window,
+                    replan_interval,
+                })
+            }
+            "tp" => {
+                parse_parameters::<u64>(text, &parameters, 0)?;
+                Ok(PathAlgorithmType::TokenPassing)
+            }
+            "tpts" => {
+                parse_parameters::<u64>(text, &parameters, 0)?;
+                Ok(PathAlgorithmType::TokenPassingWithTaskSwaps)
+            }
+            _ => Err(ParseError::new(format!(
+                "Unknown path algorithm \"{}\"",
+                text
+            ))),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum AssignmentAlgorithmType {
+    GreedyMakespan,
+    MultiVehicleIlp,
+    MakespanSingleVehicleIlp,
+}
+
+impl AssignmentAlgorithmType {
+    pub fn create<'p, 's, 'a>(
+        &self,
+        plan: &'p Plan,
+        settings: &'s Settings,
+    ) -> Box<AssignmentAlgorithm<'p, 's> + 'a>
+    where
+        'p: 'a,
+        's: 'a,
+    {
+        match self {
+            AssignmentAlgorithmType::GreedyMakespan => {
+                Box::new(GreedyMakespan::new(plan, settings))
+            }
+            AssignmentAlgorithmType::MultiVehicleIlp => {
+                Box::new(MultiVehicleIlpFormulation::new(plan, settings))
+            }
+            AssignmentAlgorithmType::MakespanSingleVehicleIlp => {
+                Box::new(MakespanSingleVehicleILP::new(plan, settings))
+            }
+        }
+    }
+}
+
+impl fmt::Display for AssignmentAlgorithmType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            AssignmentAlgorithmType::GreedyMakespan => "greedy-makespan",
+            AssignmentAlgorithmType::MultiVehicleIlp => "multi-vehicle-ilp",
+            AssignmentAlgorithmType::MakespanSingleVehicleIlp => "makespan-single-vehicle-ilp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parses `greedy-makespan`, `multi-vehicle-ilp` and `makespan-single-vehicle-ilp`.
+impl FromStr for AssignmentAlgorithmType {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "greedy-makespan" => Ok(AssignmentAlgorithmType::GreedyMakespan),
+            "multi-vehicle-ilp" => Ok(AssignmentAlgorithmType::MultiVehicleIlp),
+            "makespan-single-vehicle-ilp" => Ok(AssignmentAlgorithmType::MakespanSingleVehicleIlp),
+            _ => Err(ParseError::new(format!(
+                "Unknown assignment algorithm \"{}\"",
+                text
+            ))),
+        }
+    }
+}
+
+/// How robots are placed, the random placement depends on the seed of the experiment.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PlacementType {
+    Random,
+    Sources,
+}
+
+impl PlacementType {
+    pub fn create(&self, seed: [u8; 32]) -> Placement {
+        match self {
+            PlacementType::Random => Placement::Random(seed),
+            PlacementType::Sources => Placement::Sources,
+        }
+    }
+}
+
+impl fmt::Display for PlacementType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlacementType::Random => write!(f, "random"),
+            PlacementType::Sources => write!(f, "sources"),
+        }
+    }
+}
+
+/// Parses `random` and `sources`.
+impl FromStr for PlacementType {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "random" => Ok(PlacementType::Random),
+            "sources" => Ok(PlacementType::Sources),
+            _ => Err(ParseError::new(format!("Unknown placement \"{}\"", text))),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Native => write!(f, "native"),
+            Backend::Ampl { solver } => write!(f, "ampl:{}", solver),
+        }
+    }
+}
+
+/// Parses `native` and `ampl:solver`, in which the solver is a name or a path.
+impl FromStr for Backend {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text == "native" {
+            Ok(Backend::Native)
+        } else if text.starts_with("ampl:") && text.len() > "ampl:".len() {
+            Ok(Backend::Ampl {
+                solver: text["ampl:".len()..].to_string(),
+            })
+        } else {
+            Err(ParseError::new(format!("Unknown solver \"{}\"", text)))
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ParseError {
+    message: String,
+}
+
+impl ParseError {
+    pub fn new(message: String) -> ParseError {
+        ParseError { message }
+    }
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Splits `name:first,second` into the name and its parameters.
+fn split_parameters(text: &str) -> (&str, Vec<&str>) {
+    let mut parts = text.splitn(2, ':');
+    let name = parts.next().unwrap();
+    let parameters = match parts.next() {
+        Some(parameters) => parameters.split(',').map(str::trim).collect(),
+        None => Vec::with_capacity(0),
+    };
+
+    (name, parameters)
+}
+
+fn parse_parameters<T: FromStr>(
+    text: &str,
+    parameters: &Vec<&str>,
+    expected: usize,
+) -> Result<Vec<T>, ParseError> {
+    if parameters.len() != expected {
+        return Err(ParseError::new(format!(
+            "\"{}\" should have {} parameter(s), not {}",
+            text,
+            expected,
+            parameters.len()
+        )));
+    }
+
+    parameters
+        .iter()
+        .map(|parameter| {
+            parameter.parse::<T>().map_err(|_| {
+                ParseError::new(format!(
+                    "Invalid parameter \"{}\" in \"{}\"",
+                    parameter, text
+                ))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::solver::Backend;
+    use algorithm::solver::SolverSettings;
+    use experiment::seed_from;
+    use experiment::AssignmentAlgorithmType;
+    use experiment::DemandType;
+    use experiment::Experiment;
+    use experiment::PathAlgorithmType;
+    use experiment::PlanType;
+    use simulation::placement::Placement;
+    use simulation::settings::Settings;
+
+    #[test]
+    fn test_parse_plan() {
+        assert_eq!(
+            "one-three-rectangle:30,20".parse(),
+            Ok(PlanType::OneThreeRectangle {
+                x_size: 30,
+                y_size: 20,
+            })
+        );
+        assert_eq!(
+            "e-plan:62,63,20,3".parse(),
+            Ok(PlanType::EPlan {
+                x_size: 62,
+                y_size: 63,
+                vertical_piece_width: 20,
+                horizontal_piece_height: 3,
+            })
+        );
+        assert_eq!(
+            "middle-terminals:10,10,3,3".parse(),
+            Ok(PlanType::MiddleTerminals {
+                x_size: 10,
+                y_size: 10,
+                padding: 3,
+                interval: 3,
+            })
+        );
+
+        assert!("one-three-rectangle:30".parse::<PlanType>().is_err());
+        assert!("one-three-rectangle:30,a".parse::<PlanType>().is_err());
+        assert!("e-plan:62,62,20,3".parse::<PlanType>().is_err());
+        assert!("rectangle:3,3".parse::<PlanType>().is_err());
+    }
+
+    #[test]
+    fn test_parse_algorithms() {
+        assert_eq!("uniform".parse(), Ok(DemandType::Uniform));
+        assert_eq!("poisson:0.5".parse(), Ok(DemandType::Poisson { rate: 0.5 }));
+        assert!("poisson".parse::<DemandType>().is_err());
+
+        assert_eq!(
+            "greedy-shortest-paths".parse(),
+            Ok(PathAlgorithmType::GreedyShortestPaths)
+        );
+        assert_eq!(
+            "greedy-shortest-paths-buffer".parse(),
+            Ok(PathAlgorithmType::GreedyShortestPathsWithBuffer)
+        );
+        assert_eq!(
+            "ilp-steps:2".parse(),
+            Ok(PathAlgorithmType::ILPSteps { steps_at_once: 2 })
+        );
+        assert!("ilp-steps:0".parse::<PathAlgorithmType>().is_err());
+        assert_eq!("cbs".parse(), Ok(PathAlgorithmType::ConflictBasedSearch));
+        assert!("cbs:2".parse::<PathAlgorithmType>().is_err());
+        assert_eq!(
+            "ecbs:1.5".parse(),
+            Ok(PathAlgorithmType::EnhancedConflictBasedSearch { suboptimality: 1.5 })
+        );
+        assert!("ecbs:0.9".parse::<PathAlgorithmType>().is_err());
+        assert!("ecbs:NaN".parse::<PathAlgorithmType>().is_err());
+        assert_eq!(
+            "whca:8,4".parse(),
+            Ok(PathAlgorithmType::WindowedCooperativeAStar {
+                window: 8,
+                replan_interval: 4,
+            })
+        );
+        assert!("whca:4,8".parse::<PathAlgorithmType>().is_err());
+        assert!("whca:4,0".parse::<PathAlgorithmType>().is_err());
+        assert_eq!("tp".parse(), Ok(PathAlgorithmType::TokenPassing));
+        assert_eq!(
+            "tpts".parse(),
+            Ok(PathAlgorithmType::TokenPassingWithTaskSwaps)
+        );
+        assert!("tp:1".parse::<PathAlgorithmType>().is_err());
+
+        assert_eq!(
+            "multi-vehicle-ilp".parse(),
+            Ok(AssignmentAlgorithmType::MultiVehicleIlp)
+        );
+        assert!("greedy".parse::<AssignmentAlgorithmType>().is_err());
+
+        assert_eq!("native".parse(), Ok(Backend::Native));
+        assert_eq!(
+            "ampl:/opt/ampl/gurobi".parse(),
+            Ok(Backend::Ampl {
+                solver: "/opt/ampl/gurobi".to_string()
+            })
+        );
+        assert!("ampl".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for text in &[
+            "one-three-rectangle:30,20",
+            "e-plan:62,63,20,3",
+            "middle-terminals:10,10,3,3",
+            "map:/tmp/warehouse,1.map",
+        ] {
+            assert_eq!(text.parse::<PlanType>().unwrap().to_string(), *text);
+        }
+        for text in &["uniform", "poisson:0.5"] {
+            assert_eq!(text.parse::<DemandType>().unwrap().to_string(), *text);
+        }
+        for text in &[
+            "greedy-shortest-paths",
+            "greedy-shortest-paths-buffer",
+            "ilp-steps:2",
+            "cbs",
+            "ecbs:1.5",
+            "whca:8,4",
+            "tp",
+            "tpts",
+        ] {
+            assert_eq!(
+                text.parse::<PathAlgorithmType>().unwrap().to_string(),
+                *text
+            );
+        }
+        for text in &["greedy-makespan", "makespan-single-vehicle-ilp"] {
+            assert_eq!(
+                text.parse::<AssignmentAlgorithmType>().unwrap().to_string(),
+                *text
+            );
+        }
+    }
+
+    #[test]
+    fn test_seed_from() {
+        assert_eq!(seed_from(0), [0; 32]);
+        assert_ne!(seed_from(1), seed_from(256));
+        assert_eq!(seed_from(258)[..2], [2, 1]);
+    }
+
+    #[test]
+    fn test_run() {
+        let experiment = Experiment {
+            plan: PlanType::OneThreeRectangle {
+                x_size: 5,
+                y_size: 5,
+            },
+            demand: DemandType::Uniform,
+            seed: seed_from(3),
+            path_algorithm: PathAlgorithmType::GreedyShortestPaths,
+            assignment_algorithm: AssignmentAlgorithmType::GreedyMakespan,
+            settings: Settings {
+                total_time: 100,
+                nr_robots: 2,
+                nr_requests: 3,
+                output_file: None,
+                forbid_following: false,
+                placement: Placement::Random(seed_from(3)),
+                solver: SolverSettings::default(),
+            },
+            live_view: false,
+        };
+
+        let statistics = experiment.run().ok().unwrap();
+        assert_eq!(statistics.requests.len(), 3);
+        assert!(statistics.duration < 100);
+    }
+}
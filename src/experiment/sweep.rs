@@ -0,0 +1,262 @@
+use algorithm::solver::SolverSettings;
+use experiment::seed_from;
+use experiment::AssignmentAlgorithmType;
+use experiment::DemandType;
+use experiment::Experiment;
+use experiment::PathAlgorithmType;
+use experiment::PlacementType;
+use experiment::PlanType;
+use simulation::settings::Settings;
+use simulation::statistics::Statistics;
+use std::any::Any;
+use std::io;
+use std::io::Write;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::spawn;
+
+const HEADER: &str = "run,plan,demand,seed,robots,requests,path_algorithm,assignment_algorithm,\
+                      status,makespan,duration,delivered,mean_waiting_time,mean_delivery_time,\
+                      mean_latency,total_distance,mean_utilisation,mean_calculation_time,\
+                      p95_calculation_time,max_calculation_time,error";
+
+/// A grid of parameters, every combination of which is ran as a separate experiment.
+pub struct Sweep {
+    pub plans: Vec<PlanType>,
+    pub demands: Vec<DemandType>,
+    pub nr_robots: Vec<usize>,
+    pub nr_requests: Vec<u64>,
+    pub seeds: Vec<u64>,
+    /// Path algorithm and the assignment algorithm it uses
+    pub algorithms: Vec<(PathAlgorithmType, AssignmentAlgorithmType)>,
+    pub placement: PlacementType,
+    pub total_time: usize,
+    pub forbid_following: bool,
+    pub solver: SolverSettings,
+}
+
+impl Sweep {
+    /// All combinations of the parameters, numbered in the order in which they are started.
+    pub fn experiments(&self) -> Vec<(usize, u64, Experiment)> {
+        let mut experiments = Vec::new();
+
+        for plan in &self.plans {
+            for &demand in &self.demands {
+                for &(path_algorithm, assignment_algorithm) in &self.algorithms {
+                    for &nr_robots in &self.nr_robots {
+                        for &nr_requests in &self.nr_requests {
+                            for &seed_number in &self.seeds {
+                                let seed = seed_from(seed_number);
+                                let experiment = Experiment {
+                                    plan: plan.clone(),
+                                    demand,
+                                    seed,
+                                    path_algorithm,
+                                    assignment_algorithm,
+                                    settings: Settings {
+                                        total_time: self.total_time,
+                                        nr_robots,
+                                        nr_requests,
+                                        output_file: None,
+                                        forbid_following: self.forbid_following,
+                                        placement: self.placement.create(seed),
+                                        solver: self.solver.clone(),
+                                    },
+                                    live_view: false,
+                                };
+                                experiments.push((experiments.len(), seed_number, experiment));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        experiments
+    }
+    /// Runs all experiments on `nr_threads` threads and writes a CSV row for each of them.
+    ///
+    /// Rows are written as soon as a run finishes, so they are not necessarily in order of the
+    /// `run` column. A run that fails or panics gets a row with status `failed` and the reason.
+    pub fn run(&self, nr_threads: usize, writer: &mut impl Write) -> io::Result<()> {
+        debug_assert!(nr_threads > 0);
+
+        writeln!(writer, "{}", HEADER)?;
+
+        let queue = Arc::new(Mutex::new(self.experiments().into_iter()));
+        let (sender, receiver) = channel();
+        let workers = (0..nr_threads)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                let sender = sender.clone();
+                spawn(move || loop {
+                    let next = queue.lock().unwrap().next();
+                    match next {
+                        Some((run, seed, experiment)) => {
+                            let result = catch_unwind(AssertUnwindSafe(|| experiment.run()))
+                                .map_err(panic_message)
+                                .and_then(|result| result.map_err(|error| error.to_string()));
+                            if sender.send(row(run, seed, &experiment, result)).is_err() {
+                                break;
+                            }
+                        }
+                        None => break,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        drop(sender);
+
+        for row in receiver {
+            writeln!(writer, "{}", row)?;
+        }
+        writer.flush()?;
+
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        Ok(())
+    }
+}
+
+fn panic_message(panic: Box<Any + Send>) -> String {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match panic.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "Panicked".to_string(),
+        },
+    }
+}
+
+fn row(
+    run: usize,
+    seed: u64,
+    experiment: &Experiment,
+    result: Result<Statistics, String>,
+) -> String {
+    fn optional<T: ToString>(value: Option<T>) -> String {
+        value.map_or(String::new(), |value| value.to_string())
+    }
+
+    let parameters = format!(
+        "{},{},{},{},{},{},{},{}",
+        run,
+        quote(&experiment.plan.to_string()),
+        quote(&experiment.demand.to_string()),
+        seed,
+        experiment.settings.nr_robots,
+        experiment.settings.nr_requests,
+        quote(&experiment.path_algorithm.to_string()),
+        quote(&experiment.assignment_algorithm.to_string()),
+    );
+
+    match result {
+        Ok(statistics) => format!(
+            "{},ok,{},{},{},{},{},{},{},{},{},{},{},",
+            parameters,
+            optional(statistics.makespan),
+            statistics.duration,
+            statistics.nr_delivered(),
+            optional(statistics.mean_waiting_time()),
+            optional(statistics.mean_delivery_time()),
+            optional(statistics.mean_latency()),
+            statistics.total_distance(),
+            optional(statistics.mean_utilisation()),
+            optional(statistics.calculation_times.map(|times| times.mean)),
+            optional(statistics.calculation_times.map(|times| times.p95)),
+            optional(statistics.calculation_times.map(|times| times.max)),
+        ),
+        Err(message) => format!("{},failed,,,,,,,,,,,,{}", parameters, quote(&message)),
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or newline.
+fn quote(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::solver::SolverSettings;
+    use experiment::sweep::quote;
+    use experiment::sweep::Sweep;
+    use experiment::AssignmentAlgorithmType;
+    use experiment::DemandType;
+    use experiment::PathAlgorithmType;
+    use experiment::PlacementType;
+    use experiment::PlanType;
+
+    fn sweep() -> Sweep {
+        Sweep {
+            plans: vec![PlanType::OneThreeRectangle {
+                x_size: 5,
+                y_size: 5,
+            }],
+            demands: vec![DemandType::Uniform],
+            nr_robots: vec![1, 2],
+            nr_requests: vec![2],
+            seeds: vec![0, 1, 2],
+            algorithms: vec![(
+                PathAlgorithmType::GreedyShortestPaths,
+                AssignmentAlgorithmType::GreedyMakespan,
+            )],
+            placement: PlacementType::Random,
+            total_time: 50,
+            forbid_following: false,
+            solver: SolverSettings::default(),
+        }
+    }
+
+    #[test]
+    fn test_experiments() {
+        let experiments = sweep().experiments();
+
+        assert_eq!(experiments.len(), 6);
+        assert!(experiments
+            .iter()
+            .enumerate()
+            .all(|(index, &(run, _, _))| index == run));
+        assert_eq!(experiments[4].1, 1);
+        assert_eq!(experiments[4].2.settings.nr_robots, 2);
+        assert_ne!(experiments[0].2.seed, experiments[1].2.seed);
+    }
+
+    #[test]
+    fn test_run() {
+        let mut output = Vec::new();
+        sweep().run(3, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("run,plan,"));
+        let header_columns = lines[0].split(',').count();
+        for line in &lines[1..] {
+            assert!(line.contains("\"one-three-rectangle:5,5\""));
+            assert_eq!(line.split(',').count(), header_columns + 1);
+        }
+        let mut runs = lines[1..]
+            .iter()
+            .map(|line| line.split(',').next().unwrap().parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        runs.sort();
+        assert_eq!(runs, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("uniform"), "uniform");
+        assert_eq!(quote("e-plan:1,2"), "\"e-plan:1,2\"");
+        assert_eq!(quote("a \"b\""), "\"a \"\"b\"\"\"");
+    }
+}
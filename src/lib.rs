@@ -6,11 +6,16 @@ extern crate fnv;
 extern crate itertools;
 extern crate priority_queue;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
 #[macro_use]
 pub mod macros;
 
 pub mod algorithm;
+pub mod experiment;
 pub mod simulation;
 
 #[cfg(test)]
@@ -0,0 +1,448 @@
+extern crate disjoint_path_routing;
+extern crate fnv;
+extern crate itertools;
+
+use disjoint_path_routing::algorithm::solver::Backend;
+use disjoint_path_routing::algorithm::solver::SolverSettings;
+use disjoint_path_routing::experiment::seed_from;
+use disjoint_path_routing::experiment::sweep::Sweep;
+use disjoint_path_routing::experiment::AssignmentAlgorithmType;
+use disjoint_path_routing::experiment::DemandType;
+use disjoint_path_routing::experiment::Experiment;
+use disjoint_path_routing::experiment::ParseError;
+use disjoint_path_routing::experiment::PathAlgorithmType;
+use disjoint_path_routing::experiment::PlacementType;
+use disjoint_path_routing::experiment::PlanType;
+use disjoint_path_routing::simulation::settings::Settings;
+use disjoint_path_routing::simulation::svg::Renderer;
+use disjoint_path_routing::simulation::trace;
+use fnv::FnvHashMap;
+use itertools::Itertools;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::path::Path;
+use std::process::exit;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "Runs a single simulation and prints its statistics.
+
+Usage: disjoint-path-routing [OPTIONS]
+       disjoint-path-routing --sweep [OPTIONS]
+       disjoint-path-routing --replay TRACE (--svg PATH | --frames DIRECTORY)
+
+With --sweep, the options marked with * can be given more than once. Every combination of their
+values is simulated and a CSV row with the statistics of each run is printed.
+
+With --replay, a trace written with --output-file is drawn instead of running a simulation.
+
+Options:
+  * --plan PLAN                     one-three-rectangle:X,Y (default: one-three-rectangle:10,10)
+                                    e-plan:X,Y,VERTICAL_PIECE_WIDTH,HORIZONTAL_PIECE_HEIGHT
+                                    middle-terminals:X,Y,PADDING,INTERVAL
+                                    map:PATH, a MovingAI grid map with S sources and T terminals
+  * --demand DEMAND                 uniform (default) or poisson:RATE
+  * --seed NUMBER                   Seed for the demand and random placement (default: 0)
+    --seeds COUNT                   With --sweep, use the seeds 0 up to COUNT instead of --seed
+    --total-time STEPS              Maximum number of time steps
+  * --robots NUMBER                 Number of robots
+  * --requests NUMBER               Number of requests at the start
+    --placement PLACEMENT           random (default) or sources
+    --forbid-following              Don't let robots move onto a vertex that is being left
+    --live                          Draw the simulation in the terminal while it runs, type p, s, +,
+                                    - or q followed by enter to pause, step, speed up, slow down
+                                    or stop drawing
+    --output-file PATH              Write a JSON Lines trace of the simulation to an existing
+                                    file, not with --sweep
+  * --path-algorithm ALGORITHM      greedy-shortest-paths (default),
+                                    greedy-shortest-paths-buffer, which keeps robots a step apart
+                                    even if they may follow each other, ilp-steps:STEPS_AT_ONCE,
+                                    cbs, ecbs:SUBOPTIMALITY, with a suboptimality factor of at
+                                    least one, whca:WINDOW,REPLAN_INTERVAL, planning again at
+                                    least once per window, or tp or tpts, which assign the
+                                    requests themselves
+  * --assignment-algorithm ALGORITHM
+                                    greedy-makespan (default), multi-vehicle-ilp or
+                                    makespan-single-vehicle-ilp
+    --solver SOLVER                 Solver for the ILP based algorithms, native (default) or
+                                    ampl:SOLVER with the name of a solver such as gurobi or cplex,
+                                    or the path to its executable
+    --ampl-path PATH                AMPL executable (default: ampl, looked up in the PATH)
+    --solver-time-limit SECONDS     Time limit for each integer program, replaces the limit of
+                                    the algorithm
+    --mip-gap GAP                   Relative gap at which a solution is good enough (default: 0)
+    --solver-threads NUMBER         Number of threads the AMPL solver may use
+    --sweep                         Run every combination of the given values
+    --threads NUMBER                With --sweep, the number of simulations to run in parallel
+                                    (default: number of available cores)
+    --replay TRACE                  Draw the simulation in a trace
+    --svg PATH                      With --replay, write an animated SVG image of all steps
+    --frames DIRECTORY              With --replay, write an SVG image of every step
+    --help                          Print this message
+";
+
+/// Options that take a value.
+const OPTIONS: [&str; 20] = [
+    "plan",
+    "demand",
+    "seed",
+    "seeds",
+    "total-time",
+    "robots",
+    "requests",
+    "placement",
+    "output-file",
+    "path-algorithm",
+    "assignment-algorithm",
+    "solver",
+    "ampl-path",
+    "solver-time-limit",
+    "mip-gap",
+    "solver-threads",
+    "threads",
+    "replay",
+    "svg",
+    "frames",
+];
+/// Options that don't take a value.
+const FLAGS: [&str; 4] = ["forbid-following", "live", "sweep", "help"];
+
+fn main() {
+    let arguments = match Arguments::parse(env::args().skip(1)) {
+        Ok(arguments) => arguments,
+        Err(error) => fail(error),
+    };
+    if arguments.has_flag("help") {
+        print!("{}", USAGE);
+        return;
+    }
+
+    if arguments.is_given("replay") {
+        let (trace_path, svg, frames) = match replay_paths(&arguments) {
+            Ok(paths) => paths,
+            Err(error) => fail(error),
+        };
+        if let Err(message) = replay(trace_path, svg, frames) {
+            eprintln!("{}", message);
+            exit(1);
+        }
+    } else if arguments.is_given("svg") || arguments.is_given("frames") {
+        fail(ParseError::new(
+            "Options \"--svg\" and \"--frames\" can only be used with \"--replay\"".to_string(),
+        ));
+    } else if arguments.has_flag("sweep") {
+        let (sweep, nr_threads) = match create_sweep(&arguments) {
+            Ok(sweep) => sweep,
+            Err(error) => fail(error),
+        };
+        let stdout = io::stdout();
+        if let Err(error) = sweep.run(nr_threads, &mut stdout.lock()) {
+            eprintln!("Can't write results: {}", error);
+            exit(1);
+        }
+    } else {
+        let experiment = match create_experiment(&arguments) {
+            Ok(experiment) => experiment,
+            Err(error) => fail(error),
+        };
+        match experiment.run() {
+            Ok(statistics) => println!("{}", statistics),
+            Err(error) => {
+                eprintln!("{}", error);
+                exit(1);
+            }
+        }
+    }
+}
+
+fn fail(error: ParseError) -> ! {
+    eprintln!("{}\n\n{}", error, USAGE);
+    exit(2);
+}
+
+const DEFAULT_PLAN: PlanType = PlanType::OneThreeRectangle {
+    x_size: 10,
+    y_size: 10,
+};
+const DEFAULT_DEMAND: DemandType = DemandType::Uniform;
+const DEFAULT_PATH_ALGORITHM: PathAlgorithmType = PathAlgorithmType::GreedyShortestPaths;
+const DEFAULT_ASSIGNMENT_ALGORITHM: AssignmentAlgorithmType =
+    AssignmentAlgorithmType::GreedyMakespan;
+
+fn create_experiment(arguments: &Arguments) -> Result<Experiment, ParseError> {
+    for option in &["seeds", "threads"] {
+        if arguments.is_given(option) {
+            return Err(ParseError::new(format!(
+                "Option \"--{}\" can only be used with \"--sweep\"",
+                option
+            )));
+        }
+    }
+
+    let defaults = Settings::default();
+    let seed = seed_from(arguments.value("seed", 0)?);
+
+    Ok(Experiment {
+        plan: arguments.value("plan", DEFAULT_PLAN)?,
+        demand: arguments.value("demand", DEFAULT_DEMAND)?,
+        seed,
+        path_algorithm: arguments.value("path-algorithm", DEFAULT_PATH_ALGORITHM)?,
+        assignment_algorithm: arguments
+            .value("assignment-algorithm", DEFAULT_ASSIGNMENT_ALGORITHM)?,
+        settings: Settings {
+            total_time: arguments.value("total-time", defaults.total_time)?,
+            nr_robots: arguments.value("robots", defaults.nr_robots)?,
+            nr_requests: arguments.value("requests", defaults.nr_requests)?,
+            output_file: arguments.text("output-file")?.map(str::to_string),
+            forbid_following: arguments.has_flag("forbid-following"),
+            placement: arguments
+                .value("placement", PlacementType::Random)?
+                .create(seed),
+            solver: solver_settings(arguments)?,
+        },
+        live_view: arguments.has_flag("live"),
+    })
+}
+
+/// Seconds that a single step is shown in an animated replay.
+const STEP_DURATION: f64 = 0.25;
+
+/// The trace to replay, and the paths to write the animation and the frames to.
+fn replay_paths(arguments: &Arguments) -> Result<(&str, Option<&str>, Option<&str>), ParseError> {
+    let trace_path = arguments.text("replay")?.unwrap();
+    let svg = arguments.text("svg")?;
+    let frames = arguments.text("frames")?;
+    if svg.is_none() && frames.is_none() {
+        return Err(ParseError::new(
+            "Option \"--replay\" needs \"--svg\" or \"--frames\"".to_string(),
+        ));
+    }
+    let other_option = arguments
+        .values
+        .keys()
+        .chain(arguments.flags.iter())
+        .find(|option| !["replay", "svg", "frames"].contains(&option.as_str()));
+    if let Some(option) = other_option {
+        return Err(ParseError::new(format!(
+            "Option \"--{}\" can't be used with \"--replay\"",
+            option
+        )));
+    }
+
+    Ok((trace_path, svg, frames))
+}
+
+fn replay(trace_path: &str, svg: Option<&str>, frames: Option<&str>) -> Result<(), String> {
+    let file =
+        File::open(trace_path).map_err(|error| format!("Can't open the trace: {}", error))?;
+    let trace = trace::read(BufReader::new(file)).map_err(|error| error.to_string())?;
+    let renderer = Renderer::new(&trace.plan);
+
+    if let Some(path) = svg {
+        fs::write(path, renderer.animation(&trace.history, STEP_DURATION))
+            .map_err(|error| format!("Can't write the image: {}", error))?;
+    }
+    if let Some(directory) = frames {
+        let frames = renderer.frames(&trace.history);
+        let width = (frames.len() - 1).to_string().len();
+        for (time, frame) in frames.into_iter().enumerate() {
+            let path = Path::new(directory).join(format!("{:0width$}.svg", time, width = width));
+            fs::write(path, frame).map_err(|error| format!("Can't write a frame: {}", error))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The sweep described by the arguments, and the number of threads to run it on.
+fn create_sweep(arguments: &Arguments) -> Result<(Sweep, usize), ParseError> {
+    if arguments.is_given("output-file") {
+        return Err(ParseError::new(
+            "Option \"--output-file\" can't be used with \"--sweep\"".to_string(),
+        ));
+    }
+    if arguments.has_flag("live") {
+        return Err(ParseError::new(
+            "Option \"--live\" can't be used with \"--sweep\"".to_string(),
+        ));
+    }
+
+    let defaults = Settings::default();
+    let seeds = if arguments.is_given("seeds") {
+        if arguments.is_given("seed") {
+            return Err(ParseError::new(
+                "Options \"--seed\" and \"--seeds\" can't be used together".to_string(),
+            ));
+        }
+        (0..arguments.value("seeds", 0)?).collect()
+    } else {
+        arguments.values("seed", 0)?
+    };
+    let path_algorithms = arguments.values("path-algorithm", DEFAULT_PATH_ALGORITHM)?;
+    let assignment_algorithms =
+        arguments.values("assignment-algorithm", DEFAULT_ASSIGNMENT_ALGORITHM)?;
+    let default_threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let nr_threads = arguments.value("threads", default_threads)?;
+    if nr_threads == 0 {
+        return Err(ParseError::new(
+            "Sweep needs at least one thread".to_string(),
+        ));
+    }
+
+    let sweep = Sweep {
+        plans: arguments.values("plan", DEFAULT_PLAN)?,
+        demands: arguments.values("demand", DEFAULT_DEMAND)?,
+        nr_robots: arguments.values("robots", defaults.nr_robots)?,
+        nr_requests: arguments.values("requests", defaults.nr_requests)?,
+        seeds,
+        algorithms: path_algorithms
+            .into_iter()
+            .cartesian_product(assignment_algorithms.into_iter())
+            .collect(),
+        placement: arguments.value("placement", PlacementType::Random)?,
+        total_time: arguments.value("total-time", defaults.total_time)?,
+        forbid_following: arguments.has_flag("forbid-following"),
+        solver: solver_settings(arguments)?,
+    };
+
+    Ok((sweep, nr_threads))
+}
+
+fn solver_settings(arguments: &Arguments) -> Result<SolverSettings, ParseError> {
+    let defaults = SolverSettings::default();
+
+    let time_limit = match arguments.text("solver-time-limit")? {
+        Some(text) => {
+            let seconds = Arguments::parse_value::<f64>("solver-time-limit", text)?;
+            match Duration::try_from_secs_f64(seconds) {
+                Ok(time_limit) if time_limit > Duration::from_secs(0) => Some(time_limit),
+                _ => {
+                    return Err(ParseError::new(
+                        "The solver time limit should be a positive number of seconds".to_string(),
+                    ))
+                }
+            }
+        }
+        None => defaults.time_limit,
+    };
+    let mip_gap = arguments.value("mip-gap", defaults.mip_gap)?;
+    if mip_gap.is_nan() || mip_gap < 0_f64 {
+        return Err(ParseError::new(
+            "The MIP gap should be at least zero".to_string(),
+        ));
+    }
+    let threads = match arguments.text("solver-threads")? {
+        Some(text) => Some(Arguments::parse_value("solver-threads", text)?),
+        None => defaults.threads,
+    };
+    if threads == Some(0) {
+        return Err(ParseError::new(
+            "The solver needs at least one thread".to_string(),
+        ));
+    }
+
+    Ok(SolverSettings {
+        backend: arguments.value("solver", Backend::Native)?,
+        ampl_path: arguments
+            .text("ampl-path")?
+            .map_or(defaults.ampl_path, str::to_string),
+        time_limit,
+        mip_gap,
+        threads,
+    })
+}
+
+/// Command line options of the form `--name value` and `--flag`.
+struct Arguments {
+    values: FnvHashMap<String, Vec<String>>,
+    flags: Vec<String>,
+}
+
+impl Arguments {
+    fn parse(mut arguments: impl Iterator<Item = String>) -> Result<Arguments, ParseError> {
+        let mut values = FnvHashMap::default();
+        let mut flags = Vec::new();
+
+        while let Some(argument) = arguments.next() {
+            if !argument.starts_with("--") {
+                return Err(ParseError::new(format!(
+                    "Unexpected argument \"{}\"",
+                    argument
+                )));
+            }
+            let name = argument[2..].to_string();
+
+            if FLAGS.contains(&name.as_str()) {
+                flags.push(name);
+            } else if OPTIONS.contains(&name.as_str()) {
+                let value = arguments.next().ok_or(ParseError::new(format!(
+                    "Option \"{}\" needs a value",
+                    argument
+                )))?;
+                values.entry(name).or_insert_with(Vec::new).push(value);
+            } else {
+                return Err(ParseError::new(format!("Unknown option \"{}\"", argument)));
+            }
+        }
+
+        Ok(Arguments { values, flags })
+    }
+    fn has_flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+    fn is_given(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+    /// The value of an option that may be given at most once.
+    fn text(&self, name: &str) -> Result<Option<&str>, ParseError> {
+        match self.values.get(name) {
+            Some(values) if values.len() > 1 => Err(ParseError::new(format!(
+                "Option \"--{}\" is given more than once",
+                name
+            ))),
+            Some(values) => Ok(Some(values[0].as_str())),
+            None => Ok(None),
+        }
+    }
+    fn value<T>(&self, name: &str, default: T) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.text(name)? {
+            Some(text) => Arguments::parse_value(name, text),
+            None => Ok(default),
+        }
+    }
+    /// All values of an option that may be given more than once.
+    fn values<T>(&self, name: &str, default: T) -> Result<Vec<T>, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        match self.values.get(name) {
+            Some(texts) => texts
+                .iter()
+                .map(|text| Arguments::parse_value(name, text))
+                .collect(),
+            None => Ok(vec![default]),
+        }
+    }
+    fn parse_value<T>(name: &str, text: &str) -> Result<T, ParseError>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        text.parse().map_err(|error| {
+            ParseError::new(format!(
+                "Invalid value for option \"--{}\": {}",
+                name, error
+            ))
+        })
+    }
+}
@@ -0,0 +1,216 @@
+use fnv::FnvHashMap;
+use simulation::plan::Vertex;
+use simulation::state::RobotState;
+
+/// Two robots that can't make their moves in the same time step.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// The robot making the offending move
+    pub robot_id: usize,
+    pub other_robot_id: usize,
+    /// Vertex the offending robot moves to
+    pub vertex: Vertex,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ConflictKind {
+    /// Both robots are at the same vertex after the step
+    Vertex,
+    /// The robots exchange positions over the same edge
+    Swap,
+    /// The robot moves to the vertex the other robot left in this step
+    Following,
+}
+
+/// All conflicts between the robots when moving from the `previous` to the `next` positions.
+///
+/// Robots that are not placed on the plan in either of the two states don't cause conflicts.
+/// Vertex conflicts are listed first, followed by swap and following conflicts.
+pub fn find_conflicts(
+    previous: &Vec<RobotState>,
+    next: &Vec<RobotState>,
+    forbid_following: bool,
+) -> Vec<Conflict> {
+    debug_assert_eq!(previous.len(), next.len());
+
+    let moves = previous
+        .iter()
+        .zip(next.iter())
+        .filter_map(|(before, after)| match (before.vertex, after.vertex) {
+            (Some(from), Some(to)) => Some((before.robot_id, from, to)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let mut conflicts = Vec::new();
+    conflicts.append(&mut find_vertex_conflicts(&moves));
+
+    let previous_occupants = moves
+        .iter()
+        .map(|&(robot_id, from, _)| (from, robot_id))
+        .collect::<FnvHashMap<_, _>>();
+    for &(robot_id, from, to) in moves.iter().filter(|&&(_, from, to)| from != to) {
+        if let Some(&other_robot_id) = previous_occupants.get(&to) {
+            let other_to = moves
+                .iter()
+                .find(|&&(robot, _, _)| robot == other_robot_id)
+                .map(|&(_, _, other_to)| other_to)
+                .unwrap();
+
+            if other_to == from {
+                if robot_id < other_robot_id {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::Swap,
+                        robot_id,
+                        other_robot_id,
+                        vertex: to,
+                    });
+                }
+            } else if forbid_following && other_to != to {
+                conflicts.push(Conflict {
+                    kind: ConflictKind::Following,
+                    robot_id,
+                    other_robot_id,
+                    vertex: to,
+                });
+            }
+        }
+    }
+
+    conflicts.sort_by_key(|conflict| match conflict.kind {
+        ConflictKind::Vertex => 0,
+        ConflictKind::Swap => 1,
+        ConflictKind::Following => 2,
+    });
+    conflicts
+}
+
+fn find_vertex_conflicts(moves: &Vec<(usize, Vertex, Vertex)>) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+
+    let mut occupants: FnvHashMap<Vertex, (usize, bool)> = FnvHashMap::default();
+    for &(robot_id, from, to) in moves {
+        let moved = from != to;
+        match occupants.get(&to) {
+            Some(&(other_robot_id, other_moved)) => {
+                // Blame the robot that moved in, if only one of them did
+                let (robot_id, other_robot_id) = if !moved && other_moved {
+                    (other_robot_id, robot_id)
+                } else {
+                    (robot_id, other_robot_id)
+                };
+                conflicts.push(Conflict {
+                    kind: ConflictKind::Vertex,
+                    robot_id,
+                    other_robot_id,
+                    vertex: to,
+                });
+            }
+            None => {
+                occupants.insert(to, (robot_id, moved));
+            }
+        }
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod test {
+
+    use simulation::conflict::find_conflicts;
+    use simulation::conflict::Conflict;
+    use simulation::conflict::ConflictKind;
+    use simulation::plan::Vertex;
+    use simulation::state::RobotState;
+
+    fn states(vertices: Vec<Option<(u64, u64)>>) -> Vec<RobotState> {
+        vertices
+            .into_iter()
+            .enumerate()
+            .map(|(robot_id, vertex)| RobotState {
+                robot_id,
+                parcel_id: None,
+                vertex: vertex.map(|(x, y)| Vertex { x, y }),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_conflict() {
+        let previous = states(vec![Some((0, 0)), Some((2, 2)), None]);
+        let next = states(vec![Some((0, 1)), Some((2, 2)), None]);
+
+        assert_eq!(find_conflicts(&previous, &next, true), vec![]);
+    }
+
+    #[test]
+    fn test_vertex_conflict() {
+        let previous = states(vec![Some((1, 1)), Some((1, 0))]);
+        let next = states(vec![Some((1, 1)), Some((1, 1))]);
+
+        assert_eq!(
+            find_conflicts(&previous, &next, false),
+            vec![Conflict {
+                kind: ConflictKind::Vertex,
+                robot_id: 1,
+                other_robot_id: 0,
+                vertex: Vertex { x: 1, y: 1 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_vertex_conflict_blames_mover() {
+        let previous = states(vec![Some((1, 0)), Some((1, 1))]);
+        let next = states(vec![Some((1, 1)), Some((1, 1))]);
+
+        let conflicts = find_conflicts(&previous, &next, false);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].kind, ConflictKind::Vertex);
+        assert_eq!(conflicts[0].robot_id, 0);
+        assert_eq!(conflicts[0].other_robot_id, 1);
+    }
+
+    #[test]
+    fn test_swap_conflict() {
+        let previous = states(vec![Some((0, 0)), Some((0, 1))]);
+        let next = states(vec![Some((0, 1)), Some((0, 0))]);
+
+        assert_eq!(
+            find_conflicts(&previous, &next, true),
+            vec![Conflict {
+                kind: ConflictKind::Swap,
+                robot_id: 0,
+                other_robot_id: 1,
+                vertex: Vertex { x: 0, y: 1 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_following_conflict() {
+        let previous = states(vec![Some((0, 0)), Some((0, 1))]);
+        let next = states(vec![Some((0, 1)), Some((0, 2))]);
+
+        assert_eq!(find_conflicts(&previous, &next, false), vec![]);
+        assert_eq!(
+            find_conflicts(&previous, &next, true),
+            vec![Conflict {
+                kind: ConflictKind::Following,
+                robot_id: 0,
+                other_robot_id: 1,
+                vertex: Vertex { x: 0, y: 1 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_removed_robot() {
+        let previous = states(vec![Some((0, 0)), Some((0, 1))]);
+        let next = states(vec![Some((0, 1)), None]);
+
+        assert_eq!(find_conflicts(&previous, &next, true), vec![]);
+    }
+}
@@ -1,16 +1,28 @@
+use rand::Rng;
 use simulation::plan::Plan;
 use simulation::plan::Vertex;
 
+pub mod poisson;
+pub mod schedule;
 pub mod uniform;
 
 pub trait Demand {
     fn create(seed: [u8; 32]) -> Self
     where
         Self: Sized;
+    /// Requests that exist at the start of the simulation, at time 0.
     fn generate(&mut self, plan: &Plan, nr_requests: u64) -> Vec<Request>;
+    /// Requests arriving at time `time`, which is at least 1.
+    fn arrivals(&mut self, _plan: &Plan, _time: usize) -> Vec<Request> {
+        Vec::with_capacity(0)
+    }
+    /// Whether requests may still arrive after time `time`.
+    fn has_future_arrivals(&self, _time: usize) -> bool {
+        false
+    }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct Request {
     pub from: Vertex,
     pub to: Vertex,
@@ -20,4 +32,11 @@ impl Request {
     pub fn distance(&self) -> u64 {
         self.from.distance(self.to)
     }
+    /// A request from a source to a terminal, both chosen uniformly at random.
+    fn uniform(rng: &mut impl Rng, sources: &Vec<Vertex>, terminals: &Vec<Vertex>) -> Request {
+        Request {
+            from: *rng.choose(sources).unwrap(),
+            to: *rng.choose(terminals).unwrap(),
+        }
+    }
 }
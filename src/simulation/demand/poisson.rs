@@ -0,0 +1,93 @@
+use rand::distributions::Distribution;
+use rand::distributions::Poisson as PoissonDistribution;
+use rand::{SeedableRng, StdRng};
+use simulation::demand::Demand;
+use simulation::demand::Request;
+use simulation::plan::Plan;
+
+const DEFAULT_RATE: f64 = 1 as f64;
+
+/// Requests keep arriving, the number per time step is Poisson distributed.
+///
+/// Sources and terminals are distributed uniformly, like in `Uniform`.
+pub struct Poisson {
+    rng: StdRng,
+    /// Expected number of arrivals per time step
+    rate: f64,
+}
+
+impl Poisson {
+    pub fn new(seed: [u8; 32], rate: f64) -> Poisson {
+        debug_assert!(rate >= 0 as f64);
+
+        Poisson {
+            rng: StdRng::from_seed(seed),
+            rate,
+        }
+    }
+}
+
+impl Demand for Poisson {
+    fn create(seed: [u8; 32]) -> Poisson {
+        Poisson::new(seed, DEFAULT_RATE)
+    }
+    fn generate(&mut self, plan: &Plan, nr_requests: u64) -> Vec<Request> {
+        let sources = plan.sources();
+        let terminals = plan.terminals();
+
+        (0..nr_requests)
+            .map(|_| Request::uniform(&mut self.rng, &sources, &terminals))
+            .collect()
+    }
+    fn arrivals(&mut self, plan: &Plan, _time: usize) -> Vec<Request> {
+        if self.rate == 0 as f64 {
+            return Vec::with_capacity(0);
+        }
+
+        let nr_arrivals = PoissonDistribution::new(self.rate).sample(&mut self.rng);
+        self.generate(plan, nr_arrivals)
+    }
+    fn has_future_arrivals(&self, _time: usize) -> bool {
+        self.rate > 0 as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use simulation::demand::poisson::Poisson;
+    use simulation::demand::Demand;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+
+    #[test]
+    fn test_arrivals() {
+        let plan = OneThreeRectangle::new(5, 5);
+        let mut demand = Poisson::new([0; 32], 2 as f64);
+
+        let nr_arrivals = (1..1001)
+            .map(|time| demand.arrivals(&plan, time).len())
+            .sum::<usize>();
+        assert!(1800 < nr_arrivals && nr_arrivals < 2200);
+        assert!(demand.has_future_arrivals(1000));
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let plan = OneThreeRectangle::new(5, 5);
+        let mut first = Poisson::new([3; 32], 1 as f64);
+        let mut second = Poisson::new([3; 32], 1 as f64);
+
+        for time in 1..20 {
+            assert_eq!(first.arrivals(&plan, time), second.arrivals(&plan, time));
+        }
+    }
+
+    #[test]
+    fn test_no_rate() {
+        let plan = OneThreeRectangle::new(5, 5);
+        let mut demand = Poisson::new([0; 32], 0 as f64);
+
+        assert_eq!(demand.arrivals(&plan, 1), vec![]);
+        assert!(!demand.has_future_arrivals(0));
+    }
+}
@@ -0,0 +1,71 @@
+use simulation::demand::Demand;
+use simulation::demand::Request;
+use simulation::plan::Plan;
+
+/// Requests arrive at fixed, predetermined times.
+pub struct Schedule {
+    /// Arrival time and request, sorted by time
+    arrivals: Vec<(usize, Request)>,
+}
+
+impl Schedule {
+    pub fn new(mut arrivals: Vec<(usize, Request)>) -> Schedule {
+        arrivals.sort_by_key(|&(time, _)| time);
+
+        Schedule { arrivals }
+    }
+    fn at(&self, time: usize) -> Vec<Request> {
+        self.arrivals
+            .iter()
+            .filter(|&&(arrival, _)| arrival == time)
+            .map(|&(_, request)| request)
+            .collect()
+    }
+}
+
+impl Demand for Schedule {
+    /// An empty schedule, the seed is not used.
+    fn create(_seed: [u8; 32]) -> Schedule {
+        Schedule::new(Vec::new())
+    }
+    /// The requests scheduled at time 0, regardless of `nr_requests`.
+    fn generate(&mut self, _plan: &Plan, _nr_requests: u64) -> Vec<Request> {
+        self.at(0)
+    }
+    fn arrivals(&mut self, _plan: &Plan, time: usize) -> Vec<Request> {
+        self.at(time)
+    }
+    fn has_future_arrivals(&self, time: usize) -> bool {
+        self.arrivals.last().map_or(false, |&(last, _)| last > time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use simulation::demand::schedule::Schedule;
+    use simulation::demand::Demand;
+    use simulation::demand::Request;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use simulation::plan::Vertex;
+
+    #[test]
+    fn test_schedule() {
+        let plan = OneThreeRectangle::new(3, 3);
+        let first = Request {
+            from: Vertex { x: 0, y: 1 },
+            to: Vertex { x: 2, y: 1 },
+        };
+        let second = Request {
+            from: Vertex { x: 0, y: 1 },
+            to: Vertex { x: 1, y: 0 },
+        };
+        let mut demand = Schedule::new(vec![(3, second), (0, first)]);
+
+        assert_eq!(demand.generate(&plan, 5), vec![first]);
+        assert_eq!(demand.arrivals(&plan, 1), vec![]);
+        assert!(demand.has_future_arrivals(2));
+        assert_eq!(demand.arrivals(&plan, 3), vec![second]);
+        assert!(!demand.has_future_arrivals(3));
+    }
+}
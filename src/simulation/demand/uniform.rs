@@ -1,4 +1,4 @@
-use rand::{Rng, SeedableRng, StdRng};
+use rand::{SeedableRng, StdRng};
 use simulation::demand::Demand;
 use simulation::demand::Request;
 use simulation::plan::Plan;
@@ -15,21 +15,11 @@ impl Demand for Uniform {
         }
     }
     fn generate(&mut self, plan: &Plan, nr_requests: u64) -> Vec<Request> {
-        let mut requests = Vec::new();
-
         let sources = plan.sources();
         let terminals = plan.terminals();
 
-        for _ in 0..nr_requests {
-            let source = *self.rng.choose(&sources).unwrap();
-            let terminal = *self.rng.choose(&terminals).unwrap();
-
-            requests.push(Request {
-                from: source,
-                to: terminal,
-            });
-        }
-
-        requests
+        (0..nr_requests)
+            .map(|_| Request::uniform(&mut self.rng, &sources, &terminals))
+            .collect()
     }
 }
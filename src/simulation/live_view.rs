@@ -0,0 +1,350 @@
+use fnv::FnvHashMap;
+use simulation::demand::Request;
+use simulation::observer::Observer;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::settings::Settings;
+use simulation::state::State;
+use simulation::Instructions;
+use std::fmt::Write as FmtWrite;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::mpsc::channel;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread::sleep;
+use std::thread::spawn;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Delay between two steps that are shown, at the start of a simulation.
+pub const DEFAULT_DELAY: Duration = Duration::from_millis(200);
+const MINIMUM_DELAY: Duration = Duration::from_millis(10);
+const MAXIMUM_DELAY: Duration = Duration::from_secs(5);
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const RESET: &str = "\x1b[0m";
+const HELP: &str = "p: pause or resume, s: step while paused, +: faster, -: slower, q: stop \
+                    drawing (followed by enter)";
+
+/// Draws the states of a running simulation in the terminal.
+///
+/// Every cell of the plan is three characters wide. Obstacles are grey, sources are green `S` and
+/// terminals blue `T`, followed by the number of requests waiting there. Robots are shown by
+/// their id, followed by a `*` when they carry a parcel. The parcels and where they go are listed
+/// below the grid.
+pub struct LiveView<'p> {
+    plan: &'p Plan,
+    x_size: u64,
+    y_size: u64,
+    /// Every request seen so far, such that the destination of a carried parcel is known
+    requests: FnvHashMap<usize, Request>,
+
+    commands: Receiver<Command>,
+    delay: Duration,
+    paused: bool,
+    stopped: bool,
+}
+
+/// Controls of the live view, one per line of input.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    /// Stop or continue running
+    Pause,
+    /// Show the next step, while paused
+    Step,
+    /// Halve the delay between steps
+    Faster,
+    /// Double the delay between steps
+    Slower,
+    /// Stop drawing, the simulation runs to the end without delay
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Command, ()> {
+        match text.trim() {
+            "p" => Ok(Command::Pause),
+            "s" | "" => Ok(Command::Step),
+            "+" => Ok(Command::Faster),
+            "-" => Ok(Command::Slower),
+            "q" => Ok(Command::Quit),
+            _ => Err(()),
+        }
+    }
+}
+
+impl<'p> LiveView<'p> {
+    pub fn new(plan: &'p Plan, delay: Duration, commands: Receiver<Command>) -> LiveView<'p> {
+        let vertices = plan.vertices();
+        let x_size = vertices
+            .iter()
+            .map(|vertex| vertex.x + 1)
+            .max()
+            .unwrap_or(0);
+        let y_size = vertices
+            .iter()
+            .map(|vertex| vertex.y + 1)
+            .max()
+            .unwrap_or(0);
+
+        LiveView {
+            plan,
+            x_size,
+            y_size,
+            requests: FnvHashMap::default(),
+
+            commands,
+            delay,
+            paused: false,
+            stopped: false,
+        }
+    }
+    /// A live view that reads its commands from the lines of standard input.
+    pub fn from_stdin(plan: &'p Plan) -> LiveView<'p> {
+        let (sender, receiver) = channel();
+        spawn(move || {
+            let stdin = io::stdin();
+            for line in stdin.lock().lines() {
+                let command = match line {
+                    Ok(line) => line.parse(),
+                    Err(_) => break,
+                };
+                if let Ok(command) = command {
+                    if sender.send(command).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        LiveView::new(plan, DEFAULT_DELAY, receiver)
+    }
+    /// Draws the state to standard output and waits before the simulation may continue.
+    pub fn show(&mut self, state: &State, time: usize) {
+        if self.stopped {
+            return;
+        }
+
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        // The view is best effort, a terminal that went away shouldn't stop the simulation
+        let _ = write!(stdout, "{}{}", CLEAR_SCREEN, self.draw(state, time));
+        let _ = stdout.flush();
+
+        self.wait();
+    }
+    /// Waits for the delay to pass, or for a step command when paused.
+    fn wait(&mut self) {
+        let start = Instant::now();
+        while !self.stopped {
+            let command = if self.paused {
+                self.commands
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                match self.delay.checked_sub(start.elapsed()) {
+                    Some(remaining) => self.commands.recv_timeout(remaining),
+                    None => return,
+                }
+            };
+
+            match command {
+                Ok(Command::Pause) => self.paused = !self.paused,
+                Ok(Command::Step) if self.paused => return,
+                Ok(Command::Step) => (),
+                Ok(Command::Faster) => self.delay = (self.delay / 2).max(MINIMUM_DELAY),
+                Ok(Command::Slower) => self.delay = (self.delay * 2).min(MAXIMUM_DELAY),
+                Ok(Command::Quit) => self.stopped = true,
+                Err(RecvTimeoutError::Timeout) => return,
+                // Without input, the view can only continue at its current speed
+                Err(RecvTimeoutError::Disconnected) if self.paused => self.paused = false,
+                Err(RecvTimeoutError::Disconnected) => {
+                    sleep(self.delay.checked_sub(start.elapsed()).unwrap_or_default());
+                    return;
+                }
+            }
+        }
+    }
+    fn draw(&mut self, state: &State, time: usize) -> String {
+        for (&id, &request) in &state.requests {
+            self.requests.insert(id, request);
+        }
+        let sources = self.plan.sources();
+        let terminals = self.plan.terminals();
+
+        let mut robots = FnvHashMap::default();
+        for robot in &state.robot_states {
+            if let Some(vertex) = robot.vertex {
+                robots.insert(vertex, robot);
+            }
+        }
+        let mut waiting = FnvHashMap::default();
+        for request in state.requests.values() {
+            *waiting.entry(request.from).or_insert(0) += 1;
+        }
+
+        let mut text = String::new();
+        for y in (0..self.y_size).rev() {
+            for x in 0..self.x_size {
+                let vertex = Vertex { x, y };
+                let cell = if let Some(robot) = robots.get(&vertex) {
+                    let marker = if robot.parcel_id.is_some() { '*' } else { ' ' };
+                    format!(
+                        "\x1b[1;{}m{:>2}{}",
+                        robot_colour(robot.robot_id),
+                        robot.robot_id,
+                        marker
+                    )
+                } else if !self.plan.contains(&vertex) {
+                    "\x1b[100m   ".to_string()
+                } else if sources.contains(&vertex) {
+                    format!("\x1b[32m S{}", count(&waiting, vertex))
+                } else if terminals.contains(&vertex) {
+                    format!("\x1b[34m T{}", count(&waiting, vertex))
+                } else {
+                    " . ".to_string()
+                };
+                write!(text, "{}{}", cell, RESET).unwrap();
+            }
+            writeln!(text).unwrap();
+        }
+
+        writeln!(
+            text,
+            "\ntime {}, {} open requests, {} ms per step{}",
+            time,
+            state.requests.len(),
+            self.delay.as_millis(),
+            if self.paused { " (paused)" } else { "" }
+        ).unwrap();
+        for robot in &state.robot_states {
+            if let Some(parcel) = robot.parcel_id {
+                match self.requests.get(&parcel) {
+                    Some(Request { to, .. }) => writeln!(
+                        text,
+                        "robot {} carries parcel {} to ({}, {})",
+                        robot.robot_id, parcel, to.x, to.y
+                    ),
+                    None => writeln!(text, "robot {} carries parcel {}", robot.robot_id, parcel),
+                }
+                .unwrap();
+            }
+        }
+        writeln!(text, "{}", HELP).unwrap();
+
+        text
+    }
+}
+
+impl<'p> Observer for LiveView<'p> {
+    fn on_initialize(&mut self, _: &Settings, _: &Plan, state: &State) -> io::Result<()> {
+        self.show(state, 0);
+        Ok(())
+    }
+    fn on_step(&mut self, time: usize, _: &Instructions, state: &State, _: f64) {
+        self.show(state, time);
+    }
+}
+
+/// Number of requests waiting at a vertex, as a single character.
+fn count(waiting: &FnvHashMap<Vertex, usize>, vertex: Vertex) -> char {
+    match waiting.get(&vertex) {
+        None => ' ',
+        Some(&count) if count < 10 => (b'0' + count as u8) as char,
+        Some(_) => '+',
+    }
+}
+
+/// ANSI foreground colour code of a robot, cycling through the six non grey colours.
+fn robot_colour(robot_id: usize) -> usize {
+    31 + robot_id % 6
+}
+
+#[cfg(test)]
+mod test {
+
+    use fnv::FnvHashMap;
+    use simulation::demand::Request;
+    use simulation::live_view::Command;
+    use simulation::live_view::LiveView;
+    use simulation::plan::middle_terminals::MiddleTerminals;
+    use simulation::plan::Vertex;
+    use simulation::state::RobotState;
+    use simulation::state::State;
+    use std::sync::mpsc::channel;
+    use std::time::Duration;
+
+    #[test]
+    fn test_draw() {
+        let plan = MiddleTerminals::new(8, 8, 2, 2);
+        let (_, receiver) = channel();
+        let mut view = LiveView::new(&plan, Duration::from_millis(0), receiver);
+
+        let mut requests = FnvHashMap::default();
+        requests.insert(
+            3,
+            Request {
+                from: Vertex { x: 0, y: 3 },
+                to: Vertex { x: 4, y: 4 },
+            },
+        );
+        let mut state = State {
+            robot_states: vec![
+                RobotState {
+                    robot_id: 0,
+                    parcel_id: None,
+                    vertex: Some(Vertex { x: 1, y: 1 }),
+                },
+                RobotState {
+                    robot_id: 12,
+                    parcel_id: None,
+                    vertex: None,
+                },
+            ],
+            requests,
+        };
+        let text = view.draw(&state, 0);
+        assert_eq!(text.lines().take_while(|line| !line.is_empty()).count(), 8);
+        assert!(text.contains(" S1"));
+        assert!(text.contains(" 0 "));
+        assert!(!text.contains("12"));
+        assert!(text.contains("time 0, 1 open requests"));
+
+        state.robot_states[0] = RobotState {
+            robot_id: 0,
+            parcel_id: Some(3),
+            vertex: Some(Vertex { x: 0, y: 3 }),
+        };
+        state.requests.clear();
+        let text = view.draw(&state, 1);
+        assert!(text.contains(" 0*"));
+        assert!(text.contains("robot 0 carries parcel 3 to (4, 4)"));
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!("p\n".parse(), Ok(Command::Pause));
+        assert_eq!("".parse(), Ok(Command::Step));
+        assert_eq!("q".parse(), Ok(Command::Quit));
+        assert!("x".parse::<Command>().is_err());
+
+        let plan = MiddleTerminals::new(8, 8, 2, 2);
+        let (sender, receiver) = channel();
+        let mut view = LiveView::new(&plan, Duration::from_millis(40), receiver);
+        for &command in &[Command::Faster, Command::Pause, Command::Step] {
+            sender.send(command).unwrap();
+        }
+        view.wait();
+        assert!(view.paused);
+        assert_eq!(view.delay, Duration::from_millis(20));
+
+        sender.send(Command::Quit).unwrap();
+        view.wait();
+        assert!(view.stopped);
+    }
+}
@@ -1,12 +1,20 @@
+use simulation::conflict::Conflict;
 use simulation::plan::Vertex;
 
+pub mod conflict;
 pub mod demand;
+pub mod live_view;
+pub mod observer;
+pub mod placement;
 pub mod plan;
 pub mod settings;
 pub mod simulation;
 pub mod state;
 pub mod statistics;
+pub mod svg;
+pub mod trace;
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Instructions {
     pub movements: Vec<MoveInstruction>,
     pub placements: Vec<PlacementInstruction>,
@@ -20,12 +28,12 @@ pub enum Instruction {
     Remove(RemovalInstruction),
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct MoveInstruction {
     pub robot_id: usize,
     pub vertex: Vertex,
 }
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct ParcelInstruction {
     pub robot_id: usize,
     pub parcel: usize,
@@ -40,20 +48,40 @@ pub trait IllegalInstructionError {
     fn message(&self) -> &String;
     fn time(&self) -> usize;
 }
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MoveErrorKind {
+    /// The robot moved over more than one edge
+    TooFar,
+    /// The vertex moved to is not part of the plan
+    OutsidePlan,
+    /// The move collides with the move of another robot
+    Conflict(Conflict),
+}
+
 #[derive(Debug)]
 pub struct IllegalMoveError {
     instruction: MoveInstruction,
+    kind: MoveErrorKind,
     message: String,
     time: usize,
 }
 impl IllegalMoveError {
-    fn from(instruction: MoveInstruction, message: String, time: usize) -> IllegalMoveError {
+    fn from(
+        instruction: MoveInstruction,
+        kind: MoveErrorKind,
+        message: String,
+        time: usize,
+    ) -> IllegalMoveError {
         IllegalMoveError {
             instruction,
+            kind,
             message,
             time,
         }
     }
+    pub fn kind(&self) -> MoveErrorKind {
+        self.kind
+    }
 }
 impl IllegalInstructionError for IllegalMoveError {
     fn instruction(&self) -> Instruction {
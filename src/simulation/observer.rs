@@ -0,0 +1,174 @@
+use simulation::demand::Request;
+use simulation::plan::Plan;
+use simulation::settings::Settings;
+use simulation::state::History;
+use simulation::state::State;
+use simulation::IllegalInstructionError;
+use simulation::Instructions;
+use std::io;
+
+/// Gets notified of the progress of a simulation, see `Simulation::add_observer`.
+///
+/// Observers can't change the course of the simulation. All callbacks do nothing by default, so
+/// an observer only implements those it is interested in.
+pub trait Observer {
+    /// The initial state, at time 0, has been set up.
+    ///
+    /// An error stops the simulation before it starts.
+    fn on_initialize(
+        &mut self,
+        _settings: &Settings,
+        _plan: &Plan,
+        _state: &State,
+    ) -> io::Result<()> {
+        Ok(())
+    }
+    /// The state at `time` followed from the instructions, which took the path algorithm
+    /// `calculation_time` seconds to calculate.
+    fn on_step(
+        &mut self,
+        _time: usize,
+        _instructions: &Instructions,
+        _state: &State,
+        _calculation_time: f64,
+    ) {
+    }
+    /// The parcel of a request was delivered in the step to `time`.
+    ///
+    /// Called before `on_step` of that same step.
+    fn on_request_completed(&mut self, _time: usize, _request_id: usize, _request: Request) {}
+    /// The path algorithm gave an instruction that isn't allowed, the simulation stops.
+    fn on_error(&mut self, _error: &IllegalInstructionError) {}
+    /// The simulation ended without errors, either because all requests were delivered or
+    /// because the time ran out.
+    fn on_finish(&mut self, _history: &History) {}
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::path::PathAlgorithm;
+    use algorithm::solver::SolverSettings;
+    use algorithm::NoSolutionError;
+    use simulation::demand::schedule::Schedule;
+    use simulation::demand::Request;
+    use simulation::observer::Observer;
+    use simulation::placement::Placement;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use simulation::plan::Plan;
+    use simulation::plan::Vertex;
+    use simulation::settings::Settings;
+    use simulation::simulation::Simulation;
+    use simulation::state::History;
+    use simulation::state::State;
+    use simulation::Instructions;
+    use simulation::MoveInstruction;
+    use simulation::ParcelInstruction;
+    use std::cell::RefCell;
+    use std::io;
+    use std::rc::Rc;
+
+    /// Gives predetermined instructions, one set per step.
+    struct Script {
+        steps: Vec<Instructions>,
+    }
+
+    impl<'p, 's, 'a> PathAlgorithm<'p, 's, 'a> for Script {
+        fn initialize(&mut self) -> Result<(), NoSolutionError> {
+            Ok(())
+        }
+        fn next_step(&mut self, _history: &History) -> Instructions {
+            self.steps.remove(0)
+        }
+    }
+
+    struct Recorder {
+        events: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Observer for Recorder {
+        fn on_initialize(&mut self, _: &Settings, _: &Plan, state: &State) -> io::Result<()> {
+            let event = format!("initialize {}", state.requests.len());
+            Ok(self.events.borrow_mut().push(event))
+        }
+        fn on_step(&mut self, time: usize, instructions: &Instructions, _: &State, _: f64) {
+            let event = format!("step {} {}", time, instructions.movements.len());
+            self.events.borrow_mut().push(event);
+        }
+        fn on_request_completed(&mut self, time: usize, request_id: usize, _: Request) {
+            let event = format!("completed {} {}", time, request_id);
+            self.events.borrow_mut().push(event);
+        }
+        fn on_finish(&mut self, history: &History) {
+            let event = format!("finish {}", history.time());
+            self.events.borrow_mut().push(event);
+        }
+    }
+
+    fn instructions(
+        movements: Vec<MoveInstruction>,
+        placements: Vec<ParcelInstruction>,
+        removals: Vec<ParcelInstruction>,
+    ) -> Instructions {
+        Instructions {
+            movements,
+            placements,
+            removals,
+            robot_removals: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_observer() {
+        let plan = OneThreeRectangle::new(3, 3);
+        let settings = Settings {
+            total_time: 10,
+            nr_robots: 1,
+            nr_requests: 1,
+            output_file: None,
+            forbid_following: false,
+            placement: Placement::Fixed(vec![Vertex { x: 0, y: 1 }]),
+            solver: SolverSettings::default(),
+        };
+        let (from, to) = (Vertex { x: 0, y: 1 }, Vertex { x: 2, y: 1 });
+        let parcel = |vertex| ParcelInstruction {
+            robot_id: 0,
+            parcel: 0,
+            vertex,
+        };
+        let movement = |x| MoveInstruction {
+            robot_id: 0,
+            vertex: Vertex { x, y: 1 },
+        };
+        let script = Script {
+            steps: vec![
+                instructions(Vec::new(), vec![parcel(from)], Vec::new()),
+                instructions(vec![movement(1)], Vec::new(), Vec::new()),
+                instructions(vec![movement(2)], Vec::new(), Vec::new()),
+                instructions(Vec::new(), Vec::new(), vec![parcel(to)]),
+            ],
+        };
+        let demand = Schedule::new(vec![(0, Request { from, to })]);
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut simulation = Simulation::new(Box::new(script), &plan, Box::new(demand), &settings);
+        simulation.add_observer(Box::new(Recorder {
+            events: Rc::clone(&events),
+        }));
+        simulation.initialize().unwrap();
+        simulation.run().ok().unwrap();
+
+        assert_eq!(
+            *events.borrow(),
+            vec![
+                "initialize 1",
+                "step 1 0",
+                "step 2 1",
+                "step 3 1",
+                "completed 4 0",
+                "step 4 0",
+                "finish 5",
+            ]
+        );
+    }
+}
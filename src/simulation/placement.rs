@@ -0,0 +1,124 @@
+use fnv::FnvHashSet;
+use rand::seq::sample_iter;
+use rand::{SeedableRng, StdRng};
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+
+/// Where the robots are at the start of the simulation.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Placement {
+    /// Uniformly at random over the vertices of the plan, using the seed
+    Random([u8; 32]),
+    /// On the sources of the plan, in the order of `Plan::sources`
+    Sources,
+    /// Robot `i` is placed at the `i`th vertex
+    Fixed(Vec<Vertex>),
+    /// Inside a rectangle of the plan, filled row by row starting at `lower_left`
+    Parking {
+        lower_left: Vertex,
+        upper_right: Vertex,
+    },
+}
+
+impl Placement {
+    /// Distinct vertices for each of the robots, or `None` if the robots don't fit.
+    pub fn place(&self, plan: &Plan, nr_robots: usize) -> Option<Vec<Vertex>> {
+        let vertices = match self {
+            Placement::Random(seed) => {
+                let mut rng = StdRng::from_seed(*seed);
+                sample_iter(&mut rng, plan.vertices().into_iter(), nr_robots).ok()?
+            }
+            Placement::Sources => plan.sources(),
+            Placement::Fixed(vertices) => vertices.clone(),
+            Placement::Parking {
+                lower_left,
+                upper_right,
+            } => (lower_left.y..(upper_right.y + 1))
+                .flat_map(|y| (lower_left.x..(upper_right.x + 1)).map(move |x| Vertex { x, y }))
+                .filter(|vertex| plan.contains(vertex))
+                .collect(),
+        };
+
+        if vertices.len() < nr_robots {
+            return None;
+        }
+        let vertices = vertices.into_iter().take(nr_robots).collect::<Vec<_>>();
+
+        let distinct = vertices.iter().collect::<FnvHashSet<_>>().len() == vertices.len();
+        if distinct && vertices.iter().all(|vertex| plan.contains(vertex)) {
+            Some(vertices)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use simulation::placement::Placement;
+    use simulation::plan::one_three_rectangle::OneThreeRectangle;
+    use simulation::plan::Vertex;
+
+    #[test]
+    fn test_random() {
+        let plan = OneThreeRectangle::new(10, 10);
+
+        let first = Placement::Random([1; 32]).place(&plan, 5);
+        let second = Placement::Random([1; 32]).place(&plan, 5);
+        assert!(first.is_some());
+        assert_eq!(first, second);
+        assert_eq!(first.unwrap().len(), 5);
+
+        assert_eq!(Placement::Random([1; 32]).place(&plan, 101), None);
+    }
+
+    #[test]
+    fn test_sources() {
+        let plan = OneThreeRectangle::new(3, 4);
+
+        assert_eq!(
+            Placement::Sources.place(&plan, 2),
+            Some(vec![Vertex { x: 0, y: 1 }, Vertex { x: 0, y: 2 }])
+        );
+        assert_eq!(Placement::Sources.place(&plan, 3), None);
+    }
+
+    #[test]
+    fn test_fixed() {
+        let plan = OneThreeRectangle::new(3, 3);
+        let vertices = vec![Vertex { x: 1, y: 1 }, Vertex { x: 2, y: 2 }];
+
+        assert_eq!(
+            Placement::Fixed(vertices.clone()).place(&plan, 2),
+            Some(vertices.clone())
+        );
+        assert_eq!(
+            Placement::Fixed(vec![Vertex { x: 1, y: 1 }, Vertex { x: 1, y: 1 }]).place(&plan, 2),
+            None
+        );
+        assert_eq!(
+            Placement::Fixed(vec![Vertex { x: 3, y: 1 }]).place(&plan, 1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parking() {
+        let plan = OneThreeRectangle::new(5, 5);
+        let placement = Placement::Parking {
+            lower_left: Vertex { x: 3, y: 3 },
+            upper_right: Vertex { x: 5, y: 5 },
+        };
+
+        assert_eq!(
+            placement.place(&plan, 3),
+            Some(vec![
+                Vertex { x: 3, y: 3 },
+                Vertex { x: 4, y: 3 },
+                Vertex { x: 3, y: 4 },
+            ])
+        );
+        assert_eq!(placement.place(&plan, 5), None);
+    }
+}
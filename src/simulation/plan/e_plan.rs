@@ -1,3 +1,4 @@
+use simulation::plan::PathLengths;
 use simulation::plan::Plan;
 use simulation::plan::Rectangle;
 use simulation::plan::Vertex;
@@ -7,6 +8,8 @@ pub struct EPlan {
     pub y_size: u64,
     pub vertical_piece_width: u64,
     pub horizontal_piece_height: u64,
+
+    path_lengths: PathLengths,
 }
 
 impl EPlan {
@@ -26,6 +29,8 @@ impl EPlan {
             y_size,
             vertical_piece_width,
             horizontal_piece_height,
+
+            path_lengths: PathLengths::default(),
         }
     }
     fn nr_gaps(&self) -> u64 {
@@ -110,6 +115,10 @@ impl Plan for EPlan {
         vertices
     }
 
+    fn path_length(&self, from: Vertex, to: Vertex) -> u64 {
+        self.path_lengths.get(self, from, to)
+    }
+
     fn neighbors(&self, &Vertex { x, y }: &Vertex) -> Vec<Vertex> {
         debug_assert!(x < self.x_size);
         debug_assert!(y < self.y_size);
@@ -134,3 +143,24 @@ impl Plan for EPlan {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+
+    use simulation::plan::e_plan::EPlan;
+    use simulation::plan::Plan;
+    use simulation::plan::Vertex;
+
+    #[test]
+    fn test_path_length() {
+        // Wall at y = 3 right of the vertical piece
+        let plan = EPlan::new(5, 6, 2, 2);
+        let (below, above) = (Vertex { x: 4, y: 2 }, Vertex { x: 4, y: 4 });
+
+        assert!(!plan.contains(&Vertex { x: 4, y: 3 }));
+        assert_eq!(plan.path_length(below, above), 8);
+        assert_eq!(plan.path_length(above, below), 8);
+        assert_eq!(plan.path_length(below, Vertex { x: 1, y: 2 }), 3);
+        assert_eq!(plan.path_length(below, below), 0);
+    }
+}
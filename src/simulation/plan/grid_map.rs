@@ -0,0 +1,400 @@
+use fnv::FnvHashSet;
+use simulation::plan::breadth_first_distances;
+use simulation::plan::PathLengths;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::trace;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// A plan read from a file, such as the map of an actual warehouse floor.
+///
+/// Three formats can be read:
+///
+/// * A grid map in the MovingAI `.map` format, in which `.` and `G` are free cells and `@`, `O`
+///   and `W` are obstacles. Cells marked `S` are sources and cells marked `T` are terminals, which
+///   replaces the swamp and tree cells of the original format. The first row of the map is the
+///   top of the plan, at the highest `y` coordinate.
+/// * The output of `Plan::write`, such that written plans can be read back in.
+/// * A trace of a simulation, see `simulation::trace`, of which the plan is used.
+pub struct GridMap {
+    vertices: Vec<Vertex>,
+    vertex_set: FnvHashSet<Vertex>,
+    sources: Vec<Vertex>,
+    terminals: Vec<Vertex>,
+
+    path_lengths: PathLengths,
+}
+
+impl GridMap {
+    pub fn new(
+        mut vertices: Vec<Vertex>,
+        mut sources: Vec<Vertex>,
+        mut terminals: Vec<Vertex>,
+    ) -> Result<GridMap, MapError> {
+        for vertices in [&mut vertices, &mut sources, &mut terminals] {
+            vertices.sort_by_key(|&Vertex { x, y }| (x, y));
+            vertices.dedup();
+        }
+
+        if vertices.is_empty() {
+            return Err(MapError::new(None, "The plan has no vertices".to_string()));
+        }
+        if sources.is_empty() || terminals.is_empty() {
+            return Err(MapError::new(
+                None,
+                "The plan needs at least one source and one terminal".to_string(),
+            ));
+        }
+        let vertex_set = vertices.iter().cloned().collect::<FnvHashSet<_>>();
+        if let Some(vertex) = sources
+            .iter()
+            .chain(terminals.iter())
+            .find(|vertex| !vertex_set.contains(vertex))
+        {
+            return Err(MapError::new(
+                None,
+                format!("{:?} is a source or terminal, but not a vertex", vertex),
+            ));
+        }
+
+        let plan = GridMap {
+            vertices,
+            vertex_set,
+            sources,
+            terminals,
+
+            path_lengths: PathLengths::default(),
+        };
+        // Robots can be placed on any vertex, so all of them need to be reachable from the sources
+        let distances = breadth_first_distances(&plan, plan.sources[0]);
+        if let Some(vertex) = plan
+            .vertices
+            .iter()
+            .find(|vertex| !distances.contains_key(vertex))
+        {
+            return Err(MapError::new(
+                None,
+                format!("{:?} isn't connected to the sources", vertex),
+            ));
+        }
+
+        Ok(plan)
+    }
+    /// Reads a file in any of the three formats, based on its first line.
+    pub fn read(path: impl AsRef<Path>) -> Result<GridMap, MapError> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut text))
+            .map_err(|error| MapError::new(None, format!("Can't read the map: {}", error)))?;
+
+        if text.starts_with('#') {
+            GridMap::from_plan_output(&text)
+        } else if text.starts_with('{') {
+            trace::read(text.as_bytes())
+                .map(|trace| trace.plan)
+                .map_err(|error| MapError::new(error.line(), error.message().clone()))
+        } else {
+            GridMap::from_map(&text)
+        }
+    }
+    /// Parses a MovingAI `.map` grid, extended with sources and terminals.
+    pub fn from_map(text: &str) -> Result<GridMap, MapError> {
+        let mut lines = text.lines().map(|line| line.trim_end()).enumerate();
+
+        let (mut height, mut width) = (None, None);
+        loop {
+            let (number, line) = lines.next().ok_or(MapError::new(
+                None,
+                "The map has no \"map\" line".to_string(),
+            ))?;
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("map"), None) => break,
+                (Some("type"), _) | (None, _) => continue,
+                (Some("height"), Some(value)) => height = Some(parse_size(number, value)?),
+                (Some("width"), Some(value)) => width = Some(parse_size(number, value)?),
+                _ => {
+                    return Err(MapError::new(
+                        Some(number),
+                        format!("Unknown header \"{}\"", line),
+                    ))
+                }
+            }
+        }
+        let height = height.ok_or(MapError::new(None, "The map has no height".to_string()))?;
+        let width = width.ok_or(MapError::new(None, "The map has no width".to_string()))?;
+
+        let (mut vertices, mut sources, mut terminals) = (Vec::new(), Vec::new(), Vec::new());
+        let rows = lines.take(height as usize).collect::<Vec<_>>();
+        if rows.len() as u64 != height {
+            return Err(MapError::new(
+                None,
+                format!("The map has {} rows instead of {}", rows.len(), height),
+            ));
+        }
+        for (row, &(number, line)) in rows.iter().enumerate() {
+            if line.chars().count() as u64 != width {
+                return Err(MapError::new(
+                    Some(number),
+                    format!(
+                        "The row has {} cells instead of {}",
+                        line.chars().count(),
+                        width
+                    ),
+                ));
+            }
+
+            let y = height - 1 - row as u64;
+            for (x, cell) in line.chars().enumerate() {
+                let vertex = Vertex { x: x as u64, y };
+                match cell {
+                    '.' | 'G' => vertices.push(vertex),
+                    'S' => {
+                        vertices.push(vertex);
+                        sources.push(vertex);
+                    }
+                    'T' => {
+                        vertices.push(vertex);
+                        terminals.push(vertex);
+                    }
+                    '@' | 'O' | 'W' => (),
+                    _ => {
+                        return Err(MapError::new(
+                            Some(number),
+                            format!("Unknown cell '{}'", cell),
+                        ))
+                    }
+                }
+            }
+        }
+
+        GridMap::new(vertices, sources, terminals)
+    }
+    /// Parses the vertices, sources and terminals as written by `Plan::write`.
+    ///
+    /// Other sections, such as those written by `Settings::write`, are ignored.
+    pub fn from_plan_output(text: &str) -> Result<GridMap, MapError> {
+        let mut sections: Vec<(&str, Vec<(usize, &str)>)> = Vec::new();
+        let mut current = None;
+
+        for (number, line) in text.lines().map(str::trim).enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            current = match current {
+                None if line.starts_with('#') => Some((line[1..].trim(), Vec::new())),
+                None => {
+                    return Err(MapError::new(
+                        Some(number),
+                        format!("Expected a section header, found \"{}\"", line),
+                    ))
+                }
+                Some(section) if line == "###" => {
+                    sections.push(section);
+                    None
+                }
+                Some((name, mut lines)) => {
+                    lines.push((number, line));
+                    Some((name, lines))
+                }
+            };
+        }
+        if current.is_some() {
+            return Err(MapError::new(
+                None,
+                "The last section isn't closed".to_string(),
+            ));
+        }
+
+        let find_section = |name: &str| -> Result<Vec<Vertex>, MapError> {
+            sections
+                .iter()
+                .find(|&&(section, _)| section == name)
+                .ok_or(MapError::new(
+                    None,
+                    format!("Section \"{}\" is missing", name),
+                ))?
+                .1
+                .iter()
+                .map(|&(number, line)| parse_vertex(number, line))
+                .collect()
+        };
+
+        GridMap::new(
+            find_section("Vertices")?,
+            find_section("Sources")?,
+            find_section("Terminals")?,
+        )
+    }
+}
+
+impl Plan for GridMap {
+    fn vertices(&self) -> Vec<Vertex> {
+        self.vertices.clone()
+    }
+    fn contains(&self, vertex: &Vertex) -> bool {
+        self.vertex_set.contains(vertex)
+    }
+    fn sources(&self) -> Vec<Vertex> {
+        self.sources.clone()
+    }
+    fn terminals(&self) -> Vec<Vertex> {
+        self.terminals.clone()
+    }
+    fn neighbors(&self, &Vertex { x, y }: &Vertex) -> Vec<Vertex> {
+        let mut neighbors = vec![Vertex { x: x + 1, y }, Vertex { x, y: y + 1 }];
+        if y > 0 {
+            neighbors.push(Vertex { x, y: y - 1 });
+        }
+        if x > 0 {
+            neighbors.push(Vertex { x: x - 1, y });
+        }
+
+        neighbors
+            .into_iter()
+            .filter(|vertex| self.contains(vertex))
+            .collect()
+    }
+    fn path_length(&self, from: Vertex, to: Vertex) -> u64 {
+        self.path_lengths.get(self, from, to)
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct MapError {
+    /// Line of the file, counting from zero, if the error is about a specific line
+    line: Option<usize>,
+    message: String,
+}
+
+impl MapError {
+    fn new(line: Option<usize>, message: String) -> MapError {
+        MapError { line, message }
+    }
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {}: {}", line + 1, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+fn parse_size(line: usize, value: &str) -> Result<u64, MapError> {
+    value
+        .parse()
+        .map_err(|_| MapError::new(Some(line), format!("Invalid size \"{}\"", value)))
+}
+
+fn parse_vertex(line: usize, text: &str) -> Result<Vertex, MapError> {
+    let coordinates = text
+        .split(',')
+        .map(|coordinate| coordinate.trim().parse::<u64>())
+        .collect::<Result<Vec<_>, _>>();
+    match coordinates {
+        Ok(ref coordinates) if coordinates.len() == 2 => Ok(Vertex {
+            x: coordinates[0],
+            y: coordinates[1],
+        }),
+        _ => Err(MapError::new(
+            Some(line),
+            format!("Invalid vertex \"{}\"", text),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use simulation::plan::e_plan::EPlan;
+    use simulation::plan::grid_map::GridMap;
+    use simulation::plan::Plan;
+    use simulation::plan::Vertex;
+
+    const MAP: &str = "type octile
+height 4
+width 5
+map
+S..@T
+S.@@.
+S....
+.@@.T
+";
+
+    #[test]
+    fn test_from_map() {
+        let plan = GridMap::from_map(MAP).unwrap();
+
+        assert_eq!(plan.vertices().len(), 20 - 5);
+        assert_eq!(
+            plan.sources(),
+            vec![
+                Vertex { x: 0, y: 1 },
+                Vertex { x: 0, y: 2 },
+                Vertex { x: 0, y: 3 },
+            ]
+        );
+        assert_eq!(
+            plan.terminals(),
+            vec![Vertex { x: 4, y: 0 }, Vertex { x: 4, y: 3 }]
+        );
+        assert!(!plan.contains(&Vertex { x: 3, y: 3 }));
+        assert_eq!(
+            plan.neighbors(&Vertex { x: 1, y: 0 }),
+            vec![Vertex { x: 1, y: 1 }, Vertex { x: 0, y: 0 }]
+        );
+        // Around the wall through the row in the middle
+        assert_eq!(
+            plan.path_length(Vertex { x: 0, y: 3 }, Vertex { x: 4, y: 3 }),
+            8
+        );
+    }
+
+    #[test]
+    fn test_invalid_map() {
+        let error = GridMap::from_map("height 1\nwidth 2\nmap\nSX\n")
+            .err()
+            .unwrap();
+        assert_eq!(error.line(), Some(3));
+
+        assert!(GridMap::from_map("height 2\nwidth 2\nmap\nST\n").is_err());
+        assert!(GridMap::from_map("height 1\nwidth 3\nmap\nST\n").is_err());
+        assert!(GridMap::from_map("height 1\nwidth 2\nmap\n..\n").is_err());
+        // The terminal can't be reached from the source
+        assert!(GridMap::from_map("height 1\nwidth 3\nmap\nS@T\n").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = EPlan::new(10, 10, 3, 2);
+        let mut output = Vec::new();
+        original.write(&mut output).unwrap();
+
+        let text = "# Number of robots\n2\n###\n".to_string() + &String::from_utf8(output).unwrap();
+        let plan = GridMap::from_plan_output(&text).unwrap();
+        let sorted = |mut vertices: Vec<Vertex>| {
+            vertices.sort_by_key(|&Vertex { x, y }| (x, y));
+            vertices.dedup();
+            vertices
+        };
+        assert_eq!(plan.vertices(), sorted(original.vertices()));
+        assert_eq!(plan.sources(), sorted(original.sources()));
+        assert_eq!(plan.terminals(), sorted(original.terminals()));
+        assert_eq!(
+            plan.path_length(Vertex { x: 9, y: 1 }, Vertex { x: 9, y: 5 }),
+            original.path_length(Vertex { x: 9, y: 1 }, Vertex { x: 9, y: 5 })
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use simulation::plan::PathLengths;
 use simulation::plan::Plan;
 use simulation::plan::Rectangle;
 use simulation::plan::Vertex;
@@ -9,6 +10,8 @@ pub struct MiddleTerminals {
     pub y_size: u64,
     pub padding: u64,
     pub interval: u64,
+
+    path_lengths: PathLengths,
 }
 
 impl MiddleTerminals {
@@ -24,8 +27,18 @@ impl MiddleTerminals {
             y_size,
             padding,
             interval,
+
+            path_lengths: PathLengths::default(),
         }
     }
+    fn is_hole(&self, &Vertex { x, y }: &Vertex) -> bool {
+        self.padding <= x
+            && x <= self.x_size - self.padding
+            && self.padding <= y
+            && y <= self.y_size - self.padding
+            && (x - self.padding) % self.interval == 0
+            && (y - self.padding) % self.interval == 0
+    }
     fn holes(&self) -> Vec<Vertex> {
         let nr_x_terminals = (self.x_size - 2 * self.padding) / self.interval;
         let nr_y_terminals = (self.y_size - 2 * self.padding) / self.interval;
@@ -65,11 +78,8 @@ impl Plan for MiddleTerminals {
             .filter(|v| <MiddleTerminals as Plan>::contains(self, v))
             .collect()
     }
-    fn contains(&self, &Vertex { x, y }: &Vertex) -> bool {
-        x < self.x_size
-            && y < self.y_size
-            && (x as i64 - self.padding as i64) % self.interval as i64 != 0
-            || (y as i64 - self.padding as i64) % self.interval as i64 != 0
+    fn contains(&self, vertex: &Vertex) -> bool {
+        vertex.x < self.x_size && vertex.y < self.y_size && !self.is_hole(vertex)
     }
     fn sources(&self) -> Vec<Vertex> {
         (0..self.y_size)
@@ -83,6 +93,9 @@ impl Plan for MiddleTerminals {
             .flat_map(|v| self.neighbors(&v))
             .collect()
     }
+    fn path_length(&self, from: Vertex, to: Vertex) -> u64 {
+        self.path_lengths.get(self, from, to)
+    }
     fn neighbors(&self, &Vertex { x, y }: &Vertex) -> Vec<Vertex> {
         debug_assert!(x < self.x_size);
         debug_assert!(y < self.y_size);
@@ -102,5 +115,43 @@ impl Plan for MiddleTerminals {
         }
 
         neighbors
+            .into_iter()
+            .filter(|v| <MiddleTerminals as Plan>::contains(self, v))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use simulation::plan::middle_terminals::MiddleTerminals;
+    use simulation::plan::Plan;
+    use simulation::plan::Vertex;
+
+    #[test]
+    fn test_contains() {
+        let plan = MiddleTerminals::new(7, 7, 2, 2);
+
+        assert!(!plan.contains(&Vertex { x: 2, y: 2 }));
+        assert!(!plan.contains(&Vertex { x: 4, y: 4 }));
+        assert!(plan.contains(&Vertex { x: 0, y: 0 }));
+        assert!(plan.contains(&Vertex { x: 6, y: 6 }));
+        assert!(!plan.contains(&Vertex { x: 7, y: 0 }));
+        assert!(plan.sources().iter().all(|source| plan.contains(source)));
+        assert!(plan
+            .terminals()
+            .iter()
+            .all(|terminal| plan.contains(terminal)));
+        assert_eq!(plan.vertices().len(), 7 * 7 - 4);
+    }
+
+    #[test]
+    fn test_path_length() {
+        let plan = MiddleTerminals::new(7, 7, 2, 2);
+        let (left, right) = (Vertex { x: 1, y: 2 }, Vertex { x: 3, y: 2 });
+
+        assert_eq!(plan.path_length(left, right), 4);
+        assert_eq!(plan.path_length(right, left), 4);
+        assert_eq!(plan.path_length(left, Vertex { x: 1, y: 5 }), 3);
     }
 }
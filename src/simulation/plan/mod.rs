@@ -1,16 +1,20 @@
-use std::fs::File;
 use std::io;
-use std::io::BufWriter;
 use std::io::Write;
 
+use fnv::FnvHashMap;
 use fnv::FnvHashSet;
 use fnv::FnvHasher;
 use itertools::Itertools;
+use std::collections::hash_map::Entry;
+use std::collections::VecDeque;
 use std::hash::Hash;
 use std::hash::Hasher;
 use std::num::Wrapping;
+use std::sync::Arc;
+use std::sync::Mutex;
 
 pub mod e_plan;
+pub mod grid_map;
 pub mod middle_terminals;
 pub mod one_three_rectangle;
 
@@ -20,7 +24,7 @@ pub trait Plan: Send + Sync {
     fn sources(&self) -> Vec<Vertex>;
     fn terminals(&self) -> Vec<Vertex>;
     fn neighbors(&self, vertex: &Vertex) -> Vec<Vertex>;
-    fn write(&self, writer: &mut BufWriter<File>) -> io::Result<()> {
+    fn write(&self, writer: &mut Write) -> io::Result<()> {
         writer.write("# Vertices\n".as_bytes())?;
         for Vertex { x, y } in self.vertices() {
             writer.write(format!("{},{}\n", x, y).as_bytes())?;
@@ -41,9 +45,13 @@ pub trait Plan: Send + Sync {
 
         writer.flush()
     }
+    /// Length of a shortest path between two vertices of the plan.
+    ///
+    /// Plans that are expected to be queried often should override this with a cached version.
     fn path_length(&self, from: Vertex, to: Vertex) -> u64 {
-        // TODO
-        from.distance(to)
+        *breadth_first_distances(self, to)
+            .get(&from)
+            .expect("Vertices are not connected")
     }
     fn neighborhood(&self, vertex: Vertex, radius: u64) -> Vec<Vertex> {
         debug_assert!(self.contains(&vertex));
@@ -89,6 +97,62 @@ pub trait Plan: Send + Sync {
     }
 }
 
+/// Lengths of the shortest paths from `from` to all vertices that can be reached from it.
+pub fn breadth_first_distances<P: Plan + ?Sized>(
+    plan: &P,
+    from: Vertex,
+) -> FnvHashMap<Vertex, u64> {
+    debug_assert!(plan.contains(&from));
+
+    let mut distances = FnvHashMap::default();
+    distances.insert(from, 0);
+    let mut to_visit = VecDeque::new();
+    to_visit.push_back(from);
+
+    while let Some(vertex) = to_visit.pop_front() {
+        let distance = distances[&vertex];
+        for neighbor in plan.neighbors(&vertex) {
+            if let Entry::Vacant(entry) = distances.entry(neighbor) {
+                entry.insert(distance + 1);
+                to_visit.push_back(neighbor);
+            }
+        }
+    }
+
+    distances
+}
+
+/// Shortest path lengths in a plan, calculated with a breadth first search from the target of
+/// the path and cached for each target.
+///
+/// Paths are mostly requested towards sources and terminals, such that only few searches are
+/// needed.
+#[derive(Default)]
+pub struct PathLengths {
+    distances: Mutex<FnvHashMap<Vertex, Arc<FnvHashMap<Vertex, u64>>>>,
+}
+
+impl PathLengths {
+    pub fn get<P: Plan + ?Sized>(&self, plan: &P, from: Vertex, to: Vertex) -> u64 {
+        let mut cache = self.distances.lock().unwrap();
+        // The plan is undirected, so a search from either end of the path will do
+        let (distances, other_end) = match (cache.get(&to), cache.get(&from)) {
+            (Some(distances), _) => (Arc::clone(distances), from),
+            (None, Some(distances)) => (Arc::clone(distances), to),
+            (None, None) => {
+                let distances = Arc::new(breadth_first_distances(plan, to));
+                cache.insert(to, Arc::clone(&distances));
+                (distances, from)
+            }
+        };
+        drop(cache);
+
+        *distances
+            .get(&other_end)
+            .expect("Vertices are not connected")
+    }
+}
+
 pub trait Rectangle: Plan {
     fn vertices(&self) -> Vec<Vertex> {
         (0..self.x_size())
@@ -131,7 +195,7 @@ impl Hash for UndirectedEdge {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct Vertex {
     pub x: u64,
     pub y: u64,
@@ -56,6 +56,10 @@ impl Plan for OneThreeRectangle {
 
         terminals
     }
+    /// There are no obstacles, so the Manhattan distance is exact.
+    fn path_length(&self, from: Vertex, to: Vertex) -> u64 {
+        from.distance(to)
+    }
     fn neighbors(&self, &Vertex { x, y }: &Vertex) -> Vec<Vertex> {
         debug_assert!(x < self.x_size);
         debug_assert!(y < self.y_size);
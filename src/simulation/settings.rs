@@ -1,23 +1,21 @@
-use std::fs::File;
-use std::io;
-use std::io::BufWriter;
-use std::io::Write;
+use algorithm::solver::SolverSettings;
+use simulation::placement::Placement;
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
     pub total_time: usize,
     pub nr_robots: usize,
+    /// Number of requests at the start of the simulation, more might arrive later
     pub nr_requests: u64,
+    /// File to write a trace of the simulation to, see `simulation::trace`
+    #[serde(skip)]
     pub output_file: Option<String>,
-}
-
-impl Settings {
-    pub fn write(&self, writer: &mut BufWriter<File>) -> io::Result<()> {
-        writer.write("# Number of robots\n".as_bytes())?;
-        writer.write(format!("{}\n", self.nr_robots).as_bytes())?;
-        writer.write("###\n".as_bytes())?;
-
-        writer.flush()
-    }
+    /// Whether robots are forbidden to move to a vertex in the same step as another robot leaves it
+    pub forbid_following: bool,
+    pub placement: Placement,
+    /// Solver for the ILP based algorithms
+    #[serde(default)]
+    pub solver: SolverSettings,
 }
 
 impl Default for Settings {
@@ -27,6 +25,9 @@ impl Default for Settings {
             nr_robots: 2,
             nr_requests: 4,
             output_file: None,
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
         }
     }
 }
@@ -1,30 +1,33 @@
 use algorithm::path::PathAlgorithm;
 use fnv::FnvHashMap;
 use fnv::FnvHashSet;
-use rand::seq::sample_iter;
-use rand::thread_rng;
+use simulation::conflict::find_conflicts;
+use simulation::conflict::ConflictKind;
 use simulation::demand::Demand;
 use simulation::demand::Request;
+use simulation::observer::Observer;
 use simulation::plan::Plan;
 use simulation::plan::Vertex;
 use simulation::settings::Settings;
 use simulation::state::History;
 use simulation::state::RobotState;
 use simulation::state::State;
+use simulation::trace::TraceWriter;
 use simulation::IllegalInstructionError;
 use simulation::IllegalMoveError;
 use simulation::IllegalPlacementError;
 use simulation::IllegalRemovalError;
 use simulation::Instructions;
+use simulation::MoveErrorKind;
 use simulation::MoveInstruction;
 use simulation::ParcelInstruction;
 use simulation::PlacementInstruction;
 use simulation::RemovalInstruction;
 use simulation::RobotRemovalInstruction;
 use std::fs::File;
-use std::fs::OpenOptions;
 use std::io;
 use std::io::BufWriter;
+use std::time::Instant;
 
 pub struct Simulation<'a, 'p, 's> {
     algorithm: Box<PathAlgorithm<'p, 's, 'a> + 'a>,
@@ -33,7 +36,8 @@ pub struct Simulation<'a, 'p, 's> {
     settings: &'s Settings,
 
     pub history: History,
-    output_writer: Option<BufWriter<File>>,
+    next_request_id: usize,
+    observers: Vec<Box<Observer + 'a>>,
 }
 
 impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
@@ -50,9 +54,15 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
             settings,
 
             history: History::empty(),
-            output_writer: None,
+            next_request_id: 0,
+            observers: Vec::new(),
         }
     }
+    /// Registers an observer, which is notified of the progress of the simulation from the call
+    /// to `initialize` on.
+    pub fn add_observer(&mut self, observer: Box<Observer + 'a>) {
+        self.observers.push(observer);
+    }
     /// Gets the initial state of the system set up, creates history of time 0.
     pub fn initialize(&mut self) -> io::Result<()> {
         if let Some(ref file_name) = self.settings.output_file {
@@ -61,27 +71,25 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
         self.set_initial_state();
         self.algorithm.initialize();
 
-        if let Some(ref mut writer) = self.output_writer {
-            self.settings.write(writer)?;
-            self.plan.write(writer)?;
+        for observer in &mut self.observers {
+            observer.on_initialize(self.settings, self.plan, self.history.last_state())?;
         }
 
         Ok(())
     }
     fn setup_output(&mut self, output_file_name: &String) -> io::Result<()> {
-        let file = OpenOptions::new().write(true).open(output_file_name)?;
+        let file = File::create(output_file_name)?;
         let buffered_writer = BufWriter::new(file);
 
-        Ok(self.output_writer = Some(buffered_writer))
+        Ok(self.add_observer(Box::new(TraceWriter::new(buffered_writer))))
     }
     fn set_initial_state(&mut self) {
-        let mut rng = thread_rng();
-        let sample = sample_iter(
-            &mut rng,
-            self.plan.vertices().into_iter(),
-            self.settings.nr_robots,
-        ).unwrap();
-        let robot_states = sample
+        let vertices = self
+            .settings
+            .placement
+            .place(self.plan, self.settings.nr_robots)
+            .expect("Robots can't be placed on the plan");
+        let robot_states = vertices
             .into_iter()
             .enumerate()
             .map(|(robot_id, vertex)| RobotState {
@@ -91,43 +99,82 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
             })
             .collect();
 
-        let requests = self
-            .demand
-            .generate(self.plan, self.settings.nr_requests)
-            .into_iter()
-            .enumerate()
-            .collect();
+        let requests = self.demand.generate(self.plan, self.settings.nr_requests);
 
         self.history.states.push(State {
             robot_states,
-            requests,
+            requests: FnvHashMap::default(),
         });
+        self.add_requests(requests);
+    }
+    /// Adds requests to the last state, under new request ids.
+    fn add_requests(&mut self, requests: Vec<Request>) {
+        let state = self.history.states.last_mut().unwrap();
+        for request in requests {
+            state.requests.insert(self.next_request_id, request);
+            self.next_request_id += 1;
+        }
     }
     pub fn run(mut self) -> Result<History, Box<IllegalInstructionError>> {
-        while self.history.last_state().requests.len() > 0
+        while (self.history.last_state().requests.len() > 0
+            || self.demand.has_future_arrivals(self.history.time() - 1))
             && self.history.time() < self.settings.total_time
         {
-            println!("{:?}", self.history.last_state());
-            println!("{}", self.history.time());
+            let start = Instant::now();
             let instructions = self.algorithm.next_step(&self.history);
-            self.new_state(instructions)?;
-            if let Some(ref mut writer) = self.output_writer {
-                self.history.last_state().write(writer);
+            let elapsed = start.elapsed();
+            let calculation_time = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+            self.history.calculation_times.push(calculation_time);
+            if let Err(error) = self.new_state(&instructions) {
+                for observer in &mut self.observers {
+                    observer.on_error(&*error);
+                }
+                return Err(error);
             }
+            let arrivals = self.demand.arrivals(self.plan, self.history.time() - 1);
+            self.add_requests(arrivals);
+            self.notify_step(&instructions, calculation_time);
         }
 
+        for observer in &mut self.observers {
+            observer.on_finish(&self.history);
+        }
         Ok(self.history)
     }
+    /// Notifies the observers of the last state and the requests that were completed in it.
+    fn notify_step(&mut self, instructions: &Instructions, calculation_time: f64) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        let time = self.history.time() - 1;
+        let state = self.history.last_state();
+        let previous_state = &self.history.states[time - 1];
+        let mut completed = previous_state
+            .requests
+            .iter()
+            .filter(|(id, _)| !state.requests.contains_key(id))
+            .map(|(&id, &request)| (id, request))
+            .collect::<Vec<_>>();
+        completed.sort_by_key(|&(id, _)| id);
+
+        for observer in &mut self.observers {
+            for &(id, request) in &completed {
+                observer.on_request_completed(time, id, request);
+            }
+            observer.on_step(time, instructions, state, calculation_time);
+        }
+    }
     fn new_state(
         &mut self,
-        instructions: Instructions,
+        instructions: &Instructions,
     ) -> Result<(), Box<IllegalInstructionError>> {
         let used_vertices = self
             .history
             .last_state()
             .robot_states
             .iter()
-            .map(|robot| (robot.vertex.unwrap(), robot.robot_id))
+            .filter_map(|robot| robot.vertex.map(|vertex| (vertex, robot.robot_id)))
             .collect::<FnvHashMap<_, _>>();
         let mut newly_used_vertices = FnvHashSet::default();
 
@@ -135,25 +182,26 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
         let mut new_requests = self.history.last_state().requests.clone();
 
         self.process_move_instructions(
-            instructions.movements,
+            &instructions.movements,
             &mut new_states,
-            &used_vertices,
             &mut newly_used_vertices,
         ).map_err(|e| Box::new(e) as Box<IllegalInstructionError>)?;
         self.process_placement_instructions(
-            instructions.placements,
+            &instructions.placements,
             &mut new_states,
             &used_vertices,
             &mut newly_used_vertices,
             &mut new_requests,
         ).map_err(|e| Box::new(e) as Box<IllegalInstructionError>)?;
         self.process_removal_instructions(
-            instructions.removals,
+            &instructions.removals,
             &mut new_states,
             &mut new_requests,
             &mut newly_used_vertices,
         ).map_err(|e| Box::new(e) as Box<IllegalInstructionError>)?;
-        self.process_robot_removal_instructions(instructions.robot_removals, &mut new_states);
+        self.process_robot_removal_instructions(&instructions.robot_removals, &mut new_states);
+        self.check_for_conflicts(&new_states)
+            .map_err(|e| Box::new(e) as Box<IllegalInstructionError>)?;
 
         Ok(self.history.states.push(State {
             robot_states: new_states,
@@ -162,12 +210,11 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
     }
     fn process_move_instructions(
         &self,
-        move_instructions: Vec<MoveInstruction>,
+        move_instructions: &[MoveInstruction],
         new_states: &mut Vec<RobotState>,
-        used_vertices: &FnvHashMap<Vertex, usize>,
         newly_used_vertices: &mut FnvHashSet<Vertex>,
     ) -> Result<(), IllegalMoveError> {
-        for instruction in move_instructions {
+        for &instruction in move_instructions {
             if self
                 .history
                 .last_robot_state(instruction.robot_id)
@@ -177,11 +224,7 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
                 continue;
             }
 
-            if let Some(error) = self.check_for_move_instruction_error(
-                instruction,
-                used_vertices,
-                newly_used_vertices,
-            ) {
+            if let Some(error) = self.check_for_move_instruction_error(instruction) {
                 return Err(error);
             }
             let MoveInstruction { robot_id, vertex } = instruction;
@@ -199,29 +242,17 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
     fn check_for_move_instruction_error(
         &self,
         instruction: MoveInstruction,
-        used_vertices: &FnvHashMap<Vertex, usize>,
-        newly_used_vertices: &FnvHashSet<Vertex>,
     ) -> Option<IllegalMoveError> {
         let MoveInstruction { robot_id, vertex } = instruction;
 
-        //        match used_vertices.get(&vertex) {
-        //            None => (),
-        //            Some(robot) if *robot != instruction.robot_id => {
-        //                return Some(IllegalMoveError::from(
-        //                    instruction,
-        //                    "State used in previous time step by other robot".to_string(),
-        //                    self.history.time(),
-        //                ));
-        //            }
-        //            _ => (),
-        //        }
-        //        if newly_used_vertices.contains(&vertex) {
-        //            return Some(IllegalMoveError::from(
-        //                instruction,
-        //                "State will be used in next time step".to_string(),
-        //                self.history.time(),
-        //            ));
-        //        }
+        if !self.plan.contains(&vertex) {
+            return Some(IllegalMoveError::from(
+                instruction,
+                MoveErrorKind::OutsidePlan,
+                format!("Vertex {:?} not part of currently active plan", vertex),
+                self.history.time(),
+            ));
+        }
         if self
             .history
             .last_robot_state(robot_id)
@@ -229,24 +260,54 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
             .unwrap()
             .distance(vertex) > 1
         {
-            return Some(IllegalMoveError {
+            return Some(IllegalMoveError::from(
                 instruction,
-                message: "Only one move at a time".to_string(),
-                time: self.history.time(),
-            });
+                MoveErrorKind::TooFar,
+                "Only one move at a time".to_string(),
+                self.history.time(),
+            ));
         }
 
         None
     }
+    /// Checks that the robots can make their moves simultaneously, see `conflict::find_conflicts`.
+    fn check_for_conflicts(&self, new_states: &Vec<RobotState>) -> Result<(), IllegalMoveError> {
+        let conflicts = find_conflicts(
+            &self.history.last_state().robot_states,
+            new_states,
+            self.settings.forbid_following,
+        );
+
+        match conflicts.first() {
+            None => Ok(()),
+            Some(&conflict) => {
+                let message = match conflict.kind {
+                    ConflictKind::Vertex => "Vertex used by other robot in next time step",
+                    ConflictKind::Swap => "Robots swap positions over the same edge",
+                    ConflictKind::Following => "Vertex used by other robot in previous time step",
+                };
+
+                Err(IllegalMoveError::from(
+                    MoveInstruction {
+                        robot_id: conflict.robot_id,
+                        vertex: conflict.vertex,
+                    },
+                    MoveErrorKind::Conflict(conflict),
+                    format!("{} (robot {})", message, conflict.other_robot_id),
+                    self.history.time(),
+                ))
+            }
+        }
+    }
     fn process_placement_instructions(
         &self,
-        placement_instructions: Vec<PlacementInstruction>,
+        placement_instructions: &[PlacementInstruction],
         new_states: &mut Vec<RobotState>,
         used_vertices: &FnvHashMap<Vertex, usize>,
         newly_used_vertices: &mut FnvHashSet<Vertex>,
         new_requests: &mut FnvHashMap<usize, Request>,
     ) -> Result<(), IllegalPlacementError> {
-        for instruction in placement_instructions {
+        for &instruction in placement_instructions {
             if self
                 .history
                 .last_robot_state(instruction.robot_id)
@@ -326,12 +387,12 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
     }
     fn process_removal_instructions(
         &self,
-        removal_instructions: Vec<RemovalInstruction>,
+        removal_instructions: &[RemovalInstruction],
         new_states: &mut Vec<RobotState>,
         new_requests: &mut FnvHashMap<usize, Request>,
         used_vertices: &mut FnvHashSet<Vertex>,
     ) -> Result<(), IllegalRemovalError> {
-        for instruction in removal_instructions {
+        for &instruction in removal_instructions {
             if self
                 .history
                 .last_robot_state(instruction.robot_id)
@@ -415,10 +476,10 @@ impl<'a, 'p, 's> Simulation<'a, 'p, 's> {
     }
     fn process_robot_removal_instructions(
         &self,
-        instructions: Vec<RobotRemovalInstruction>,
+        instructions: &[RobotRemovalInstruction],
         new_states: &mut Vec<RobotState>,
     ) {
-        for MoveInstruction { robot_id, vertex } in instructions {
+        for &MoveInstruction { robot_id, vertex } in instructions {
             new_states[robot_id].vertex = None;
         }
     }
@@ -2,10 +2,6 @@ use fnv::FnvHashMap;
 use simulation::demand::Request;
 use simulation::plan::Vertex;
 use simulation::statistics::Statistics;
-use std::fs::File;
-use std::io;
-use std::io::BufWriter;
-use std::io::Write;
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct State {
@@ -13,25 +9,7 @@ pub struct State {
     pub requests: FnvHashMap<usize, Request>,
 }
 
-impl State {
-    pub fn write(&self, writer: &mut BufWriter<File>) -> io::Result<()> {
-        writer.write(format!("# Robot positions\n").as_bytes())?;
-        for RobotState {
-            robot_id,
-            parcel_id,
-            vertex,
-        } in &self.robot_states
-        {
-            if let Some(Vertex { x, y }) = vertex {
-                writer.write(format!("{},{},{},{}\n", robot_id, -1, x, y).as_bytes())?;
-            }
-        }
-        writer.write("###\n".as_bytes())?;
-        writer.flush()
-    }
-}
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct RobotState {
     pub robot_id: usize,
     pub parcel_id: Option<usize>,
@@ -41,6 +19,7 @@ pub struct RobotState {
 #[derive(Debug, PartialEq)]
 pub struct History {
     pub states: Vec<State>,
+    /// Seconds the path algorithm needed to calculate the instructions leading to each next state
     pub calculation_times: Vec<f64>,
 }
 
@@ -52,7 +31,7 @@ impl History {
         }
     }
     pub fn calculate_statistics(&self) -> Statistics {
-        Statistics {}
+        Statistics::from(self)
     }
     pub fn last_state(&self) -> &State {
         self.states.last().unwrap()
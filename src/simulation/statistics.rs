@@ -1 +1,422 @@
-pub struct Statistics {}
+use fnv::FnvHashMap;
+use simulation::state::History;
+use std::fmt;
+
+/// Summary of a simulation run, derived from the states in a `History`.
+#[derive(Debug, PartialEq)]
+pub struct Statistics {
+    /// Time at which the last request was delivered, if all requests were delivered
+    pub makespan: Option<usize>,
+    /// Number of time steps the simulation ran
+    pub duration: usize,
+    pub requests: Vec<RequestStatistics>,
+    pub robots: Vec<RobotStatistics>,
+    /// Time the path algorithm needed to calculate the steps, if any were calculated
+    pub calculation_times: Option<CalculationTimeStatistics>,
+}
+
+/// Moments in the life of a single request.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RequestStatistics {
+    pub request_id: usize,
+    /// First time the request was part of the state
+    pub arrival: usize,
+    /// First time a robot carried the parcel
+    pub pickup: Option<usize>,
+    /// First time the request was no longer part of the state
+    pub delivery: Option<usize>,
+}
+
+impl RequestStatistics {
+    /// Time between arrival and pickup
+    pub fn waiting_time(&self) -> Option<usize> {
+        self.pickup.map(|pickup| pickup - self.arrival)
+    }
+    /// Time between pickup and delivery
+    pub fn delivery_time(&self) -> Option<usize> {
+        match (self.pickup, self.delivery) {
+            (Some(pickup), Some(delivery)) => Some(delivery - pickup),
+            _ => None,
+        }
+    }
+    /// Time between arrival and delivery
+    pub fn latency(&self) -> Option<usize> {
+        self.delivery.map(|delivery| delivery - self.arrival)
+    }
+}
+
+/// Activity of a single robot, counted over the steps it was placed on the plan.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct RobotStatistics {
+    pub robot_id: usize,
+    /// Number of edges traversed
+    pub distance: u64,
+    /// Steps in which the robot moved, carried a parcel, or picked up or delivered one
+    pub busy_time: usize,
+    /// Steps in which the robot stood still without a parcel
+    pub idle_time: usize,
+}
+
+impl RobotStatistics {
+    /// Fraction of the steps on the plan that the robot was busy
+    pub fn utilisation(&self) -> f64 {
+        let active_time = self.busy_time + self.idle_time;
+        if active_time > 0 {
+            self.busy_time as f64 / active_time as f64
+        } else {
+            0 as f64
+        }
+    }
+}
+
+/// Time in seconds that the path algorithm needed to calculate a single step.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CalculationTimeStatistics {
+    pub mean: f64,
+    /// 95th percentile, using the nearest rank
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl CalculationTimeStatistics {
+    pub fn from(calculation_times: &Vec<f64>) -> Option<CalculationTimeStatistics> {
+        if calculation_times.is_empty() {
+            return None;
+        }
+
+        let mut sorted = calculation_times.clone();
+        sorted.sort_by(|first, second| first.partial_cmp(second).unwrap());
+        let p95_rank = (sorted.len() * 95 + 99) / 100;
+
+        Some(CalculationTimeStatistics {
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p95: sorted[p95_rank - 1],
+            max: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+impl Statistics {
+    pub fn from(history: &History) -> Statistics {
+        let requests = Statistics::calculate_request_statistics(history);
+        let robots = Statistics::calculate_robot_statistics(history);
+
+        let makespan = if requests.iter().all(|request| request.delivery.is_some()) {
+            Some(
+                requests
+                    .iter()
+                    .filter_map(|request| request.delivery)
+                    .max()
+                    .unwrap_or(0),
+            )
+        } else {
+            None
+        };
+
+        Statistics {
+            makespan,
+            duration: history.time().max(1) - 1,
+            requests,
+            robots,
+            calculation_times: CalculationTimeStatistics::from(&history.calculation_times),
+        }
+    }
+    fn calculate_request_statistics(history: &History) -> Vec<RequestStatistics> {
+        let mut requests: FnvHashMap<usize, RequestStatistics> = FnvHashMap::default();
+
+        for (time, state) in history.states.iter().enumerate() {
+            for &request_id in state.requests.keys() {
+                requests
+                    .entry(request_id)
+                    .or_insert_with(|| RequestStatistics {
+                        request_id,
+                        arrival: time,
+                        pickup: None,
+                        delivery: None,
+                    });
+            }
+            for robot_state in state.robot_states.iter() {
+                if let Some(parcel) = robot_state.parcel_id {
+                    if let Some(request) = requests.get_mut(&parcel) {
+                        if request.pickup.is_none() {
+                            request.pickup = Some(time);
+                        }
+                    }
+                }
+            }
+            for request in requests.values_mut() {
+                if request.delivery.is_none() && !state.requests.contains_key(&request.request_id)
+                {
+                    request.delivery = Some(time);
+                }
+            }
+        }
+
+        let mut requests = requests.into_iter().map(|(_, r)| r).collect::<Vec<_>>();
+        requests.sort_by_key(|request| request.request_id);
+        requests
+    }
+    fn calculate_robot_statistics(history: &History) -> Vec<RobotStatistics> {
+        let nr_robots = history
+            .states
+            .first()
+            .map_or(0, |state| state.robot_states.len());
+        let mut robots = (0..nr_robots)
+            .map(|robot_id| RobotStatistics {
+                robot_id,
+                distance: 0,
+                busy_time: 0,
+                idle_time: 0,
+            })
+            .collect::<Vec<_>>();
+
+        for (previous, next) in history.states.iter().zip(history.states.iter().skip(1)) {
+            for (before, after) in previous.robot_states.iter().zip(next.robot_states.iter()) {
+                if let (Some(from), Some(to)) = (before.vertex, after.vertex) {
+                    let statistics = &mut robots[before.robot_id];
+                    let distance = from.distance(to);
+                    statistics.distance += distance;
+
+                    if distance > 0 || before.parcel_id.is_some() || after.parcel_id.is_some() {
+                        statistics.busy_time += 1;
+                    } else {
+                        statistics.idle_time += 1;
+                    }
+                }
+            }
+        }
+
+        robots
+    }
+    pub fn nr_delivered(&self) -> usize {
+        self.requests
+            .iter()
+            .filter(|request| request.delivery.is_some())
+            .count()
+    }
+    pub fn total_distance(&self) -> u64 {
+        self.robots.iter().map(|robot| robot.distance).sum()
+    }
+    pub fn total_idle_time(&self) -> usize {
+        self.robots.iter().map(|robot| robot.idle_time).sum()
+    }
+    pub fn mean_waiting_time(&self) -> Option<f64> {
+        Statistics::mean(self.requests.iter().filter_map(RequestStatistics::waiting_time))
+    }
+    pub fn mean_delivery_time(&self) -> Option<f64> {
+        Statistics::mean(self.requests.iter().filter_map(RequestStatistics::delivery_time))
+    }
+    pub fn mean_latency(&self) -> Option<f64> {
+        Statistics::mean(self.requests.iter().filter_map(RequestStatistics::latency))
+    }
+    pub fn mean_utilisation(&self) -> Option<f64> {
+        if self.robots.len() > 0 {
+            Some(
+                self.robots
+                    .iter()
+                    .map(RobotStatistics::utilisation)
+                    .sum::<f64>() / self.robots.len() as f64,
+            )
+        } else {
+            None
+        }
+    }
+    /// Number of deliveries in each consecutive window of `window` time steps, starting at time 0
+    pub fn throughput(&self, window: usize) -> Vec<usize> {
+        debug_assert!(window > 0);
+
+        let mut deliveries = vec![0; self.duration / window + 1];
+        for delivery in self.requests.iter().filter_map(|request| request.delivery) {
+            deliveries[delivery / window] += 1;
+        }
+
+        deliveries
+    }
+    fn mean(values: impl Iterator<Item = usize>) -> Option<f64> {
+        let (count, sum) = values.fold((0, 0), |(count, sum), value| (count + 1, sum + value));
+        if count > 0 {
+            Some(sum as f64 / count as f64)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn optional(value: Option<f64>) -> String {
+            value.map_or("-".to_string(), |value| format!("{:.3}", value))
+        }
+
+        match self.makespan {
+            Some(makespan) => writeln!(f, "makespan: {}", makespan)?,
+            None => writeln!(f, "makespan: -")?,
+        }
+        writeln!(f, "duration: {}", self.duration)?;
+        writeln!(
+            f,
+            "delivered: {}/{}",
+            self.nr_delivered(),
+            self.requests.len()
+        )?;
+        writeln!(f, "mean waiting time: {}", optional(self.mean_waiting_time()))?;
+        writeln!(
+            f,
+            "mean delivery time: {}",
+            optional(self.mean_delivery_time())
+        )?;
+        writeln!(f, "mean latency: {}", optional(self.mean_latency()))?;
+        writeln!(f, "total distance: {}", self.total_distance())?;
+        writeln!(f, "total idle time: {}", self.total_idle_time())?;
+        writeln!(f, "mean utilisation: {}", optional(self.mean_utilisation()))?;
+        match self.calculation_times {
+            Some(CalculationTimeStatistics { mean, p95, max }) => write!(
+                f,
+                "calculation time (s): mean {:.6}, p95 {:.6}, max {:.6}",
+                mean, p95, max
+            ),
+            None => write!(f, "calculation time (s): -"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use fnv::FnvHashMap;
+    use simulation::demand::Request;
+    use simulation::plan::Vertex;
+    use simulation::state::History;
+    use simulation::state::RobotState;
+    use simulation::state::State;
+    use simulation::statistics::CalculationTimeStatistics;
+    use simulation::statistics::RequestStatistics;
+    use simulation::statistics::RobotStatistics;
+
+    fn robot(parcel_id: Option<usize>, x: u64, y: u64) -> Vec<RobotState> {
+        vec![RobotState {
+            robot_id: 0,
+            parcel_id,
+            vertex: Some(Vertex { x, y }),
+        }]
+    }
+
+    /// One robot picks up a parcel at (0, 1), delivers it at (2, 1) and then waits.
+    fn history() -> History {
+        let requests = map![
+            0 => Request {
+                from: Vertex { x: 0, y: 1 },
+                to: Vertex { x: 2, y: 1 },
+            },
+        ];
+
+        History {
+            states: vec![
+                State {
+                    robot_states: robot(None, 0, 0),
+                    requests: FnvHashMap::default(),
+                },
+                State {
+                    robot_states: robot(None, 0, 0),
+                    requests: requests.clone(),
+                },
+                State {
+                    robot_states: robot(None, 0, 1),
+                    requests: requests.clone(),
+                },
+                State {
+                    robot_states: robot(Some(0), 0, 1),
+                    requests: requests.clone(),
+                },
+                State {
+                    robot_states: robot(Some(0), 1, 1),
+                    requests: requests.clone(),
+                },
+                State {
+                    robot_states: robot(Some(0), 2, 1),
+                    requests: requests.clone(),
+                },
+                State {
+                    robot_states: robot(None, 2, 1),
+                    requests: FnvHashMap::default(),
+                },
+                State {
+                    robot_states: robot(None, 2, 1),
+                    requests: FnvHashMap::default(),
+                },
+            ],
+            calculation_times: vec![0.5, 0.1, 0.2, 0.3, 0.4, 0.1, 0.2],
+        }
+    }
+
+    #[test]
+    fn test_requests() {
+        let statistics = history().calculate_statistics();
+
+        assert_eq!(
+            statistics.requests,
+            vec![RequestStatistics {
+                request_id: 0,
+                arrival: 1,
+                pickup: Some(3),
+                delivery: Some(6),
+            }]
+        );
+        assert_eq!(statistics.makespan, Some(6));
+        assert_eq!(statistics.duration, 7);
+        assert_eq!(statistics.mean_waiting_time(), Some(2 as f64));
+        assert_eq!(statistics.mean_delivery_time(), Some(3 as f64));
+        assert_eq!(statistics.mean_latency(), Some(5 as f64));
+    }
+
+    #[test]
+    fn test_robots() {
+        let statistics = history().calculate_statistics();
+
+        assert_eq!(
+            statistics.robots,
+            vec![RobotStatistics {
+                robot_id: 0,
+                distance: 3,
+                busy_time: 5,
+                idle_time: 2,
+            }]
+        );
+        assert_eq!(statistics.total_distance(), 3);
+        assert_eq!(statistics.mean_utilisation(), Some(5 as f64 / 7 as f64));
+    }
+
+    #[test]
+    fn test_throughput() {
+        let statistics = history().calculate_statistics();
+
+        assert_eq!(statistics.throughput(4), vec![0, 1]);
+        assert_eq!(statistics.throughput(10), vec![1]);
+    }
+
+    #[test]
+    fn test_calculation_times() {
+        let statistics = history().calculate_statistics();
+
+        let CalculationTimeStatistics { mean, p95, max } = statistics.calculation_times.unwrap();
+        assert!((mean - 0.257).abs() < 0.001);
+        assert_eq!(p95, 0.5);
+        assert_eq!(max, 0.5);
+
+        let times = (1..101).map(|time| time as f64).collect();
+        assert_eq!(CalculationTimeStatistics::from(&times).unwrap().p95, 95 as f64);
+        assert_eq!(CalculationTimeStatistics::from(&Vec::new()), None);
+    }
+
+    #[test]
+    fn test_undelivered() {
+        let mut history = history();
+        history.states.truncate(5);
+        let statistics = history.calculate_statistics();
+
+        assert_eq!(statistics.makespan, None);
+        assert_eq!(statistics.nr_delivered(), 0);
+        assert_eq!(statistics.requests[0].latency(), None);
+        assert_eq!(statistics.mean_latency(), None);
+    }
+}
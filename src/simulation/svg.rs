@@ -0,0 +1,355 @@
+use fnv::FnvHashMap;
+use simulation::demand::Request;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::state::History;
+use simulation::state::State;
+use std::fmt::Write;
+
+/// Width and height of a single vertex in the drawing, in pixels.
+const CELL_SIZE: u64 = 20;
+/// Space above the plan for the time label.
+const HEADER_HEIGHT: u64 = 24;
+
+/// Draws states of a simulation as SVG images.
+///
+/// The plan is drawn as a grid in which obstacles are dark, sources green and terminals blue.
+/// Robots are circles in a colour of their own, labelled with their id. A robot that carries a
+/// parcel has a square with the parcel id below it and a line to the terminal it is going to.
+/// Open requests are orange dots on their source, with the number of requests waiting there.
+pub struct Renderer<'p> {
+    plan: &'p Plan,
+    x_size: u64,
+    y_size: u64,
+}
+
+impl<'p> Renderer<'p> {
+    pub fn new(plan: &'p Plan) -> Renderer<'p> {
+        let vertices = plan.vertices();
+        let x_size = vertices
+            .iter()
+            .map(|vertex| vertex.x + 1)
+            .max()
+            .unwrap_or(0);
+        let y_size = vertices
+            .iter()
+            .map(|vertex| vertex.y + 1)
+            .max()
+            .unwrap_or(0);
+
+        Renderer {
+            plan,
+            x_size,
+            y_size,
+        }
+    }
+    /// The state at `time` as a single image.
+    pub fn frame(&self, history: &History, time: usize) -> String {
+        let requests = all_requests(history);
+
+        let mut svg = self.open();
+        self.draw_plan(&mut svg);
+        self.draw_state(&mut svg, &history.states[time], time, &requests);
+        svg + "</svg>\n"
+    }
+    /// All states as separate images, indexed by time.
+    pub fn frames(&self, history: &History) -> Vec<String> {
+        let requests = all_requests(history);
+
+        let mut background = self.open();
+        self.draw_plan(&mut background);
+        history
+            .states
+            .iter()
+            .enumerate()
+            .map(|(time, state)| {
+                let mut svg = background.clone();
+                self.draw_state(&mut svg, state, time, &requests);
+                svg + "</svg>\n"
+            })
+            .collect()
+    }
+    /// A single image that shows the states one after the other, `step_duration` seconds each.
+    ///
+    /// Every state is a layer that is only visible during its own step, which keeps the image
+    /// simple at the cost of its size. The animation repeats indefinitely.
+    pub fn animation(&self, history: &History, step_duration: f64) -> String {
+        debug_assert!(step_duration > 0_f64);
+
+        let requests = all_requests(history);
+        let nr_states = history.states.len();
+
+        let mut svg = self.open();
+        self.draw_plan(&mut svg);
+        for (time, state) in history.states.iter().enumerate() {
+            let start = time as f64 / nr_states as f64;
+            let end = (time + 1) as f64 / nr_states as f64;
+            writeln!(svg, "<g visibility=\"hidden\">").unwrap();
+            writeln!(
+                svg,
+                "<animate attributeName=\"visibility\" calcMode=\"discrete\" \
+                 values=\"hidden;visible;hidden\" keyTimes=\"0;{};{}\" dur=\"{}s\" \
+                 repeatCount=\"indefinite\"/>",
+                start,
+                end,
+                nr_states as f64 * step_duration
+            ).unwrap();
+            self.draw_state(&mut svg, state, time, &requests);
+            writeln!(svg, "</g>").unwrap();
+        }
+        svg + "</svg>\n"
+    }
+    fn open(&self) -> String {
+        let width = self.x_size * CELL_SIZE;
+        let height = self.y_size * CELL_SIZE + HEADER_HEIGHT;
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             viewBox=\"0 0 {} {}\" font-family=\"sans-serif\" font-size=\"10\">",
+            width, height, width, height
+        ).unwrap();
+        writeln!(
+            svg,
+            "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>",
+            width, height
+        ).unwrap();
+        svg
+    }
+    fn draw_plan(&self, svg: &mut String) {
+        let sources = self.plan.sources();
+        let terminals = self.plan.terminals();
+
+        writeln!(svg, "<g stroke=\"#cccccc\" stroke-width=\"1\">").unwrap();
+        for y in 0..self.y_size {
+            for x in 0..self.x_size {
+                let vertex = Vertex { x, y };
+                let fill = if !self.plan.contains(&vertex) {
+                    "#404040"
+                } else if sources.contains(&vertex) {
+                    "#c8e6c9"
+                } else if terminals.contains(&vertex) {
+                    "#bbdefb"
+                } else {
+                    "#ffffff"
+                };
+                let (left, top) = self.corner(vertex);
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    left, top, CELL_SIZE, CELL_SIZE, fill
+                ).unwrap();
+            }
+        }
+        writeln!(svg, "</g>").unwrap();
+    }
+    fn draw_state(
+        &self,
+        svg: &mut String,
+        state: &State,
+        time: usize,
+        requests: &FnvHashMap<usize, Request>,
+    ) {
+        writeln!(
+            svg,
+            "<text x=\"4\" y=\"{}\" font-size=\"14\">t = {}, {} open requests</text>",
+            HEADER_HEIGHT - 8,
+            time,
+            state.requests.len()
+        ).unwrap();
+
+        let mut waiting = FnvHashMap::default();
+        for request in state.requests.values() {
+            *waiting.entry(request.from).or_insert(0) += 1;
+        }
+        let mut waiting = waiting.into_iter().collect::<Vec<_>>();
+        waiting.sort_by_key(|&(Vertex { x, y }, _)| (x, y));
+        for (vertex, count) in waiting {
+            let (x, y) = self.center(vertex);
+            writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"5\" fill=\"#ff9800\"/>\
+                 <text x=\"{}\" y=\"{}\" font-size=\"7\" text-anchor=\"middle\">{}</text>",
+                x - 4,
+                y - 4,
+                x - 4,
+                y - 2,
+                count
+            ).unwrap();
+        }
+
+        for robot in &state.robot_states {
+            let vertex = match robot.vertex {
+                Some(vertex) => vertex,
+                None => continue,
+            };
+            let (x, y) = self.center(vertex);
+            let colour = robot_colour(robot.robot_id);
+
+            if let Some(parcel) = robot.parcel_id {
+                if let Some(request) = requests.get(&parcel) {
+                    let (to_x, to_y) = self.center(request.to);
+                    writeln!(
+                        svg,
+                        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" \
+                         stroke-dasharray=\"3,2\"/>",
+                        x, y, to_x, to_y, colour
+                    ).unwrap();
+                }
+            }
+            writeln!(
+                svg,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" stroke=\"black\">\
+                 <title>robot {}</title></circle>\
+                 <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" fill=\"white\">{}</text>",
+                x,
+                y,
+                CELL_SIZE * 2 / 5,
+                colour,
+                robot.robot_id,
+                x,
+                y + 3,
+                robot.robot_id
+            ).unwrap();
+            if let Some(parcel) = robot.parcel_id {
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"8\" fill=\"#8d6e63\">\
+                     <title>parcel {}</title></rect>\
+                     <text x=\"{}\" y=\"{}\" font-size=\"6\" text-anchor=\"middle\" \
+                     fill=\"white\">{}</text>",
+                    x + 2,
+                    y + 2,
+                    parcel,
+                    x + 7,
+                    y + 8,
+                    parcel
+                ).unwrap();
+            }
+        }
+    }
+    /// Upper left corner of the cell of a vertex, the `y` axis of the plan points up.
+    fn corner(&self, Vertex { x, y }: Vertex) -> (u64, u64) {
+        (
+            x * CELL_SIZE,
+            (self.y_size - 1 - y) * CELL_SIZE + HEADER_HEIGHT,
+        )
+    }
+    fn center(&self, vertex: Vertex) -> (u64, u64) {
+        let (left, top) = self.corner(vertex);
+        (left + CELL_SIZE / 2, top + CELL_SIZE / 2)
+    }
+}
+
+/// Every request that is part of any state, such that the destination of a carried parcel is
+/// known after its request was picked up.
+fn all_requests(history: &History) -> FnvHashMap<usize, Request> {
+    let mut requests = FnvHashMap::default();
+    for state in &history.states {
+        for (&id, &request) in &state.requests {
+            requests.insert(id, request);
+        }
+    }
+
+    requests
+}
+
+/// A colour that differs between robots with nearby ids, spreading hues by the golden angle.
+fn robot_colour(robot_id: usize) -> String {
+    format!(
+        "hsl({:.0}, 70%, 40%)",
+        (robot_id as f64 * 137.508) % 360_f64
+    )
+}
+
+#[cfg(test)]
+mod test {
+
+    use fnv::FnvHashMap;
+    use simulation::demand::Request;
+    use simulation::plan::middle_terminals::MiddleTerminals;
+    use simulation::plan::Vertex;
+    use simulation::state::History;
+    use simulation::state::RobotState;
+    use simulation::state::State;
+    use simulation::svg::robot_colour;
+    use simulation::svg::Renderer;
+
+    fn history() -> History {
+        let request = Request {
+            from: Vertex { x: 0, y: 1 },
+            to: Vertex { x: 4, y: 4 },
+        };
+        let mut requests = FnvHashMap::default();
+        requests.insert(0, request);
+
+        History {
+            states: vec![
+                State {
+                    robot_states: vec![
+                        RobotState {
+                            robot_id: 0,
+                            parcel_id: None,
+                            vertex: Some(Vertex { x: 1, y: 1 }),
+                        },
+                        RobotState {
+                            robot_id: 1,
+                            parcel_id: None,
+                            vertex: Some(Vertex { x: 1, y: 2 }),
+                        },
+                    ],
+                    requests,
+                },
+                State {
+                    robot_states: vec![
+                        RobotState {
+                            robot_id: 0,
+                            parcel_id: Some(0),
+                            vertex: Some(Vertex { x: 0, y: 1 }),
+                        },
+                        RobotState {
+                            robot_id: 1,
+                            parcel_id: None,
+                            vertex: None,
+                        },
+                    ],
+                    requests: FnvHashMap::default(),
+                },
+            ],
+            calculation_times: vec![0_f64],
+        }
+    }
+
+    #[test]
+    fn test_frame() {
+        let plan = MiddleTerminals::new(8, 8, 2, 2);
+        let renderer = Renderer::new(&plan);
+        let history = history();
+
+        let first = renderer.frame(&history, 0);
+        assert!(first.starts_with("<svg"));
+        assert!(first.ends_with("</svg>\n"));
+        assert_eq!(first.matches("<rect").count(), 1 + 8 * 8);
+        assert_eq!(first.matches("<title>robot").count(), 2);
+        assert!(first.contains("t = 0, 1 open requests"));
+
+        let second = renderer.frame(&history, 1);
+        assert_eq!(second.matches("<title>robot").count(), 1);
+        assert!(second.contains("<title>parcel 0</title>"));
+        // The line to the destination of the parcel
+        assert_eq!(second.matches("<line").count(), 1);
+
+        assert_eq!(renderer.frames(&history), vec![first, second]);
+    }
+
+    #[test]
+    fn test_animation() {
+        let plan = MiddleTerminals::new(8, 8, 2, 2);
+        let svg = Renderer::new(&plan).animation(&history(), 0.5);
+
+        assert_eq!(svg.matches("<animate ").count(), 2);
+        assert!(svg.contains("keyTimes=\"0;0.5;1\" dur=\"1s\""));
+        assert_ne!(robot_colour(0), robot_colour(1));
+    }
+}
@@ -0,0 +1,379 @@
+//! A trace of a simulation in the JSON Lines format.
+//!
+//! The first line is a header record with the settings and the plan of the simulation:
+//!
+//! ```text
+//! {"type":"header","settings":{"total_time":..,"nr_robots":..,..},"plan":{"vertices":[..],..}}
+//! ```
+//!
+//! It is followed by a step record for every state of the simulation, starting at time 0. A step
+//! contains the robot positions and the parcels they carry, the open requests and, from time 1
+//! on, the instructions that led to the state and the seconds it took to calculate them:
+//!
+//! ```text
+//! {"type":"step","time":1,"robots":[{"robot_id":0,"parcel_id":null,"vertex":{"x":0,"y":2}}],
+//!  "requests":[{"id":0,"from":{"x":0,"y":1},"to":{"x":4,"y":1}}],
+//!  "instructions":{"movements":[..],"placements":[],"removals":[],"robot_removals":[]},
+//!  "calculation_time":0.0001}
+//! ```
+use serde_json;
+use simulation::demand::Request;
+use simulation::observer::Observer;
+use simulation::plan::grid_map::GridMap;
+use simulation::plan::Plan;
+use simulation::plan::Vertex;
+use simulation::settings::Settings;
+use simulation::state::History;
+use simulation::state::RobotState;
+use simulation::state::State;
+use simulation::Instructions;
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    Header {
+        settings: Settings,
+        plan: PlanRecord,
+    },
+    Step {
+        time: usize,
+        robots: Vec<RobotState>,
+        requests: Vec<RequestRecord>,
+        instructions: Option<Instructions>,
+        calculation_time: Option<f64>,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PlanRecord {
+    vertices: Vec<Vertex>,
+    sources: Vec<Vertex>,
+    terminals: Vec<Vertex>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RequestRecord {
+    id: usize,
+    #[serde(flatten)]
+    request: Request,
+}
+
+pub struct TraceWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(writer: W) -> TraceWriter<W> {
+        TraceWriter { writer }
+    }
+    pub fn write_header(&mut self, settings: &Settings, plan: &Plan) -> io::Result<()> {
+        let record = Record::Header {
+            settings: settings.clone(),
+            plan: PlanRecord {
+                vertices: plan.vertices(),
+                sources: plan.sources(),
+                terminals: plan.terminals(),
+            },
+        };
+
+        serde_json::to_writer(&mut self.writer, &record)?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+    /// Writes the state at `time`, together with the instructions that led to it.
+    pub fn write_step(
+        &mut self,
+        time: usize,
+        state: &State,
+        instructions: Option<&Instructions>,
+        calculation_time: Option<f64>,
+    ) -> io::Result<()> {
+        let mut requests = state
+            .requests
+            .iter()
+            .map(|(&id, &request)| RequestRecord { id, request })
+            .collect::<Vec<_>>();
+        requests.sort_by_key(|request| request.id);
+        let record = Record::Step {
+            time,
+            robots: state.robot_states.clone(),
+            requests,
+            instructions: instructions.cloned(),
+            calculation_time,
+        };
+
+        serde_json::to_writer(&mut self.writer, &record)?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Observer for TraceWriter<W> {
+    fn on_initialize(&mut self, settings: &Settings, plan: &Plan, state: &State) -> io::Result<()> {
+        self.write_header(settings, plan)?;
+        self.write_step(0, state, None, None)
+    }
+    fn on_step(
+        &mut self,
+        time: usize,
+        instructions: &Instructions,
+        state: &State,
+        calculation_time: f64,
+    ) {
+        // A trace that can't be written doesn't invalidate the simulation itself
+        let _ = self.write_step(time, state, Some(instructions), Some(calculation_time));
+    }
+}
+
+/// A simulation as read back from a trace.
+pub struct Trace {
+    pub settings: Settings,
+    pub plan: GridMap,
+    pub history: History,
+    /// Instructions that led to each state after the first one
+    pub instructions: Vec<Instructions>,
+}
+
+/// Reads a trace as written by `TraceWriter`.
+pub fn read(reader: impl BufRead) -> Result<Trace, TraceError> {
+    let mut records = reader
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(number, line)| {
+            let line = line.map_err(|error| {
+                TraceError::new(None, format!("Can't read the trace: {}", error))
+            })?;
+            serde_json::from_str::<Record>(&line)
+                .map(|record| (number, record))
+                .map_err(|error| TraceError::new(Some(number), error.to_string()))
+        });
+
+    let (settings, plan) = match records.next() {
+        Some(Ok((_, Record::Header { settings, plan }))) => (settings, plan),
+        Some(Ok((number, _))) => {
+            return Err(TraceError::new(
+                Some(number),
+                "Expected the header record".to_string(),
+            ))
+        }
+        Some(Err(error)) => return Err(error),
+        None => return Err(TraceError::new(None, "The trace is empty".to_string())),
+    };
+    let plan = GridMap::new(plan.vertices, plan.sources, plan.terminals)
+        .map_err(|error| TraceError::new(Some(0), error.message().clone()))?;
+
+    let mut history = History::empty();
+    let mut all_instructions = Vec::new();
+    for record in records {
+        let (number, record) = record?;
+        let (time, robots, requests, instructions, calculation_time) = match record {
+            Record::Step {
+                time,
+                robots,
+                requests,
+                instructions,
+                calculation_time,
+            } => (time, robots, requests, instructions, calculation_time),
+            Record::Header { .. } => {
+                return Err(TraceError::new(
+                    Some(number),
+                    "Only the first record can be a header".to_string(),
+                ))
+            }
+        };
+
+        if time != history.time() {
+            return Err(TraceError::new(
+                Some(number),
+                format!(
+                    "Expected the step at time {}, found {}",
+                    history.time(),
+                    time
+                ),
+            ));
+        }
+        if robots.len() != settings.nr_robots {
+            return Err(TraceError::new(
+                Some(number),
+                format!(
+                    "Expected {} robots, found {}",
+                    settings.nr_robots,
+                    robots.len()
+                ),
+            ));
+        }
+        match (time, instructions, calculation_time) {
+            (0, None, None) => (),
+            (0, _, _) => {
+                return Err(TraceError::new(
+                    Some(number),
+                    "The first step can't have instructions".to_string(),
+                ))
+            }
+            (_, Some(instructions), Some(calculation_time)) => {
+                all_instructions.push(instructions);
+                history.calculation_times.push(calculation_time);
+            }
+            _ => {
+                return Err(TraceError::new(
+                    Some(number),
+                    "The step is missing its instructions or calculation time".to_string(),
+                ))
+            }
+        }
+
+        history.states.push(State {
+            robot_states: robots,
+            requests: requests
+                .into_iter()
+                .map(|RequestRecord { id, request }| (id, request))
+                .collect(),
+        });
+    }
+
+    Ok(Trace {
+        settings,
+        plan,
+        history,
+        instructions: all_instructions,
+    })
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct TraceError {
+    /// Line of the trace, counting from zero, if the error is about a specific line
+    line: Option<usize>,
+    message: String,
+}
+
+impl TraceError {
+    fn new(line: Option<usize>, message: String) -> TraceError {
+        TraceError { line, message }
+    }
+    pub fn line(&self) -> Option<usize> {
+        self.line
+    }
+    pub fn message(&self) -> &String {
+        &self.message
+    }
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "Line {}: {}", line + 1, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use algorithm::assignment::greedy_makespan::GreedyMakespan;
+    use algorithm::path::greedy_shortest_paths::GreedyShortestPaths;
+    use algorithm::solver::SolverSettings;
+    use simulation::demand::uniform::Uniform;
+    use simulation::demand::Demand;
+    use simulation::placement::Placement;
+    use simulation::plan::middle_terminals::MiddleTerminals;
+    use simulation::plan::Plan;
+    use simulation::settings::Settings;
+    use simulation::simulation::Simulation;
+    use simulation::state::History;
+    use simulation::trace::read;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    use std::fs::File;
+    use std::io::BufReader;
+    use std::path::PathBuf;
+    use std::process;
+
+    fn simulate(path: &PathBuf, total_time: usize) -> (Settings, History) {
+        let plan = MiddleTerminals::new(10, 10, 3, 3);
+        let settings = Settings {
+            total_time,
+            nr_robots: 3,
+            nr_requests: 5,
+            output_file: Some(path.to_str().unwrap().to_string()),
+            forbid_following: false,
+            placement: Placement::Random([0; 32]),
+            solver: SolverSettings::default(),
+        };
+        let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+        let path_algorithm = Box::new(GreedyShortestPaths::new(
+            &plan,
+            &settings,
+            assignment_algorithm,
+        ));
+        let demand = Box::new(<Uniform as Demand>::create([0; 32]));
+        let mut simulation = Simulation::new(path_algorithm, &plan, demand, &settings);
+        simulation.initialize().ok().unwrap();
+        let history = simulation.run().ok().unwrap();
+
+        (settings, history)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_dir().join(format!("disjoint_trace_{}", process::id()));
+        let (settings, history) = simulate(&path, 100);
+
+        let trace = read(BufReader::new(File::open(&path).unwrap())).unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(
+            trace.settings,
+            Settings {
+                output_file: None,
+                ..settings
+            }
+        );
+        assert_eq!(
+            trace.plan.vertices().len(),
+            MiddleTerminals::new(10, 10, 3, 3).vertices().len()
+        );
+        assert_eq!(trace.instructions.len(), history.time() - 1);
+        assert_eq!(trace.history, history);
+    }
+
+    #[test]
+    fn test_overwrite() {
+        // A shorter trace replaces a longer one in the same file, without leaving its end behind
+        let path = temp_dir().join(format!("disjoint_trace_overwrite_{}", process::id()));
+        let (_, long_history) = simulate(&path, 100);
+        let (_, history) = simulate(&path, 5);
+        assert!(history.time() < long_history.time());
+
+        let trace = read(BufReader::new(File::open(&path).unwrap())).unwrap();
+        remove_file(&path).unwrap();
+
+        assert_eq!(trace.history, history);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(read("".as_bytes()).is_err());
+
+        let header = r#"{"type":"header","settings":{"total_time":5,"nr_robots":1,"nr_requests":0,"forbid_following":false,"placement":"Sources"},"plan":{"vertices":[{"x":0,"y":0},{"x":1,"y":0}],"sources":[{"x":0,"y":0}],"terminals":[{"x":1,"y":0}]}}"#;
+        let step = r#"{"type":"step","time":0,"robots":[{"robot_id":0,"parcel_id":3,"vertex":{"x":0,"y":0}}],"requests":[{"id":4,"from":{"x":0,"y":0},"to":{"x":1,"y":0}}],"instructions":null,"calculation_time":null}"#;
+        let trace = read(format!("{}\n{}\n", header, step).as_bytes()).unwrap();
+        assert_eq!(trace.history.time(), 1);
+        assert_eq!(trace.history.last_robot_state(0).parcel_id, Some(3));
+        assert!(trace.history.last_state().requests.contains_key(&4));
+        assert!(trace.history.calculation_times.is_empty());
+
+        let error = read(format!("{}\n{}\n{}\n", header, step, step).as_bytes())
+            .err()
+            .unwrap();
+        assert_eq!(error.line(), Some(2));
+        let error = read(format!("{}\n{{\n", header).as_bytes()).err().unwrap();
+        assert_eq!(error.line(), Some(1));
+        assert!(read(step.as_bytes()).is_err());
+    }
+}
@@ -1,9 +1,14 @@
 use algorithm::assignment::greedy_makespan::GreedyMakespan;
 use algorithm::assignment::multiple_vehicle_ilp::MultiVehicleIlpFormulation;
+use algorithm::path::cbs::ConflictBasedSearch;
 use algorithm::path::greedy_shortest_paths::GreedyShortestPaths;
 use algorithm::path::ilp::ILPSteps;
+use algorithm::path::token_passing::TokenPassing;
+use algorithm::path::whca::WindowedCooperativeAStar;
+use algorithm::solver::SolverSettings;
 use simulation::demand::uniform::Uniform;
 use simulation::demand::Demand;
+use simulation::placement::Placement;
 use simulation::plan::e_plan::EPlan;
 use simulation::plan::middle_terminals::MiddleTerminals;
 use simulation::plan::one_three_rectangle::OneThreeRectangle;
@@ -18,6 +23,9 @@ fn multiple_vehicle_ilp_path() {
         nr_robots: 1,
         nr_requests: 1,
         output_file: None,
+        forbid_following: false,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
     };
     let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
     let path_algorithm = Box::new(GreedyShortestPaths::new(
@@ -52,6 +60,9 @@ fn multiple_vehicle_ilp_path_large() {
         nr_requests: 10,
         //        output_file: Some("/tmp/disjoint".to_string()),
         output_file: None,
+        forbid_following: false,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
     };
     let assignment_algorithm = Box::new(MultiVehicleIlpFormulation::new(&plan, &settings));
     let path_algorithm = Box::new(ILPSteps::new(&plan, &settings, assignment_algorithm, 2));
@@ -82,6 +93,9 @@ fn e_plan_multiple_vehicle_ilp_path_large() {
         nr_requests: 10,
         output_file: Some("/tmp/disjoint".to_string()),
         //        output_file: None,
+        forbid_following: false,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
     };
     let assignment_algorithm = Box::new(MultiVehicleIlpFormulation::new(&plan, &settings));
     let path_algorithm = Box::new(ILPSteps::new(&plan, &settings, assignment_algorithm, 2));
@@ -112,6 +126,9 @@ fn e_plan_greedy() {
         nr_requests: 10,
         //        output_file: Some("/tmp/disjoint".to_string()),
         output_file: None,
+        forbid_following: false,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
     };
     let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
     let path_algorithm = Box::new(GreedyShortestPaths::new(
@@ -136,3 +153,173 @@ fn e_plan_greedy() {
     };
     assert!(is_ok);
 }
+
+#[test]
+fn conflict_based_search() {
+    let plan = MiddleTerminals::new(10, 10, 3, 3);
+    let settings = Settings {
+        total_time: 500,
+        nr_robots: 5,
+        nr_requests: 10,
+        output_file: None,
+        forbid_following: true,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
+    };
+    let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+    let path_algorithm = Box::new(ConflictBasedSearch::new(
+        &plan,
+        &settings,
+        assignment_algorithm,
+    ));
+    let demand = Box::new(<Uniform as Demand>::create([0; 32]));
+
+    let mut simulation = Simulation::new(path_algorithm, &plan, demand, &settings);
+    simulation.initialize().ok().unwrap();
+    let result = simulation.run();
+
+    match result {
+        // Every request is delivered well within the time
+        Ok(history) => assert!(history.last_state().requests.is_empty()),
+        Err(error) => panic!(
+            "{:?}: {:?} at time {}",
+            error.message(),
+            error.instruction(),
+            error.time()
+        ),
+    }
+}
+
+#[test]
+fn enhanced_conflict_based_search() {
+    let plan = MiddleTerminals::new(20, 20, 5, 5);
+    let settings = Settings {
+        total_time: 500,
+        nr_robots: 10,
+        nr_requests: 20,
+        output_file: None,
+        forbid_following: true,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
+    };
+    let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+    let path_algorithm = Box::new(
+        ConflictBasedSearch::new(&plan, &settings, assignment_algorithm).with_suboptimality(1.5),
+    );
+    let demand = Box::new(<Uniform as Demand>::create([0; 32]));
+
+    let mut simulation = Simulation::new(path_algorithm, &plan, demand, &settings);
+    simulation.initialize().ok().unwrap();
+    let result = simulation.run();
+
+    match result {
+        // Every request is delivered well within the time
+        Ok(history) => assert!(history.last_state().requests.is_empty()),
+        Err(error) => panic!(
+            "{:?}: {:?} at time {}",
+            error.message(),
+            error.instruction(),
+            error.time()
+        ),
+    }
+}
+
+#[test]
+fn windowed_cooperative_a_star() {
+    let plan = MiddleTerminals::new(20, 20, 5, 5);
+    let settings = Settings {
+        total_time: 500,
+        nr_robots: 10,
+        nr_requests: 20,
+        output_file: None,
+        forbid_following: true,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
+    };
+    let assignment_algorithm = Box::new(GreedyMakespan::new(&plan, &settings));
+    let path_algorithm = Box::new(WindowedCooperativeAStar::new(
+        &plan,
+        &settings,
+        assignment_algorithm,
+        8,
+        4,
+    ));
+    let demand = Box::new(<Uniform as Demand>::create([0; 32]));
+
+    let mut simulation = Simulation::new(path_algorithm, &plan, demand, &settings);
+    simulation.initialize().ok().unwrap();
+    let result = simulation.run();
+
+    match result {
+        // Every request is delivered well within the time
+        Ok(history) => assert!(history.last_state().requests.is_empty()),
+        Err(error) => panic!(
+            "{:?}: {:?} at time {}",
+            error.message(),
+            error.instruction(),
+            error.time()
+        ),
+    }
+}
+
+#[test]
+fn token_passing() {
+    let plan = MiddleTerminals::new(20, 20, 5, 5);
+    let settings = Settings {
+        total_time: 500,
+        nr_robots: 10,
+        nr_requests: 20,
+        output_file: None,
+        forbid_following: true,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
+    };
+    let path_algorithm = Box::new(TokenPassing::new(&plan, &settings));
+    let demand = Box::new(<Uniform as Demand>::create([0; 32]));
+
+    let mut simulation = Simulation::new(path_algorithm, &plan, demand, &settings);
+    simulation.initialize().ok().unwrap();
+    let result = simulation.run();
+
+    match result {
+        // Every request is delivered well within the time
+        Ok(history) => assert!(history.last_state().requests.is_empty()),
+        Err(error) => panic!(
+            "{:?}: {:?} at time {}",
+            error.message(),
+            error.instruction(),
+            error.time()
+        ),
+    }
+}
+
+#[test]
+fn token_passing_with_task_swaps() {
+    let plan = MiddleTerminals::new(20, 20, 5, 5);
+    let settings = Settings {
+        total_time: 500,
+        nr_robots: 10,
+        nr_requests: 20,
+        output_file: None,
+        forbid_following: true,
+        placement: Placement::Random([0; 32]),
+        solver: SolverSettings::default(),
+    };
+    let path_algorithm = Box::new(TokenPassing::new(&plan, &settings).with_task_swaps());
+    let demand = Box::new(<Uniform as Demand>::create([0; 32]));
+
+    let mut simulation = Simulation::new(path_algorithm, &plan, demand, &settings);
+    simulation.initialize().ok().unwrap();
+    let result = simulation.run();
+
+    match result {
+        // Every request is delivered well within the time
+        Ok(history) => assert!(history.last_state().requests.is_empty()),
+        Err(error) => panic!(
+            "{:?}: {:?} at time {}",
+            error.message(),
+            error.instruction(),
+            error.time()
+        ),
+    }
+}